@@ -0,0 +1,194 @@
+//! Procedural macros for `actix-cbor`, re-exported by that crate with the `macros` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized, parse_macro_input, parse_quote, DeriveInput, Error, FnArg, ItemFn, LitInt,
+    LitStr, ReturnType, Token, Type,
+};
+
+/// Whether `ty` is spelled as a `Result`, such as `Result<T, E>` or `actix_web::Result<T>`.
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Result"),
+        _ => false,
+    }
+}
+
+/// Turn a plain `async fn` handler into an actix-web handler decoding its last argument from a
+/// CBOR body and encoding its output as a CBOR response.
+///
+/// See the documentation of `actix_cbor::cbor_handler`.
+#[proc_macro_attribute]
+pub fn cbor_handler(args: TokenStream, item: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        let msg = "#[cbor_handler] takes no arguments";
+        return Error::new(Span::call_site(), msg).to_compile_error().into();
+    }
+    let handler = parse_macro_input!(item as ItemFn);
+    match expand_handler(handler) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_handler(handler: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &handler.sig;
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(
+            sig.fn_token,
+            "#[cbor_handler] requires an async fn",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "#[cbor_handler] does not support generic handlers",
+        ));
+    }
+
+    let mut params = Vec::new();
+    let mut values = Vec::new();
+    let last = sig.inputs.len().saturating_sub(1);
+    for (index, input) in sig.inputs.iter().enumerate() {
+        let ty = match input {
+            FnArg::Typed(arg) => &arg.ty,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new_spanned(
+                    receiver,
+                    "#[cbor_handler] does not support methods",
+                ))
+            }
+        };
+        let name = format_ident!("__arg{}", index);
+        if index == last {
+            params.push(quote! { #name: ::actix_cbor::Cbor<#ty> });
+            values.push(quote! { #name.into_inner() });
+        } else {
+            params.push(quote! { #name: #ty });
+            values.push(quote! { #name });
+        }
+    }
+
+    let respond = match &sig.output {
+        ReturnType::Type(_, ty) if is_result(ty) => quote! { respond_result },
+        _ => quote! { respond_value },
+    };
+
+    let attrs = &handler.attrs;
+    let vis = &handler.vis;
+    let name = &sig.ident;
+    let inputs = &sig.inputs;
+    let output = &sig.output;
+    let block = &handler.block;
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis async fn #name(
+            __req: ::actix_cbor::__private::HttpRequest,
+            #(#params),*
+        ) -> ::actix_cbor::__private::HttpResponse {
+            async fn __cbor_handler(#inputs) #output #block
+
+            let output = __cbor_handler(#(#values),*).await;
+            ::actix_cbor::__private::#respond(output, &__req)
+        }
+    })
+}
+
+/// Derive `actix_web::Responder` for a `Serialize` type, answering it as CBOR.
+///
+/// See the documentation of `actix_cbor::CborResponder`.
+#[proc_macro_derive(CborResponder, attributes(cbor_responder))]
+pub fn derive_cbor_responder(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match expand_responder(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Whether `name` is a valid header name token.
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Whether `value` is a valid header value made of visible ASCII characters and spaces.
+fn is_header_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b == b' ' || b == b'\t' || b.is_ascii_graphic())
+}
+
+fn expand_responder(mut input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut status = None;
+    let mut headers = Vec::new();
+
+    for attr in &input.attrs {
+        if !attr.path().is_ident("cbor_responder") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("status") {
+                let lit: LitInt = meta.value()?.parse()?;
+                match lit.base10_parse::<u16>() {
+                    Ok(code) if (100..1000).contains(&code) => status = Some(code),
+                    _ => return Err(Error::new_spanned(lit, "invalid status code")),
+                }
+                Ok(())
+            } else if meta.path.is_ident("header") {
+                let content;
+                parenthesized!(content in meta.input);
+                let name: LitStr = content.parse()?;
+                content.parse::<Token![,]>()?;
+                let value: LitStr = content.parse()?;
+                if !is_header_name(&name.value()) {
+                    return Err(Error::new_spanned(name, "invalid header name"));
+                }
+                if !is_header_value(&value.value()) {
+                    return Err(Error::new_spanned(value, "invalid header value"));
+                }
+                headers.push(quote! { (#name, #value) });
+                Ok(())
+            } else {
+                Err(meta.error("expected `status = ...` or `header(\"name\", \"value\")`"))
+            }
+        })?;
+    }
+
+    let status = match status {
+        Some(code) => quote! { ::core::option::Option::Some(#code) },
+        None => quote! { ::core::option::Option::None },
+    };
+
+    input
+        .generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { Self: ::actix_cbor::__private::Serialize + 'static });
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::actix_cbor::CborResponder for #name #ty_generics #where_clause {}
+
+        impl #impl_generics ::actix_cbor::__private::Responder for #name #ty_generics #where_clause {
+            type Body = ::actix_cbor::__private::BoxBody;
+
+            fn respond_to(
+                self,
+                req: &::actix_cbor::__private::HttpRequest,
+            ) -> ::actix_cbor::__private::HttpResponse {
+                ::actix_cbor::__private::respond_derived(self, req, #status, &[#(#headers),*])
+            }
+        }
+    })
+}
@@ -0,0 +1,76 @@
+use actix_web::{
+    error::ParseError,
+    http::header::{
+        Accept, Header, HeaderName, HeaderValue, InvalidHeaderValue, Quality, TryIntoHeaderValue,
+        ACCEPT,
+    },
+    HttpMessage,
+};
+
+use crate::http_request_ext::rank;
+
+/// Typed `Accept` header, telling whether and how much CBOR responses are acceptable.
+///
+/// A header without media ranges accepts any media type.
+///
+/// # Example
+/// ```
+/// use actix_cbor::AcceptCbor;
+/// use actix_web::{http::header::Header, HttpRequest};
+///
+/// async fn index(req: HttpRequest) -> &'static str {
+///     match AcceptCbor::parse(&req) {
+///         Ok(accept) if accept.prefers_cbor_over("application", "json") => "cbor",
+///         _ => "json",
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcceptCbor(pub Accept);
+
+impl AcceptCbor {
+    /// Quality of CBOR responses, given by the most specific media range matching
+    /// `application/cbor`, or zero when none does.
+    pub fn quality(&self) -> Quality {
+        if self.0 .0.is_empty() {
+            return Quality::MAX;
+        }
+        rank(&self.0, "application", "cbor").map_or(Quality::ZERO, |(quality, ..)| quality)
+    }
+
+    /// Whether CBOR responses are acceptable.
+    pub fn accepts_cbor(&self) -> bool {
+        self.quality() > Quality::ZERO
+    }
+
+    /// Whether CBOR ranks strictly above `type_/subtype`. Ties go to the other media type, so a
+    /// header without media ranges does not prefer CBOR.
+    pub fn prefers_cbor_over(&self, type_: &str, subtype: &str) -> bool {
+        let cbor = rank(&self.0, "application", "cbor");
+        cbor.is_some_and(|(quality, ..)| quality > Quality::ZERO)
+            && cbor > rank(&self.0, type_, subtype)
+    }
+
+    /// Deconstruct to the `Accept` header
+    pub fn into_inner(self) -> Accept {
+        self.0
+    }
+}
+
+impl Header for AcceptCbor {
+    fn name() -> HeaderName {
+        ACCEPT
+    }
+
+    fn parse<M: HttpMessage>(msg: &M) -> Result<Self, ParseError> {
+        Accept::parse(msg).map(AcceptCbor)
+    }
+}
+
+impl TryIntoHeaderValue for AcceptCbor {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_value(self) -> Result<HeaderValue, Self::Error> {
+        self.0.try_into_value()
+    }
+}
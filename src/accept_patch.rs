@@ -0,0 +1,115 @@
+use std::task::{Context, Poll};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    error::InternalError,
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+};
+use futures_util::future::{ok, FutureExt, LocalBoxFuture, Ready};
+
+use crate::MERGE_PATCH_CBOR_CONTENT_TYPE;
+
+/// Name of the header advertising the patch formats accepted by a resource.
+pub const ACCEPT_PATCH: HeaderName = HeaderName::from_static("accept-patch");
+
+/// Add the `Accept-Patch` header to `err` when it rejects the content type of a patch.
+pub(crate) fn with_accept_patch(err: actix_web::Error) -> actix_web::Error {
+    let mut response = err.error_response();
+    if response.status() != StatusCode::UNSUPPORTED_MEDIA_TYPE {
+        return err;
+    }
+    response.headers_mut().insert(
+        ACCEPT_PATCH,
+        HeaderValue::from_static(MERGE_PATCH_CBOR_CONTENT_TYPE),
+    );
+    InternalError::from_response(err, response).into()
+}
+
+/// Middleware advertising CBOR merge patches through the `Accept-Patch` header.
+///
+/// The header is added to successful responses and to `OPTIONS` responses; patches rejected
+/// for their content type carry it as well, as they are answered by the extractor.
+///
+/// Wrap the resources using the [`CborPatch`](struct.CborPatch.html) extractor so that clients
+/// can discover the supported patch format.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{AcceptPatch, CborPatch};
+/// use actix_web::{web, App};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn update(patch: CborPatch<User>) -> String {
+///     format!("{:?}", patch)
+/// }
+///
+/// let app = App::new().service(
+///     web::resource("/users/{id}")
+///         .wrap(AcceptPatch)
+///         .route(web::patch().to(update)),
+/// );
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AcceptPatch;
+
+impl<S, B> Transform<S, ServiceRequest> for AcceptPatch
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AcceptPatchMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AcceptPatchMiddleware { service })
+    }
+}
+
+/// Service created by the [`AcceptPatch`](struct.AcceptPatch.html) middleware.
+pub struct AcceptPatchMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AcceptPatchMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let options = req.method() == Method::OPTIONS;
+        self.service
+            .call(req)
+            .map(move |res| {
+                res.map(|mut res| {
+                    if options || res.status().is_success() {
+                        res.headers_mut().insert(
+                            ACCEPT_PATCH,
+                            HeaderValue::from_static(MERGE_PATCH_CBOR_CONTENT_TYPE),
+                        );
+                    }
+                    res
+                })
+            })
+            .boxed_local()
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+
+use crate::{extract_cbor, CborConfig};
+
+/// Extractor decoding a CBOR body into an `Arc<T>`, as [`Cbor`](struct.Cbor.html) would decode a
+/// `T`.
+///
+/// Cloning only bumps a reference count, so the payload is shared with spawned tasks without
+/// copying it.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborArc;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Event {
+///     name: String,
+/// }
+///
+/// async fn publish(event: CborArc<Event>) -> &'static str {
+///     for _ in 0..3 {
+///         let event = event.clone();
+///         actix_web::rt::spawn(async move { log::info!("sending {}", event.name) });
+///     }
+///     "published"
+/// }
+/// ```
+pub struct CborArc<T>(pub Arc<T>);
+
+impl<T> CborArc<T> {
+    /// Deconstruct to the shared value
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Clone for CborArc<T> {
+    fn clone(&self) -> Self {
+        CborArc(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for CborArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> fmt::Debug for CborArc<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborArc: {:?}", self.0)
+    }
+}
+
+impl<T> FromRequest for CborArc<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req);
+        let strict = config.deny_unknown_fields == Some(true);
+        extract_cbor(req, payload, &config, config.limit_for(req), strict, None)
+            .map(|res| res.map(|value| CborArc(Arc::new(value))))
+            .boxed_local()
+    }
+}
@@ -0,0 +1,120 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::web::{Bytes, BytesMut};
+use futures_util::stream::Stream;
+
+/// Bound on the bytes and items a streaming responder hands to the connection at once.
+///
+/// The source is only polled when the connection asks for more of the body, which actix-web
+/// does once the previous chunk was written out, so a slow client pauses the source instead of
+/// making the server buffer for it. Frames ready at the same time are coalesced into chunks of
+/// at most the high watermark, and bigger frames are split, so no more than that many bytes are
+/// ever outstanding beyond the connection's own write buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermarks {
+    high_bytes: usize,
+    high_items: usize,
+}
+
+impl Watermarks {
+    /// Hand at most `high` bytes to the connection at once.
+    pub fn bytes(high: usize) -> Self {
+        Self {
+            high_bytes: high.max(1),
+            high_items: usize::MAX,
+        }
+    }
+
+    /// Additionally coalesce at most `high` items into a chunk.
+    pub fn items(mut self, high: usize) -> Self {
+        self.high_items = high.max(1);
+        self
+    }
+}
+
+impl Default for Watermarks {
+    /// 64KiB.
+    fn default() -> Self {
+        Self::bytes(65_536)
+    }
+}
+
+/// Body stream pulling frames from its source only when polled, in chunks bounded by the
+/// configured watermarks.
+pub(crate) struct Paced<S, E> {
+    source: S,
+    watermarks: Watermarks,
+    /// Rest of a frame which did not fit in the previous chunk, sent before polling the source
+    pending: Option<Bytes>,
+    done: bool,
+    err: Option<E>,
+}
+
+impl<S, E> Paced<S, E> {
+    pub(crate) fn new(source: S, watermarks: Watermarks) -> Self {
+        Self {
+            source,
+            watermarks,
+            pending: None,
+            done: false,
+            err: None,
+        }
+    }
+}
+
+impl<S, E> Stream for Paced<S, E>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let high = this.watermarks.high_bytes;
+        let mut chunk = BytesMut::new();
+        let mut items = 0;
+
+        while chunk.len() < high && items < this.watermarks.high_items {
+            let mut frame = match this.pending.take() {
+                Some(frame) => frame,
+                None if this.done => break,
+                None => match Pin::new(&mut this.source).poll_next(cx) {
+                    Poll::Ready(Some(Ok(frame))) => frame,
+                    Poll::Ready(Some(Err(e))) => {
+                        // deliver what was produced before the failure first
+                        this.err = Some(e);
+                        this.done = true;
+                        break;
+                    }
+                    Poll::Ready(None) => {
+                        this.done = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                },
+            };
+
+            let room = high - chunk.len();
+            if frame.len() > room {
+                if !chunk.is_empty() {
+                    this.pending = Some(frame);
+                    break;
+                }
+                this.pending = Some(frame.split_off(room));
+            }
+            chunk.extend_from_slice(&frame);
+            items += 1;
+        }
+
+        if !chunk.is_empty() {
+            return Poll::Ready(Some(Ok(chunk.freeze())));
+        }
+        match this.err.take() {
+            Some(e) => Poll::Ready(Some(Err(e))),
+            None if this.done => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
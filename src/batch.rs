@@ -0,0 +1,162 @@
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+use crate::scan::array_items;
+use crate::untagged;
+use crate::{
+    extraction_error, is_cbor_content_type, CborBody, CborConfig, CborError, CborPayloadError,
+};
+
+/// Outcome of every element of a batch, or the error rejecting the body as a whole.
+pub(crate) type BatchResults<T> = Result<Vec<Result<T, CborError>>, CborPayloadError>;
+
+/// Bound of the items of [`CborBatch`](struct.CborBatch.html) and
+/// [`CborSeqVec`](struct.CborSeqVec.html): `Send` with the `rayon` feature, which decodes large
+/// bodies on other threads, any type otherwise.
+#[cfg(feature = "rayon")]
+pub trait MaybeSend: Send {}
+
+#[cfg(feature = "rayon")]
+impl<T: Send> MaybeSend for T {}
+
+/// Bound of the items of [`CborBatch`](struct.CborBatch.html) and
+/// [`CborSeqVec`](struct.CborSeqVec.html): `Send` with the `rayon` feature, which decodes large
+/// bodies on other threads, any type otherwise.
+#[cfg(not(feature = "rayon"))]
+pub trait MaybeSend {}
+
+#[cfg(not(feature = "rayon"))]
+impl<T> MaybeSend for T {}
+
+/// Decode a top-level CBOR array, keeping the outcome of every element.
+pub(crate) fn decode_batch<T: DeserializeOwned>(body: &[u8]) -> BatchResults<T> {
+    let items = match array_items(body) {
+        Some(items) => items,
+        // let serde_cbor tell what is wrong with the body
+        None => {
+            return Err(serde_cbor::from_slice::<Vec<IgnoredAny>>(body)
+                .err()
+                .map_or(CborPayloadError::Malformed(0), Into::into))
+        }
+    };
+    Ok(items
+        .into_iter()
+        .map(|item| untagged::from_slice(item).map_err(CborError::from))
+        .collect())
+}
+
+/// Extractor for a CBOR array of items that keeps going past elements which fail to decode.
+///
+/// The body must still be a well-formed CBOR array within the configured limit, otherwise the
+/// request is rejected as a whole. Each element is decoded on its own, straight from its bytes.
+///
+/// With the `rayon` feature, bodies which may hold 1024 elements or more are decoded off the
+/// async runtime, on the blocking thread pool, and arrays of 1024 elements or more are split at
+/// element boundaries to be decoded in parallel on the rayon thread pool. Items must then be
+/// `Send`, see [`MaybeSend`](trait.MaybeSend.html).
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborBatch;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Reading {
+///     value: f64,
+/// }
+///
+/// async fn ingest(batch: CborBatch<Reading>) -> String {
+///     let (readings, failures) = batch.into_parts();
+///     format!("accepted {}, rejected {}", readings.len(), failures.len())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CborBatch<T> {
+    results: Vec<Result<T, CborError>>,
+}
+
+impl<T> CborBatch<T> {
+    /// Successfully decoded items, in request order.
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.results.iter().filter_map(|res| res.as_ref().ok())
+    }
+
+    /// Index and error of every element that failed to decode.
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &CborError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, res)| res.as_ref().err().map(|e| (idx, e)))
+    }
+
+    /// Whether every element decoded successfully.
+    pub fn is_complete(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
+
+    /// Number of elements in the request.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Whether the request contained no elements.
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Split into the decoded items and the `(index, error)` failures.
+    pub fn into_parts(self) -> (Vec<T>, Vec<(usize, CborError)>) {
+        let mut items = Vec::new();
+        let mut failures = Vec::new();
+
+        for (idx, res) in self.results.into_iter().enumerate() {
+            match res {
+                Ok(item) => items.push(item),
+                Err(e) => failures.push((idx, e)),
+            }
+        }
+
+        (items, failures)
+    }
+
+    /// Outcome of every element, in request order.
+    pub fn into_results(self) -> Vec<Result<T, CborError>> {
+        self.results
+    }
+}
+
+impl<T> FromRequest for CborBatch<T>
+where
+    T: DeserializeOwned + MaybeSend + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+
+        #[cfg(feature = "rayon")]
+        let decode = crate::parallel::decode_batch_par::<T>;
+        #[cfg(not(feature = "rayon"))]
+        let decode = decode_batch::<T>;
+
+        let body = CborBody::with_decoder(
+            req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, config.content_type.as_ref()),
+            decode,
+        )
+        .limit(config.limit_for(req));
+        #[cfg(feature = "rayon")]
+        let body = body.off_thread();
+
+        body.map(move |res| match res {
+            Err(e) => Err(extraction_error(&config, e, &req2)),
+            Ok(results) => Ok(CborBatch { results }),
+        })
+        .boxed_local()
+    }
+}
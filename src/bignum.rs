@@ -0,0 +1,186 @@
+//! Serde helpers encoding `i128` and `u128` values beyond the 64-bit range of CBOR integers as
+//! bignums (RFC 8949, tags 2 and 3), available with the `tags` feature.
+//!
+//! serde_cbor fails to serialize such values, and can only write and read the bignum tags when
+//! built with its `tags` feature. Values within the 64-bit range are still plain integers, so
+//! the encoding only changes for values which could not be encoded before.
+//!
+//! Each field opts in with `#[serde(with = "bignum")]`: other 128-bit fields keep failing
+//! beyond the 64-bit range, and enabling the feature does not change how the rest of a payload
+//! is decoded.
+//!
+//! # Example
+//! ```
+//! use actix_cbor::bignum;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Transfer {
+//!     #[serde(with = "bignum")]
+//!     amount: i128,
+//!     #[serde(with = "bignum")]
+//!     balance: u128,
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use serde_cbor::tags::{current_cbor_tag, Tagged};
+
+/// Tag of unsigned bignums.
+const POSITIVE_BIGNUM: u64 = 2;
+/// Tag of negative bignums, holding `-1 - n`.
+const NEGATIVE_BIGNUM: u64 = 3;
+
+/// Integer encoded as a bignum when it does not fit in a CBOR integer.
+pub trait Bignum: Sized + Copy {
+    /// Serialize `self`, as a bignum if needed.
+    fn serialize_bignum<S: Serializer>(self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Value of the bignum with `tag` and big-endian `magnitude`, if in range.
+    fn from_bignum(tag: u64, magnitude: u128) -> Option<Self>;
+
+    /// Value of a CBOR integer, if in range.
+    fn from_i128(value: i128) -> Option<Self>;
+}
+
+impl Bignum for u128 {
+    fn serialize_bignum<S: Serializer>(self, serializer: S) -> Result<S::Ok, S::Error> {
+        match u64::try_from(self) {
+            Ok(value) => serializer.serialize_u64(value),
+            Err(_) => Tagged::new(Some(POSITIVE_BIGNUM), Magnitude(self)).serialize(serializer),
+        }
+    }
+
+    fn from_bignum(tag: u64, magnitude: u128) -> Option<Self> {
+        match tag {
+            POSITIVE_BIGNUM => Some(magnitude),
+            _ => None,
+        }
+    }
+
+    fn from_i128(value: i128) -> Option<Self> {
+        u128::try_from(value).ok()
+    }
+}
+
+impl Bignum for i128 {
+    fn serialize_bignum<S: Serializer>(self, serializer: S) -> Result<S::Ok, S::Error> {
+        // CBOR integers range from -2^64 to 2^64 - 1
+        let in_range = (-(1 << 64)..(1 << 64)).contains(&self);
+        match (in_range, self < 0) {
+            (true, _) => serializer.serialize_i128(self),
+            (false, false) => {
+                Tagged::new(Some(POSITIVE_BIGNUM), Magnitude(self as u128)).serialize(serializer)
+            }
+            // -1 - self, without overflowing at i128::MIN
+            (false, true) => {
+                Tagged::new(Some(NEGATIVE_BIGNUM), Magnitude(!self as u128)).serialize(serializer)
+            }
+        }
+    }
+
+    fn from_bignum(tag: u64, magnitude: u128) -> Option<Self> {
+        let n = i128::try_from(magnitude).ok()?;
+        match tag {
+            POSITIVE_BIGNUM => Some(n),
+            NEGATIVE_BIGNUM => Some(!n),
+            _ => None,
+        }
+    }
+
+    fn from_i128(value: i128) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// Content of a bignum: its magnitude as a big-endian byte string without leading zeros.
+struct Magnitude(u128);
+
+impl Serialize for Magnitude {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.0.to_be_bytes();
+        let skip = (self.0.leading_zeros() / 8) as usize;
+        serializer.serialize_bytes(&bytes[skip..])
+    }
+}
+
+/// Serialize `value` as a CBOR integer when it fits, as a bignum otherwise.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Bignum,
+    S: Serializer,
+{
+    value.serialize_bignum(serializer)
+}
+
+/// Deserialize a CBOR integer or bignum, failing when it does not fit in `T`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Bignum,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(BignumVisitor(PhantomData))
+}
+
+struct BignumVisitor<T>(PhantomData<T>);
+
+impl<T> BignumVisitor<T> {
+    fn out_of_range<E: de::Error>(unexp: de::Unexpected<'_>) -> E {
+        E::invalid_value(unexp, &"an integer in range")
+    }
+}
+
+impl<'de, T: Bignum> Visitor<'de> for BignumVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an integer or a bignum")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        T::from_i128(i128::from(value))
+            .ok_or_else(|| Self::out_of_range(de::Unexpected::Unsigned(value)))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+        T::from_i128(i128::from(value))
+            .ok_or_else(|| Self::out_of_range(de::Unexpected::Signed(value)))
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<T, E> {
+        T::from_i128(value).ok_or_else(|| Self::out_of_range(de::Unexpected::Other("integer")))
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        let tag = current_cbor_tag();
+        let magnitude = deserializer.deserialize_bytes(MagnitudeVisitor)?;
+        tag.and_then(|tag| T::from_bignum(tag, magnitude))
+            .ok_or_else(|| Self::out_of_range(de::Unexpected::Other("bignum")))
+    }
+}
+
+struct MagnitudeVisitor;
+
+impl<'de> Visitor<'de> for MagnitudeVisitor {
+    type Value = u128;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the byte string of a bignum")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<u128, E> {
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        let bytes = &bytes[start..];
+        if bytes.len() > 16 {
+            return Err(E::invalid_value(
+                de::Unexpected::Bytes(bytes),
+                &"a bignum of at most 128 bits",
+            ));
+        }
+        Ok(bytes.iter().fold(0, |acc, b| (acc << 8) | u128::from(*b)))
+    }
+}
@@ -1,18 +1,25 @@
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
 use std::task::{Context, Poll};
 
 #[cfg(feature = "compress")]
 use actix_web::dev::Decompress;
 use actix_web::{
-    dev::Payload, http::header::CONTENT_LENGTH, web::BytesMut, HttpMessage, HttpRequest,
+    dev::Payload,
+    http::header::{HeaderValue, CONTENT_LENGTH},
+    web::{Bytes, BytesMut},
+    HttpMessage, HttpRequest,
 };
 use futures_util::future::{FutureExt, LocalBoxFuture};
 use futures_util::StreamExt;
 use serde::de::DeserializeOwned;
 
-use crate::CborPayloadError;
+use crate::config::{ContentTypePredicate, ProgressHook};
+use crate::scan::{Sniffer, DEFAULT_MAX_DEPTH};
+use crate::spool::{Spool, SpoolFile};
+use crate::untagged;
+use crate::utf8::repair_utf8;
+use crate::{CborConfig, CborPayloadError};
 
 /// Request's payload cbor parser, it resolves to a deserialized `T` value.
 /// This future could be used with `ServiceRequest` and `ServiceFromRequest`.
@@ -25,12 +32,98 @@ use crate::CborPayloadError;
 pub struct CborBody<U> {
     pub(crate) limit: usize,
     pub(crate) length: Option<usize>,
-    #[cfg(feature = "compress")]
-    pub(crate) stream: Option<Decompress<Payload>>,
-    #[cfg(not(feature = "compress"))]
-    pub(crate) stream: Option<Payload>,
+    pub(crate) stream: Option<BodyStream>,
+    pub(crate) buffered: Option<Bytes>,
+    pub(crate) tee: Option<HttpRequest>,
     pub(crate) err: Option<CborPayloadError>,
     pub(crate) fut: Option<LocalBoxFuture<'static, Result<U, CborPayloadError>>>,
+    pub(crate) decode: Decoder<U>,
+    pub(crate) spool: Option<Spool<U>>,
+    pub(crate) offload: Option<Offload<U>>,
+    pub(crate) progress: Option<(ProgressHook, HttpRequest)>,
+    pub(crate) sniff: bool,
+}
+
+/// Decoder of a buffered body.
+pub(crate) type Decoder<U> = fn(&[u8]) -> Result<U, CborPayloadError>;
+
+/// Future running a decoder on a buffered body away from the async runtime.
+pub(crate) type Offload<U> =
+    fn(Bytes, Decoder<U>) -> LocalBoxFuture<'static, Result<U, CborPayloadError>>;
+
+/// Raw body of a request, kept in the request extensions by [`CachedCbor`](struct.CachedCbor.html)
+/// and teeing extractors.
+#[derive(Clone)]
+pub(crate) struct BufferedBody(pub(crate) Bytes);
+
+/// Request payload, transparently decompressed when the `compress` feature is enabled.
+#[cfg(feature = "compress")]
+pub(crate) type BodyStream = Decompress<Payload>;
+#[cfg(not(feature = "compress"))]
+pub(crate) type BodyStream = Payload;
+
+/// Take the request payload, decompressing it according to its headers.
+#[cfg(feature = "compress")]
+pub(crate) fn take_payload(req: &HttpRequest, payload: &mut Payload) -> BodyStream {
+    Decompress::from_headers(payload.take(), req.headers())
+}
+
+/// Take the request payload.
+#[cfg(not(feature = "compress"))]
+pub(crate) fn take_payload(_: &HttpRequest, payload: &mut Payload) -> BodyStream {
+    payload.take()
+}
+
+/// Decode a single CBOR item spanning the whole body.
+pub(crate) fn decode_item<U: DeserializeOwned>(body: &[u8]) -> Result<U, CborPayloadError> {
+    if body.is_empty() {
+        return Err(CborPayloadError::NoBody);
+    }
+    Ok(untagged::from_slice::<U>(body)?)
+}
+
+/// Decode a single CBOR item spanning the whole body, rejecting map keys `U` does not consume.
+pub(crate) fn decode_item_strict<U: DeserializeOwned>(body: &[u8]) -> Result<U, CborPayloadError> {
+    if body.is_empty() {
+        return Err(CborPayloadError::NoBody);
+    }
+    let mut unknown = None;
+    let mut de = serde_cbor::Deserializer::from_slice(body);
+    let value = serde_ignored::deserialize(untagged::untagged(&mut de), |path| {
+        unknown.get_or_insert_with(|| path.to_string());
+    })?;
+    de.end()?;
+
+    match unknown {
+        Some(path) => Err(CborPayloadError::UnknownField(path)),
+        None => Ok(value),
+    }
+}
+
+/// Decoder of a whole body to `U`, rejecting unknown map keys when `strict` and repairing the
+/// invalid UTF-8 of text strings when `lossy`.
+pub(crate) fn body_decoder<U: DeserializeOwned>(
+    strict: bool,
+    lossy: bool,
+) -> fn(&[u8]) -> Result<U, CborPayloadError> {
+    match (strict, lossy) {
+        (false, false) => decode_item::<U>,
+        (true, false) => decode_item_strict::<U>,
+        (false, true) => |body| decode_item(&repair_utf8(body)),
+        (true, true) => |body| decode_item_strict(&repair_utf8(body)),
+    }
+}
+
+/// Content type of a CBOR body.
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// `Content-Type` of CBOR responses, cloned instead of being parsed for every response.
+pub(crate) static CBOR_CONTENT_TYPE_VALUE: HeaderValue =
+    HeaderValue::from_static(CBOR_CONTENT_TYPE);
+
+/// Whether a content type is CBOR, or accepted by the configured predicate.
+pub(crate) fn is_cbor_content_type(mime: &str, ctype: Option<&ContentTypePredicate>) -> bool {
+    mime == CBOR_CONTENT_TYPE || mime == "cbor" || ctype.is_some_and(|predicate| predicate(mime))
 }
 
 impl<U> CborBody<U>
@@ -41,21 +134,95 @@ where
     pub fn new(
         req: &HttpRequest,
         payload: &mut Payload,
-        ctype: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+        ctype: Option<ContentTypePredicate>,
     ) -> Self {
-        // check content-type
-        let mime = req.content_type();
-        let is_good_mime = mime == "application/cbor"
-            || mime == "cbor"
-            || ctype.as_ref().map_or(false, |predicate| predicate(mime));
+        Self::with_decoder(
+            req,
+            payload,
+            &CborConfig::from_req(req),
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            decode_item::<U>,
+        )
+    }
+
+    /// Create `CborBody` decoding a body already buffered, e.g. received out of band, with the
+    /// same limit and errors as a request body. No content type is checked.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborBody;
+    /// use actix_web::web::Bytes;
+    ///
+    /// # actix_rt::System::new().block_on(async {
+    /// let body = Bytes::from_static(&[0x82, 0x01, 0x02]);
+    /// let numbers: Vec<u32> = CborBody::from_bytes(body).limit(1024).await.unwrap();
+    /// assert_eq!(numbers, [1, 2]);
+    /// # });
+    /// ```
+    pub fn from_bytes(body: Bytes) -> Self {
+        CborBody {
+            limit: 262_144,
+            length: Some(body.len()),
+            stream: None,
+            buffered: Some(body),
+            tee: None,
+            fut: None,
+            err: None,
+            decode: decode_item::<U>,
+            spool: None,
+            offload: None,
+            progress: None,
+            sniff: false,
+        }
+    }
+}
 
-        if !is_good_mime {
+impl<U> CborBody<U>
+where
+    U: 'static,
+{
+    /// Create `CborBody` accepting content types matched by `accept` and decoding the buffered
+    /// body with `decode`, teeing and reporting progress according to `config`.
+    pub(crate) fn with_decoder(
+        req: &HttpRequest,
+        payload: &mut Payload,
+        config: &CborConfig,
+        accept: impl FnOnce(&str) -> bool,
+        decode: Decoder<U>,
+    ) -> Self {
+        // check content-type
+        if !accept(req.content_type()) {
             return CborBody {
                 limit: 262_144,
                 length: None,
                 stream: None,
+                buffered: None,
+                tee: None,
                 fut: None,
                 err: Some(CborPayloadError::ContentType),
+                decode,
+                spool: None,
+                offload: None,
+                progress: None,
+                sniff: false,
+            };
+        }
+
+        // body already buffered by another extractor
+        if let Some(BufferedBody(body)) = req.extensions().get::<BufferedBody>().cloned() {
+            return CborBody {
+                limit: 262_144,
+                length: Some(body.len()),
+                stream: None,
+                buffered: Some(body),
+                tee: None,
+                fut: None,
+                err: None,
+                decode,
+                spool: None,
+                offload: None,
+                progress: None,
+                sniff: false,
             };
         }
 
@@ -65,18 +232,33 @@ where
             .and_then(|l| l.to_str().ok())
             .and_then(|s| s.parse::<usize>().ok());
 
-        #[cfg(feature = "compress")]
-        let payload = Decompress::from_headers(payload.take(), req.headers());
-        #[cfg(not(feature = "compress"))]
-        let payload = payload.take();
+        let payload = take_payload(req, payload);
+        let tee = config.tee == Some(true);
+        let progress = config.progress.clone().map(|hook| (hook, req.clone()));
 
         CborBody {
             limit: 262_144,
             length: len,
             stream: Some(payload),
+            buffered: None,
+            tee: Some(req.clone()).filter(|_| tee),
             fut: None,
             err: None,
+            decode,
+            spool: None,
+            offload: None,
+            progress,
+            sniff: false,
+        }
+    }
+
+    /// Keep the raw body in the request extensions once read, see
+    /// [`CborConfig::tee`](struct.CborConfig.html#method.tee).
+    pub fn tee(mut self, req: &HttpRequest) -> Self {
+        if self.stream.is_some() {
+            self.tee = Some(req.clone());
         }
+        self
     }
 
     /// Change max size of payload. By default max size is 256Kb
@@ -84,11 +266,25 @@ where
         self.limit = limit;
         self
     }
+
+    /// Check that the payload is a single well-formed item while it is received, see
+    /// [`CborConfig::sniff`](struct.CborConfig.html#method.sniff).
+    pub(crate) fn sniff(mut self, enabled: bool) -> Self {
+        self.sniff = enabled;
+        self
+    }
+
+    /// Spool payloads bigger than the limit to a temporary file instead of failing, as `spool`
+    /// sets.
+    pub(crate) fn spool(mut self, spool: Option<Spool<U>>) -> Self {
+        self.spool = spool;
+        self
+    }
 }
 
 impl<U> Future for CborBody<U>
 where
-    U: DeserializeOwned + 'static,
+    U: 'static,
 {
     type Output = Result<U, CborPayloadError>;
 
@@ -102,26 +298,76 @@ where
         }
 
         let limit = self.limit;
+        let max = self
+            .spool
+            .as_ref()
+            .map_or(limit, |spool| spool.limit.max(limit));
         if let Some(len) = self.length.take() {
-            if len > limit {
+            if len > max {
                 return Poll::Ready(Err(CborPayloadError::Overflow));
             }
         }
+        if let Some(body) = self.buffered.take() {
+            match self.offload {
+                Some(offload) => {
+                    self.fut = Some(offload(body, self.decode));
+                    return self.poll(cx);
+                }
+                None => return Poll::Ready((self.decode)(&body)),
+            }
+        }
         let mut stream = self.stream.take().unwrap();
+        let decode = self.decode;
+        let spool = self.spool.take();
+        let offload = self.offload;
+        let tee = self.tee.take();
+        let progress = self.progress.take();
+        let mut sniffer = match self.sniff {
+            true => Some(Sniffer::new(max, DEFAULT_MAX_DEPTH)),
+            false => None,
+        };
 
         self.fut = Some(
             async move {
                 let mut body = BytesMut::with_capacity(8192);
+                let mut file: Option<SpoolFile> = None;
+                let mut len = 0;
 
                 while let Some(item) = stream.next().await {
                     let chunk = item?;
-                    if (body.len() + chunk.len()) > limit {
+                    len += chunk.len();
+                    if let Some((hook, req)) = &progress {
+                        hook(len, req);
+                    }
+                    if len > max {
                         return Err(CborPayloadError::Overflow);
+                    }
+                    if let Some(sniffer) = &mut sniffer {
+                        sniffer.feed(&chunk)?;
+                    }
+                    if let Some(file) = &mut file {
+                        file.write(chunk).await?;
+                    } else if len > limit {
+                        // too big for memory, but within the spool limit
+                        let mut spooled = SpoolFile::create().await?;
+                        spooled.write(body.split().freeze()).await?;
+                        spooled.write(chunk).await?;
+                        file = Some(spooled);
                     } else {
                         body.extend_from_slice(&chunk);
                     }
                 }
-                Ok(serde_cbor::from_slice::<U>(&body)?)
+                if let (Some(file), Some(spool)) = (file, spool) {
+                    return spool.decode(file).await;
+                }
+                let body = body.freeze();
+                if let Some(req) = tee {
+                    req.extensions_mut().insert(BufferedBody(body.clone()));
+                }
+                match offload {
+                    Some(offload) => offload(body, decode).await,
+                    None => decode(&body),
+                }
             }
             .boxed_local(),
         );
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::{
+    body::{BodySize, MessageBody},
+    web::{Bytes, BytesMut},
+};
+use bytes::BufMut;
+use futures_util::stream::{LocalBoxStream, Stream, StreamExt};
+use serde::Serialize;
+
+use crate::CborError;
+
+/// Size of the chunks values are serialized into.
+const CHUNK_SIZE: usize = 8192;
+
+/// `io::Write` sink splitting its output into fixed-size chunks.
+#[derive(Default)]
+struct ChunkWriter {
+    chunks: VecDeque<Bytes>,
+    current: BytesMut,
+    len: usize,
+}
+
+impl ChunkWriter {
+    fn finish(mut self) -> (VecDeque<Bytes>, usize) {
+        if !self.current.is_empty() {
+            self.chunks.push_back(self.current.freeze());
+        }
+        (self.chunks, self.len)
+    }
+}
+
+impl io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.current.capacity() == self.current.len() {
+            if !self.current.is_empty() {
+                let full = self.current.split();
+                self.chunks.push_back(full.freeze());
+            }
+            self.current.reserve(CHUNK_SIZE);
+        }
+
+        let len = buf.len().min(self.current.capacity() - self.current.len());
+        self.current.extend_from_slice(&buf[..len]);
+        self.len += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Inner {
+    Chunks(VecDeque<Bytes>, usize),
+    Stream(LocalBoxStream<'static, Result<Bytes, CborError>>),
+}
+
+/// Message body serializing CBOR straight into body chunks, without an intermediate `Vec<u8>`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborBodyWriter;
+/// use actix_web::HttpResponse;
+///
+/// fn respond(values: &[u32]) -> Result<HttpResponse, actix_cbor::CborError> {
+///     let body = CborBodyWriter::from_value(&values)?;
+///     Ok(HttpResponse::Ok().content_type("application/cbor").body(body))
+/// }
+/// ```
+pub struct CborBodyWriter {
+    inner: Inner,
+}
+
+impl CborBodyWriter {
+    /// Serialize a single value. The body size is known, so `Content-Length` gets set.
+    pub fn from_value<T: Serialize>(value: &T) -> Result<Self, CborError> {
+        let mut writer = ChunkWriter::default();
+        serde_cbor::to_writer(&mut writer, value)?;
+        let (chunks, len) = writer.finish();
+
+        Ok(CborBodyWriter {
+            inner: Inner::Chunks(chunks, len),
+        })
+    }
+
+    /// Serialize values as a CBOR sequence as the stream yields them.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: Stream + 'static,
+        S::Item: Serialize,
+    {
+        let chunks = stream.map(|item| {
+            let mut frame = BytesMut::new().writer();
+            serde_cbor::to_writer(&mut frame, &item)?;
+            Ok(frame.into_inner().freeze())
+        });
+
+        CborBodyWriter {
+            inner: Inner::Stream(chunks.boxed_local()),
+        }
+    }
+}
+
+impl MessageBody for CborBodyWriter {
+    type Error = CborError;
+
+    fn size(&self) -> BodySize {
+        match &self.inner {
+            Inner::Chunks(_, len) => BodySize::Sized(*len as u64),
+            Inner::Stream(_) => BodySize::Stream,
+        }
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        match &mut self.inner {
+            Inner::Chunks(chunks, _) => Poll::Ready(chunks.pop_front().map(Ok)),
+            Inner::Stream(stream) => stream.as_mut().poll_next(cx),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+
+use actix_web::{
+    body::BoxBody,
+    http::header::{
+        CacheControl, CacheDirective, Expires, HttpDate, TryIntoHeaderValue, CACHE_CONTROL, EXPIRES,
+    },
+    HttpRequest, HttpResponse, Responder,
+};
+use serde::Serialize;
+
+use crate::Cbor;
+
+/// Responder serializing `T` as CBOR along with its `Cache-Control` and `Expires` headers.
+///
+/// Headers are only set on successful responses.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborCached;
+/// use std::time::Duration;
+///
+/// async fn index() -> CborCached<Vec<u32>> {
+///     CborCached::new(vec![1, 2, 3])
+///         .public()
+///         .max_age(Duration::from_secs(60))
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CborCached<T> {
+    value: T,
+    directives: Vec<CacheDirective>,
+    expires: Option<SystemTime>,
+}
+
+/// Seconds of a duration, saturated to the range of cache directives.
+fn seconds(duration: Duration) -> u32 {
+    u32::try_from(duration.as_secs()).unwrap_or(u32::MAX)
+}
+
+impl<T> CborCached<T> {
+    /// Respond with `value` and no caching directives.
+    pub fn new(value: T) -> Self {
+        CborCached {
+            value,
+            directives: Vec::new(),
+            expires: None,
+        }
+    }
+
+    /// Add a `Cache-Control` directive.
+    pub fn directive(mut self, directive: CacheDirective) -> Self {
+        self.directives.push(directive);
+        self
+    }
+
+    /// Allow shared caches to store the response.
+    pub fn public(self) -> Self {
+        self.directive(CacheDirective::Public)
+    }
+
+    /// Restrict storage of the response to the client cache.
+    pub fn private(self) -> Self {
+        self.directive(CacheDirective::Private)
+    }
+
+    /// Require revalidation before reusing a stored response.
+    pub fn no_cache(self) -> Self {
+        self.directive(CacheDirective::NoCache)
+    }
+
+    /// Forbid storing the response.
+    pub fn no_store(self) -> Self {
+        self.directive(CacheDirective::NoStore)
+    }
+
+    /// Consider the response fresh for `max_age`.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        self.directive(CacheDirective::MaxAge(seconds(max_age)))
+    }
+
+    /// Consider the response fresh for `max_age` in shared caches.
+    pub fn s_max_age(self, max_age: Duration) -> Self {
+        self.directive(CacheDirective::SMaxAge(seconds(max_age)))
+    }
+
+    /// Set the `Expires` date, for clients ignoring `Cache-Control`.
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize + 'static> Responder for CborCached<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let mut res = Cbor(self.value).respond_to(req);
+        if !res.status().is_success() {
+            return res;
+        }
+
+        if !self.directives.is_empty() {
+            if let Ok(value) = CacheControl(self.directives).try_into_value() {
+                res.headers_mut().insert(CACHE_CONTROL, value);
+            }
+        }
+        if let Some(expires) = self.expires {
+            if let Ok(value) = Expires(HttpDate::from(expires)).try_into_value() {
+                res.headers_mut().insert(EXPIRES, value);
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+
+use crate::{extract_cbor, extraction_error, is_cbor_content_type, CborBody, CborConfig};
+
+/// Extractor decoding a CBOR body which stays available to the extractors that run after it.
+///
+/// The buffered body is kept in the request extensions, so later [`Cbor`](struct.Cbor.html),
+/// `CachedCbor` or other CBOR extractors decode it again instead of failing on an already
+/// consumed payload. Use it in a middleware or guard that needs to look at the body before the
+/// handler does. Payloads are checked against the request [`CborConfig`](struct.CborConfig.html)
+/// as with [`Cbor`](struct.Cbor.html).
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CachedCbor, Cbor};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     item: String,
+/// }
+///
+/// // `raw` buffers the body, which `order` then decodes again.
+/// async fn order(raw: CachedCbor<serde_cbor::Value>, order: Cbor<Order>) -> String {
+///     log::info!("order: {:?}", raw.into_inner());
+///     order.into_inner().item
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct CachedCbor<T>(pub T);
+
+impl<T> CachedCbor<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CachedCbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CachedCbor<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for CachedCbor<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CachedCbor: {:?}", self.0)
+    }
+}
+
+impl<T> FromRequest for CachedCbor<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req).into_owned();
+        let limit = config.limit_for(&req);
+        let strict = config.deny_unknown_fields == Some(true);
+        let ctype = config.content_type.clone();
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |_| Ok(()),
+        )
+        .tee(&req)
+        .limit(limit);
+
+        Box::pin(async move {
+            body.await.map_err(|e| extraction_error(&config, e, &req))?;
+            extract_cbor(&req, &mut Payload::None, &config, limit, strict, None)
+                .await
+                .map(CachedCbor)
+        })
+    }
+}
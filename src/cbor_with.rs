@@ -0,0 +1,111 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+
+use crate::{extract_cbor, CborConfig};
+
+/// Settings of a [`CborWith`](struct.CborWith.html) extractor, supplied by a unit type.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborSettings;
+///
+/// struct Bulk;
+///
+/// impl CborSettings for Bulk {
+///     const LIMIT: usize = 16 << 20;
+/// }
+/// ```
+pub trait CborSettings {
+    /// Max size of payload, overriding the limits of the request `CborConfig`
+    const LIMIT: usize;
+
+    /// Whether to reject map keys the target type does not consume, or `None` to follow the
+    /// request `CborConfig`
+    const DENY_UNKNOWN_FIELDS: Option<bool> = None;
+}
+
+/// Extractor behaving like [`Cbor`](struct.Cbor.html), with the settings of `C` taking precedence
+/// over the request [`CborConfig`](struct.CborConfig.html).
+///
+/// Other settings, such as the error handler, still come from the request config.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborSettings, CborWith};
+///
+/// struct Bulk;
+///
+/// impl CborSettings for Bulk {
+///     const LIMIT: usize = 16 << 20;
+/// }
+///
+/// #[derive(serde::Deserialize)]
+/// struct Record {
+///     id: u64,
+/// }
+///
+/// async fn import(records: CborWith<Vec<Record>, Bulk>) -> String {
+///     format!("{} records", records.len())
+/// }
+/// ```
+pub struct CborWith<T, C>(pub T, PhantomData<C>);
+
+impl<T, C> CborWith<T, C> {
+    /// Wrap `value`.
+    pub fn new(value: T) -> Self {
+        CborWith(value, PhantomData)
+    }
+
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, C> Deref for CborWith<T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T, C> DerefMut for CborWith<T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T, C> fmt::Debug for CborWith<T, C>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborWith: {:?}", self.0)
+    }
+}
+
+impl<T, C> FromRequest for CborWith<T, C>
+where
+    T: DeserializeOwned + 'static,
+    C: CborSettings + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req);
+        let strict = C::DENY_UNKNOWN_FIELDS
+            .or(config.deny_unknown_fields)
+            .unwrap_or(false);
+        extract_cbor(req, payload, &config, C::LIMIT, strict, None)
+            .map(|res| res.map(CborWith::new))
+            .boxed_local()
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_web::{web::Bytes, HttpResponse};
+use futures_util::future::{ready, Ready};
+
+/// Describes the CBOR encoding of a type as a CDDL ([RFC 8610]) type expression.
+///
+/// Primitive and collection types are covered out of the box. For your own types, return a rule
+/// name from [`cddl_type`](CddlSchema::cddl_type) and register its definition in
+/// [`cddl_define`](CddlSchema::cddl_define), usually with [`CddlMap`].
+///
+/// These implementations are written by hand and are not derived from `Serialize`: nothing
+/// checks that they match the actual encoding, so update them along with the serde attributes
+/// of the type.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CddlMap, CddlRegistry, CddlSchema};
+///
+/// struct User {
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// impl CddlSchema for User {
+///     fn cddl_type() -> String {
+///         "user".to_owned()
+///     }
+///
+///     fn cddl_define(registry: &mut CddlRegistry) {
+///         registry.define(
+///             "user",
+///             CddlMap::new().field::<String>("name").optional::<u32>("age"),
+///         );
+///     }
+/// }
+///
+/// let schema = CddlRegistry::new().add::<User>().to_cddl();
+/// assert_eq!(schema, "user = {\n  name: tstr,\n  ? age: uint / nil,\n}\n");
+/// ```
+///
+/// [RFC 8610]: https://tools.ietf.org/html/rfc8610
+pub trait CddlSchema {
+    /// Type expression used wherever this type is referenced.
+    fn cddl_type() -> String;
+
+    /// Register the rules this type (and the types it contains) depends on.
+    fn cddl_define(_registry: &mut CddlRegistry) {}
+}
+
+/// Collection of named CDDL rules, rendered in registration order.
+#[derive(Clone, Default)]
+pub struct CddlRegistry {
+    rules: Vec<(String, String)>,
+}
+
+impl CddlRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `T` and every rule it depends on.
+    ///
+    /// Only named rules are rendered: primitives and collections have no rule of their own, so
+    /// `add::<Vec<User>>()` contributes the rules of `User`, and `add::<Vec<String>>()` nothing.
+    pub fn add<T: CddlSchema>(mut self) -> Self {
+        self.register::<T>();
+        self
+    }
+
+    /// Register `T` and every rule it depends on.
+    pub fn register<T: CddlSchema>(&mut self) {
+        T::cddl_define(self);
+    }
+
+    /// Define a named rule. Defining the same name twice keeps the first definition, which makes
+    /// recursive and shared types safe to register repeatedly.
+    pub fn define(&mut self, name: &str, definition: impl Into<CddlDefinition>) {
+        if self.contains(name) {
+            return;
+        }
+
+        // reserve the name before resolving dependencies so recursive types terminate
+        self.rules.push((name.to_owned(), String::new()));
+        let definition = definition.into();
+        for dependency in &definition.dependencies {
+            dependency(self);
+        }
+
+        if let Some(rule) = self.rules.iter_mut().find(|(n, _)| n == name) {
+            rule.1 = definition.expr;
+        }
+    }
+
+    /// Whether a rule with this name was already defined.
+    pub fn contains(&self, name: &str) -> bool {
+        self.rules.iter().any(|(n, _)| n == name)
+    }
+
+    /// Render every rule as a CDDL document.
+    pub fn to_cddl(&self) -> String {
+        let mut out = String::new();
+        for (name, expr) in &self.rules {
+            let _ = writeln!(out, "{} = {}", name, expr);
+        }
+        out
+    }
+}
+
+/// Right-hand side of a CDDL rule, along with the rules it references.
+pub struct CddlDefinition {
+    expr: String,
+    dependencies: Vec<fn(&mut CddlRegistry)>,
+}
+
+impl CddlDefinition {
+    /// A definition given as a raw CDDL expression.
+    pub fn raw(expr: impl Into<String>) -> Self {
+        Self {
+            expr: expr.into(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Mark `T` as referenced by this definition so its rules get registered too.
+    pub fn depends_on<T: CddlSchema>(mut self) -> Self {
+        self.dependencies.push(T::cddl_define);
+        self
+    }
+}
+
+impl From<String> for CddlDefinition {
+    fn from(expr: String) -> Self {
+        Self::raw(expr)
+    }
+}
+
+impl From<&str> for CddlDefinition {
+    fn from(expr: &str) -> Self {
+        Self::raw(expr)
+    }
+}
+
+/// Builder for a CDDL map describing a struct serialized with named fields.
+#[derive(Default)]
+pub struct CddlMap {
+    members: Vec<String>,
+    dependencies: Vec<fn(&mut CddlRegistry)>,
+}
+
+impl CddlMap {
+    /// Create an empty map definition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a required field.
+    pub fn field<T: CddlSchema>(mut self, name: &str) -> Self {
+        self.members.push(format!("{}: {}", name, T::cddl_type()));
+        self.dependencies.push(T::cddl_define);
+        self
+    }
+
+    /// Add a field that may be missing, such as an `Option` skipped when `None`.
+    pub fn optional<T: CddlSchema>(mut self, name: &str) -> Self {
+        self.members
+            .push(format!("? {}: {}", name, <Option<T>>::cddl_type()));
+        self.dependencies.push(T::cddl_define);
+        self
+    }
+}
+
+impl From<CddlMap> for CddlDefinition {
+    fn from(map: CddlMap) -> Self {
+        let mut expr = String::from("{\n");
+        for member in &map.members {
+            let _ = writeln!(expr, "  {},", member);
+        }
+        expr.push('}');
+
+        Self {
+            expr,
+            dependencies: map.dependencies,
+        }
+    }
+}
+
+/// Build a handler serving the schema of every registered type as `application/cddl`.
+///
+/// ```
+/// use actix_cbor::{cddl_handler, CddlMap, CddlRegistry, CddlSchema};
+/// use actix_web::{web, App};
+///
+/// struct Tag {
+///     name: String,
+/// }
+///
+/// impl CddlSchema for Tag {
+///     fn cddl_type() -> String {
+///         "tag".to_owned()
+///     }
+///
+///     fn cddl_define(registry: &mut CddlRegistry) {
+///         registry.define("tag", CddlMap::new().field::<String>("name"));
+///     }
+/// }
+///
+/// let registry = CddlRegistry::new().add::<Vec<Tag>>();
+/// assert_eq!(registry.to_cddl(), "tag = {\n  name: tstr,\n}\n");
+/// let app = App::new().route("/schema.cddl", web::get().to(cddl_handler(registry)));
+/// ```
+pub fn cddl_handler(registry: CddlRegistry) -> impl Fn() -> Ready<HttpResponse> + Clone + 'static {
+    // rendered once, the clones share the same buffer
+    let schema = Bytes::from(registry.to_cddl());
+    move || {
+        ready(
+            HttpResponse::Ok()
+                .content_type("application/cddl")
+                .body(schema.clone()),
+        )
+    }
+}
+
+macro_rules! cddl_primitive {
+    ($cddl:expr => $($ty:ty),+) => {
+        $(
+            impl CddlSchema for $ty {
+                fn cddl_type() -> String {
+                    $cddl.to_owned()
+                }
+            }
+        )+
+    };
+}
+
+cddl_primitive!("uint" => u8, u16, u32, u64, usize);
+cddl_primitive!("int" => i8, i16, i32, i64, isize);
+cddl_primitive!("float" => f32, f64);
+cddl_primitive!("bool" => bool);
+cddl_primitive!("tstr" => String, str, char);
+cddl_primitive!("nil" => ());
+
+impl<T: CddlSchema + ?Sized> CddlSchema for &T {
+    fn cddl_type() -> String {
+        T::cddl_type()
+    }
+
+    fn cddl_define(registry: &mut CddlRegistry) {
+        T::cddl_define(registry)
+    }
+}
+
+macro_rules! cddl_transparent {
+    ($($wrapper:ident),+) => {
+        $(
+            impl<T: CddlSchema + ?Sized> CddlSchema for $wrapper<T> {
+                fn cddl_type() -> String {
+                    T::cddl_type()
+                }
+
+                fn cddl_define(registry: &mut CddlRegistry) {
+                    T::cddl_define(registry)
+                }
+            }
+        )+
+    };
+}
+
+cddl_transparent!(Box, Rc, Arc);
+
+impl<T: CddlSchema> CddlSchema for Option<T> {
+    fn cddl_type() -> String {
+        format!("{} / nil", T::cddl_type())
+    }
+
+    fn cddl_define(registry: &mut CddlRegistry) {
+        T::cddl_define(registry)
+    }
+}
+
+macro_rules! cddl_sequence {
+    ($($seq:ident),+) => {
+        $(
+            impl<T: CddlSchema> CddlSchema for $seq<T> {
+                fn cddl_type() -> String {
+                    format!("[* {}]", T::cddl_type())
+                }
+
+                fn cddl_define(registry: &mut CddlRegistry) {
+                    T::cddl_define(registry)
+                }
+            }
+        )+
+    };
+}
+
+cddl_sequence!(Vec, BTreeSet, HashSet);
+
+impl<T: CddlSchema> CddlSchema for [T] {
+    fn cddl_type() -> String {
+        format!("[* {}]", T::cddl_type())
+    }
+
+    fn cddl_define(registry: &mut CddlRegistry) {
+        T::cddl_define(registry)
+    }
+}
+
+macro_rules! cddl_map {
+    ($($map:ident),+) => {
+        $(
+            impl<K: CddlSchema, V: CddlSchema> CddlSchema for $map<K, V> {
+                fn cddl_type() -> String {
+                    format!("{{ * {} => {} }}", K::cddl_type(), V::cddl_type())
+                }
+
+                fn cddl_define(registry: &mut CddlRegistry) {
+                    K::cddl_define(registry);
+                    V::cddl_define(registry);
+                }
+            }
+        )+
+    };
+}
+
+cddl_map!(BTreeMap, HashMap);
+
+macro_rules! cddl_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: CddlSchema),+> CddlSchema for ($($name,)+) {
+            fn cddl_type() -> String {
+                let items: Vec<String> = vec![$($name::cddl_type()),+];
+                format!("[{}]", items.join(", "))
+            }
+
+            fn cddl_define(registry: &mut CddlRegistry) {
+                $($name::cddl_define(registry);)+
+            }
+        }
+    };
+}
+
+cddl_tuple!(A);
+cddl_tuple!(A, B);
+cddl_tuple!(A, B, C);
+cddl_tuple!(A, B, C, D);
@@ -0,0 +1,68 @@
+use actix_http::encoding::Encoder;
+use actix_web::{
+    body::{BodySize, BoxBody, MessageBody},
+    http::header::{AcceptEncoding, Encoding, Header, HeaderValue, CONTENT_ENCODING, ETAG},
+    HttpRequest, HttpResponse,
+};
+
+use crate::etag::encoded_etag;
+use crate::http_request_ext::add_vary;
+
+/// Encodings of compressed responses, by decreasing preference on ties.
+static SUPPORTED_ENCODINGS: &[Encoding] = &[
+    Encoding::brotli(),
+    Encoding::zstd(),
+    Encoding::gzip(),
+    Encoding::identity(),
+];
+
+/// Compress `res` with the encoding `req` accepts best, when its body is bigger than `threshold`
+/// bytes.
+///
+/// Responses without a known size, already encoded, or answering requests without an
+/// `Accept-Encoding` header are left as is. With a threshold, every response varies on
+/// `Accept-Encoding`, and the `ETag` of compressed ones is suffixed with their encoding.
+pub(crate) fn compress_above(
+    req: &HttpRequest,
+    res: HttpResponse,
+    threshold: Option<usize>,
+) -> HttpResponse {
+    let threshold = match threshold {
+        Some(threshold) => threshold as u64,
+        None => return res,
+    };
+    let mut res = compress(req, res, threshold);
+    add_vary(&mut res, "accept-encoding");
+    res
+}
+
+fn compress(req: &HttpRequest, mut res: HttpResponse, threshold: u64) -> HttpResponse {
+    match res.body().size() {
+        BodySize::Sized(len) if len > threshold => {}
+        _ => return res,
+    }
+    if res.headers().contains_key(CONTENT_ENCODING) {
+        return res;
+    }
+
+    let encoding = match AcceptEncoding::parse(req)
+        .ok()
+        .filter(|accept| !accept.0.is_empty())
+        .and_then(|accept| accept.negotiate(SUPPORTED_ENCODINGS.iter()))
+    {
+        Some(Encoding::Known(encoding)) => encoding,
+        _ => return res,
+    };
+
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(|etag| encoded_etag(&etag, encoding.as_str()));
+    if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(&etag.to_string()).ok()) {
+        res.headers_mut().insert(ETAG, value);
+    }
+
+    res.map_body(|head, body| BoxBody::new(Encoder::response(encoding, head, body)))
+}
@@ -1,22 +1,137 @@
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::env;
+use std::sync::{Arc, OnceLock, RwLock};
 
-use actix_web::{HttpRequest, web};
+use actix_web::{guard::GuardContext, http::Method, web, HttpRequest};
 
-use crate::CborPayloadError;
+use crate::{CborEnvError, CborErrorCode, CborPayloadError, SchemaRegistry};
+
+const DEFAULT_LIMIT: usize = 32_768; // 2^15 bytes, (~32kB)
 
 // Allow shared refs to default.
-const DEFAULT_CONFIG: CborConfig = CborConfig {
-    limit: 32_768, // 2^15 bytes, (~32kB)
+static DEFAULT_CONFIG: CborConfig = CborConfig {
+    limit: None,
+    method_limits: Vec::new(),
     err_handler: None,
     content_type: None,
+    schema_registry: None,
+    require_schema_id: None,
+    etag: None,
+    return_minimal: None,
+    response_limit: None,
+    deny_unknown_fields: None,
+    messages: None,
+    tee: None,
+    spool_limit: None,
+    progress: None,
+    error_content_type: None,
+    metric_label: None,
+    lossy_utf8: None,
+    compress_threshold: None,
+    sniff: None,
 };
 
+static GLOBAL_CONFIG: OnceLock<CborConfig> = OnceLock::new();
+
+/// Description of the values accepted by boolean variables.
+const BOOL_VALUES: &str = "one of true, false, 1, 0, yes, no, on, off";
+
+/// Description of the values accepted by size variables.
+const SIZE_VALUES: &str = "a size such as 32768, 512kB or 2MiB";
+
+/// Read the variable `var` with `lookup`, parsing it with `parse`.
+fn parse_var<T>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    expected: &'static str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, CborEnvError> {
+    let value = match lookup(var) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match parse(value.trim()) {
+        Some(parsed) => Ok(Some(parsed)),
+        None => Err(CborEnvError {
+            var,
+            value,
+            expected,
+        }),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a size in bytes, with an optional decimal (`kB`, `MB`, `GB`) or binary (`KiB`, `MiB`,
+/// `GiB`) unit. Digits may be separated by `_`.
+fn parse_size(value: &str) -> Option<usize> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '_')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number.replace('_', "").parse::<usize>().ok()?;
+
+    let multiplier: usize = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+/// Shared error handler invoked when extraction fails.
+pub(crate) type ErrorHandler =
+    Arc<dyn Fn(CborPayloadError, &HttpRequest) -> actix_web::Error + Send + Sync>;
+
+/// Shared catalog of error messages, keyed by error code and language tag.
+pub(crate) type MessageCatalog = Arc<dyn Fn(CborErrorCode, &str) -> Option<String> + Send + Sync>;
+
+/// Shared callback reporting the bytes received so far of a payload.
+pub(crate) type ProgressHook = Arc<dyn Fn(usize, &HttpRequest) + Send + Sync>;
+
+/// Shared predicate deciding whether a content type is acceptable.
+pub(crate) type ContentTypePredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Configuration of the CBOR extractors and responders, registered as app data.
+///
+/// Settings left unset fall back to a `Data<CborConfig>`, a
+/// [`CborConfigHandle`](struct.CborConfigHandle.html) and the
+/// [global](#method.set_global_default) configuration. actix-web only exposes the innermost
+/// value of each type, so a `CborConfig` registered with `app_data` on a scope hides the one of
+/// the app: register it with a [`CborConfigLayer`](struct.CborConfigLayer.html) instead to
+/// merge the app, scope and resource configurations field by field.
 #[derive(Clone)]
 pub struct CborConfig {
-    pub(crate) limit: usize,
-    pub(crate) err_handler: Option<Arc<dyn Fn(CborPayloadError, &HttpRequest) -> actix_web::Error
-    + Send + Sync>>,
-    pub(crate) content_type: Option<Arc<dyn Fn(&str) -> bool + Send + Sync>>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) method_limits: Vec<(Method, usize)>,
+    pub(crate) err_handler: Option<ErrorHandler>,
+    pub(crate) content_type: Option<ContentTypePredicate>,
+    pub(crate) schema_registry: Option<Arc<dyn SchemaRegistry>>,
+    pub(crate) require_schema_id: Option<bool>,
+    pub(crate) etag: Option<bool>,
+    pub(crate) return_minimal: Option<bool>,
+    pub(crate) response_limit: Option<usize>,
+    pub(crate) deny_unknown_fields: Option<bool>,
+    pub(crate) messages: Option<MessageCatalog>,
+    pub(crate) tee: Option<bool>,
+    pub(crate) spool_limit: Option<usize>,
+    pub(crate) progress: Option<ProgressHook>,
+    pub(crate) error_content_type: Option<mime::Mime>,
+    pub(crate) metric_label: Option<&'static str>,
+    pub(crate) lossy_utf8: Option<bool>,
+    pub(crate) compress_threshold: Option<usize>,
+    pub(crate) sniff: Option<bool>,
 }
 
 impl Default for CborConfig {
@@ -26,16 +141,89 @@ impl Default for CborConfig {
 }
 
 impl CborConfig {
+    /// Preset for public APIs with a published schema: 32kB payloads, checked while they are
+    /// received, and responses of at most 1MiB, rejecting invalid UTF-8 and the map keys the
+    /// target type does not consume.
+    ///
+    /// Presets only set some settings, and are refined like any other configuration:
+    ///
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::strict().limit(64 * 1024);
+    /// ```
+    ///
+    /// No preset sets the [`messages`](#method.messages) or the
+    /// [`error_content_type`](#method.error_content_type) of errors, which depend on the clients
+    /// of each API. Payloads are accepted in any well-formed encoding, canonical or not, with
+    /// definite or indefinite lengths, as no setting restricts them.
+    pub fn strict() -> Self {
+        CborConfig::default()
+            .limit(DEFAULT_LIMIT)
+            .response_limit(1 << 20)
+            .deny_unknown_fields(true)
+            .lossy_utf8(false)
+            .sniff(true)
+    }
+
+    /// Preset for internal services and migrations: 1MiB payloads, also accepted with the
+    /// `application/octet-stream` and `+cbor` suffixed content types, ignoring unknown map keys
+    /// and repairing invalid UTF-8.
+    pub fn lenient() -> Self {
+        CborConfig::default()
+            .limit(1 << 20)
+            .deny_unknown_fields(false)
+            .lossy_utf8(true)
+            .sniff(false)
+            .content_type_raw(|mime| {
+                mime == "application/octet-stream"
+                    || (mime.starts_with("application/") && mime.ends_with("+cbor"))
+            })
+    }
+
+    /// Preset for constrained devices: payloads of at most 1kB, checked while they are received,
+    /// and responses of at most 1kB without `ETag`, rejecting invalid UTF-8 and unknown map
+    /// keys.
+    pub fn constrained_device() -> Self {
+        CborConfig::default()
+            .limit(1024)
+            .response_limit(1024)
+            .deny_unknown_fields(true)
+            .lossy_utf8(false)
+            .sniff(true)
+            .etag(false)
+    }
+
     /// Change max size of payload. By default max size is 32Kb
     pub fn limit(mut self, limit: usize) -> Self {
-        self.limit = limit;
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Change max size of payload from a human readable size, such as `512kB` or `2MiB`, failing
+    /// when `limit` is not a valid size
+    pub fn limit_str(self, limit: &str) -> Result<Self, CborEnvError> {
+        match parse_size(limit.trim()) {
+            Some(limit) => Ok(self.limit(limit)),
+            None => Err(CborEnvError {
+                var: "limit",
+                value: limit.to_owned(),
+                expected: SIZE_VALUES,
+            }),
+        }
+    }
+
+    /// Change max size of payload for requests with the given method, overriding `limit`
+    pub fn method_limit(mut self, method: Method, limit: usize) -> Self {
+        self.method_limits.retain(|(m, _)| *m != method);
+        self.method_limits.push((method, limit));
         self
     }
 
     /// Set custom error handler
     pub fn error_handler<F>(mut self, f: F) -> Self
-        where
-            F: Fn(CborPayloadError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    where
+        F: Fn(CborPayloadError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
     {
         self.err_handler = Some(Arc::new(f));
         self
@@ -43,18 +231,536 @@ impl CborConfig {
 
     /// Set predicate for allowed content types
     pub fn content_type_raw<F>(mut self, predicate: F) -> Self
-        where
-            F: Fn(&str) -> bool + Send + Sync + 'static,
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
     {
         self.content_type = Some(Arc::new(predicate));
         self
     }
 
-    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
-    /// back to the default payload config.
-    pub(crate) fn from_req(req: &HttpRequest) -> &Self {
+    /// Validate bodies declaring a `CBOR-Schema-Id` header against a schema registry
+    pub fn schema_registry<R>(mut self, registry: R) -> Self
+    where
+        R: SchemaRegistry + 'static,
+    {
+        self.schema_registry = Some(Arc::new(registry));
+        self
+    }
+
+    /// Reject bodies without a valid `CBOR-Schema-Id` header with `422 Unprocessable Entity`,
+    /// instead of decoding them unchecked, when a schema registry is set
+    pub fn require_schema_id(mut self, required: bool) -> Self {
+        self.require_schema_id = Some(required);
+        self
+    }
+
+    /// Set a strong `ETag`, hashed from the serialized body, on `Cbor` responses and answer
+    /// matching `If-None-Match` requests with `304 Not Modified`
+    pub fn etag(mut self, enabled: bool) -> Self {
+        self.etag = Some(enabled);
+        self
+    }
+
+    /// Answer requests modifying a resource with `Prefer: return=minimal` by `204 No Content`,
+    /// without serializing `Cbor` responses
+    pub fn return_minimal(mut self, enabled: bool) -> Self {
+        self.return_minimal = Some(enabled);
+        self
+    }
+
+    /// Refuse to send `Cbor` responses whose encoding is bigger than `limit`. By default
+    /// responses are not limited
+    ///
+    /// Serialization stops as soon as the limit is reached, and the error handler is invoked
+    /// with `CborPayloadError::ResponseOverflow`, answering `500 Internal Server Error` when
+    /// there is none.
+    pub fn response_limit(mut self, limit: usize) -> Self {
+        self.response_limit = Some(limit);
+        self
+    }
+
+    /// Compress `Cbor` responses bigger than `threshold` bytes, with the encoding preferred by
+    /// the `Accept-Encoding` header among brotli, zstd and gzip. By default responses are not
+    /// compressed
+    ///
+    /// Smaller responses are sent as is, since compressing them mostly adds latency. Responses
+    /// already carrying a `Content-Encoding` are left untouched, so the `Compress` middleware can
+    /// still wrap the app.
+    ///
+    /// Every response then varies on `Accept-Encoding`, and the strong `ETag` of compressed ones
+    /// (see [`etag`](#method.etag)) is suffixed with their encoding, e.g. `"1a2b-gzip"`, since
+    /// their bytes differ from the identity ones. `If-None-Match` and
+    /// [`CborIfMatch`](struct.CborIfMatch.html) accept both forms.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::default().compress_above(1400);
+    /// ```
+    #[cfg(feature = "compress")]
+    pub fn compress_above(mut self, threshold: usize) -> Self {
+        self.compress_threshold = Some(threshold);
+        self
+    }
+
+    /// Reject `Cbor` payloads holding map keys that the target type does not consume, with
+    /// `CborPayloadError::UnknownField`
+    ///
+    /// This behaves like `#[serde(deny_unknown_fields)]` on every type of the payload.
+    pub fn deny_unknown_fields(mut self, enabled: bool) -> Self {
+        self.deny_unknown_fields = Some(enabled);
+        self
+    }
+
+    /// Replace the invalid UTF-8 sequences of text strings with U+FFFD instead of rejecting the
+    /// payload, e.g. for ingestion endpoints fed by devices with buggy encoders. By default such
+    /// payloads are rejected
+    ///
+    /// Bodies spooled to disk (see [`spool_to_disk`](#method.spool_to_disk)) are always decoded
+    /// strictly.
+    pub fn lossy_utf8(mut self, enabled: bool) -> Self {
+        self.lossy_utf8 = Some(enabled);
+        self
+    }
+
+    /// Check that `Cbor` payloads are well-formed while they are received, failing as soon as
+    /// the received prefix cannot start a valid item instead of buffering the whole body first.
+    /// Disabled by default
+    ///
+    /// Malformed headers and structures, and bytes following the item, fail with
+    /// `CborPayloadError::Malformed`. Strings and containers whose declared length cannot fit in
+    /// the limit fail with `CborPayloadError::Overflow`.
+    pub fn sniff(mut self, enabled: bool) -> Self {
+        self.sniff = Some(enabled);
+        self
+    }
+
+    /// Look up the messages of rejected payloads in a catalog, by error code and language tag
+    ///
+    /// When set and no error handler is, extraction errors are answered with a
+    /// [`CborRejection`](struct.CborRejection.html) whose message is the first one found for the
+    /// languages of the `Accept-Language` header, by decreasing quality. Each tag is looked up as
+    /// is, such as `fr-CA`, then by its primary language, such as `fr`. The English message is
+    /// used when the catalog has none.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::{CborConfig, CborErrorCode};
+    ///
+    /// let config = CborConfig::default().messages(|code, lang| match (code, lang) {
+    ///     (CborErrorCode::Overflow, "fr") => Some("Le contenu est trop volumineux".to_owned()),
+    ///     (CborErrorCode::DecodeError, "fr") => Some("Le contenu est invalide".to_owned()),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn messages<F>(mut self, catalog: F) -> Self
+    where
+        F: Fn(CborErrorCode, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.messages = Some(Arc::new(catalog));
+        self
+    }
+
+    /// Keep the raw body of CBOR payloads in the request extensions, so extractors running after
+    /// the first one decode it again instead of failing on a consumed payload
+    ///
+    /// The raw body can also be read with
+    /// [`HttpRequestExt::cbor_raw_body`](trait.HttpRequestExt.html#tymethod.cbor_raw_body), for
+    /// example to verify a signature of the payload.
+    pub fn tee(mut self, enabled: bool) -> Self {
+        self.tee = Some(enabled);
+        self
+    }
+
+    /// Spool [`CborSpooled`](struct.CborSpooled.html) payloads bigger than `limit`, up to
+    /// `spool_limit` bytes, to a temporary file and decode them from there, instead of buffering
+    /// them in memory
+    ///
+    /// Occasional large uploads are then accepted without raising the memory limit. Spooled
+    /// payloads are not kept for [`tee`](#method.tee), and the file is removed once decoded on
+    /// the blocking thread pool. Other extractors, `Cbor` included, keep rejecting payloads over
+    /// the limit.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::default().limit(256 << 10).spool_to_disk(64 << 20);
+    /// ```
+    pub fn spool_to_disk(mut self, spool_limit: usize) -> Self {
+        self.spool_limit = Some(spool_limit);
+        self
+    }
+
+    /// Call `hook` with the total number of bytes received each time a chunk of a CBOR payload
+    /// is buffered, e.g. to report the progress of large uploads
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::default().on_progress(|received, req| {
+    ///     log::debug!("{}: {} bytes received", req.path(), received);
+    /// });
+    /// ```
+    pub fn on_progress<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(usize, &HttpRequest) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the content type of the error bodies rendered by this crate, `application/cbor` by
+    /// default
+    ///
+    /// Applies to [`cbor_error_handler`](fn.cbor_error_handler.html), localized rejections (see
+    /// [`messages`](#method.messages)) and the handlers of
+    /// [`cbor_handler`](attr.cbor_handler.html). Bodies keep the shape of a
+    /// [`CborErrorBody`](struct.CborErrorBody.html), except for `text/*` types whose body is the
+    /// error message.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::default()
+    ///     .error_content_type("application/concise-problem-details+cbor".parse().unwrap());
+    /// ```
+    pub fn error_content_type(mut self, mime: mime::Mime) -> Self {
+        self.error_content_type = Some(mime);
+        self
+    }
+
+    /// Label the payloads of the routes using this configuration, e.g. with the name of their
+    /// operation
+    ///
+    /// The label is part of the failed extraction and [`CborSampleLog`](struct.CborSampleLog.html)
+    /// log records, and is read by hooks with
+    /// [`HttpRequestExt::cbor_metric_label`](trait.HttpRequestExt.html#tymethod.cbor_metric_label),
+    /// so metrics can be broken down per endpoint.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::{CborConfig, HttpRequestExt};
+    /// use actix_web::{web, App};
+    ///
+    /// let app = App::new().service(
+    ///     web::resource("/orders")
+    ///         .app_data(CborConfig::default().metric_label("create_order").on_progress(
+    ///             |received, req| {
+    ///                 let label = req.cbor_metric_label().unwrap_or("unlabeled");
+    ///                 log::debug!("{}: {} bytes received", label, received);
+    ///             },
+    ///         ))
+    ///         .route(web::post().to(|| async { "created" })),
+    /// );
+    /// ```
+    pub fn metric_label(mut self, label: &'static str) -> Self {
+        self.metric_label = Some(label);
+        self
+    }
+
+    /// Fill the settings left unset with those of `parent`, merging configurations by hand.
+    ///
+    /// Extractors merge a `CborConfig` registered with `app_data` over one registered as
+    /// `Data<CborConfig>`, but actix-web only exposes the innermost value of each type: a
+    /// `CborConfig` registered on a scope hides the one of the app. Nested configurations
+    /// either inherit their parent explicitly, or are registered with a
+    /// [`CborConfigLayer`](struct.CborConfigLayer.html) which inherits it on each request:
+    ///
+    /// ```
+    /// use actix_cbor::CborConfig;
+    /// use actix_web::{web, App};
+    ///
+    /// let app_config = CborConfig::default()
+    ///     .limit(4096)
+    ///     .error_handler(|err, _| err.into());
+    ///
+    /// let app = App::new().app_data(app_config.clone()).service(
+    ///     // only raises the limit, keeping the error handler
+    ///     web::scope("/bulk").app_data(CborConfig::default().limit(1 << 20).inherit(&app_config)),
+    /// );
+    /// ```
+    pub fn inherit(mut self, parent: &CborConfig) -> Self {
+        self.limit = self.limit.or(parent.limit);
+        for (method, limit) in &parent.method_limits {
+            if self.method_limits.iter().all(|(m, _)| m != method) {
+                self.method_limits.push((method.clone(), *limit));
+            }
+        }
+        self.err_handler = self.err_handler.or_else(|| parent.err_handler.clone());
+        self.content_type = self.content_type.or_else(|| parent.content_type.clone());
+        self.schema_registry = self
+            .schema_registry
+            .or_else(|| parent.schema_registry.clone());
+        self.require_schema_id = self.require_schema_id.or(parent.require_schema_id);
+        self.etag = self.etag.or(parent.etag);
+        self.return_minimal = self.return_minimal.or(parent.return_minimal);
+        self.response_limit = self.response_limit.or(parent.response_limit);
+        self.deny_unknown_fields = self.deny_unknown_fields.or(parent.deny_unknown_fields);
+        self.messages = self.messages.or_else(|| parent.messages.clone());
+        self.tee = self.tee.or(parent.tee);
+        self.spool_limit = self.spool_limit.or(parent.spool_limit);
+        self.progress = self.progress.or_else(|| parent.progress.clone());
+        self.error_content_type = self
+            .error_content_type
+            .or_else(|| parent.error_content_type.clone());
+        self.metric_label = self.metric_label.or(parent.metric_label);
+        self.lossy_utf8 = self.lossy_utf8.or(parent.lossy_utf8);
+        self.compress_threshold = self.compress_threshold.or(parent.compress_threshold);
+        self.sniff = self.sniff.or(parent.sniff);
+        self
+    }
+
+    /// Read settings from the environment, leaving unset the settings whose variable is missing.
+    ///
+    /// | Variable | Setting |
+    /// |---|---|
+    /// | `ACTIX_CBOR_LIMIT` | [`limit`](#method.limit), as a size |
+    /// | `ACTIX_CBOR_RESPONSE_LIMIT` | [`response_limit`](#method.response_limit), as a size |
+    /// | `ACTIX_CBOR_ETAG` | [`etag`](#method.etag), as a boolean |
+    /// | `ACTIX_CBOR_RETURN_MINIMAL` | [`return_minimal`](#method.return_minimal), as a boolean |
+    /// | `ACTIX_CBOR_STRICT` | [`deny_unknown_fields`](#method.deny_unknown_fields), as a boolean |
+    ///
+    /// Sizes are a number of bytes with an optional unit, such as `32768`, `512kB` or `2MiB`.
+    /// Booleans are spelled `true`, `false`, `1`, `0`, `yes`, `no`, `on` or `off`. Invalid values
+    /// are reported with the name of the variable.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// let config = CborConfig::from_env()
+    ///     .unwrap_or_else(|e| panic!("{}", e))
+    ///     .inherit(&CborConfig::default().limit(4096));
+    /// ```
+    pub fn from_env() -> Result<Self, CborEnvError> {
+        Self::from_lookup(|var| env::var_os(var).map(|value| value.to_string_lossy().into_owned()))
+    }
+
+    /// Read settings from variables returned by `lookup`.
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, CborEnvError> {
+        Ok(CborConfig {
+            limit: parse_var(&lookup, "ACTIX_CBOR_LIMIT", SIZE_VALUES, parse_size)?,
+            response_limit: parse_var(
+                &lookup,
+                "ACTIX_CBOR_RESPONSE_LIMIT",
+                SIZE_VALUES,
+                parse_size,
+            )?,
+            etag: parse_var(&lookup, "ACTIX_CBOR_ETAG", BOOL_VALUES, parse_bool)?,
+            return_minimal: parse_var(
+                &lookup,
+                "ACTIX_CBOR_RETURN_MINIMAL",
+                BOOL_VALUES,
+                parse_bool,
+            )?,
+            deny_unknown_fields: parse_var(&lookup, "ACTIX_CBOR_STRICT", BOOL_VALUES, parse_bool)?,
+            ..CborConfig::default()
+        })
+    }
+
+    /// Set the process-wide configuration, whose settings apply wherever no app data config
+    /// sets them.
+    ///
+    /// The global configuration can only be set once, preferably at startup. Later calls return
+    /// the rejected configuration.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::CborConfig;
+    ///
+    /// CborConfig::set_global_default(CborConfig::default().limit(1 << 20))
+    ///     .unwrap_or_else(|_| panic!("global cbor config already set"));
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn set_global_default(config: CborConfig) -> Result<(), CborConfig> {
+        GLOBAL_CONFIG.set(config)
+    }
+
+    /// Max size of payload for `req`, according to its method.
+    pub(crate) fn limit_for(&self, req: &HttpRequest) -> usize {
+        self.method_limits
+            .iter()
+            .find(|(method, _)| method == req.method())
+            .map(|&(_, limit)| limit)
+            .or(self.limit)
+            .unwrap_or(DEFAULT_LIMIT)
+    }
+
+    /// Extract payload config from app data. Merge the config of a `CborConfigResolver` over `T`
+    /// over `Data<T>` over the current value of a `CborConfigHandle` over the global config, and
+    /// fall back to the default payload config.
+    pub(crate) fn from_req(req: &HttpRequest) -> Cow<'_, Self> {
+        let config = req.app_data::<Self>();
+        let data = req.app_data::<web::Data<Self>>().map(|d| d.as_ref());
+
+        let resolved = CborConfigResolver::from_req(req).map(|resolver| (resolver.0)(req));
+        let snapshot = CborConfigHandle::from_req(req).map(CborConfigHandle::get);
+        if resolved.is_none() && snapshot.is_none() {
+            return Self::merge_layers(&[config, data, GLOBAL_CONFIG.get()]);
+        }
+
+        let layers = [
+            resolved.as_deref(),
+            config,
+            data,
+            snapshot.as_deref(),
+            GLOBAL_CONFIG.get(),
+        ];
+        Cow::Owned(Self::merge_layers(&layers).into_owned())
+    }
+
+    /// Merged `CborConfig` and `Data<CborConfig>` app data of `req`.
+    pub(crate) fn from_app_data(req: &HttpRequest) -> Cow<'_, Self> {
+        let data = req.app_data::<web::Data<Self>>().map(|d| d.as_ref());
+        Self::merge_layers(&[req.app_data::<Self>(), data])
+    }
+
+    /// Content type predicate of the configuration a guard sees: that of `T` over `Data<T>`
+    /// over the current value of a `CborConfigHandle` over the global config. Guards have no
+    /// `HttpRequest` to pass to a `CborConfigResolver`, which is not consulted.
+    pub(crate) fn content_type_for_guard(ctx: &GuardContext<'_>) -> Option<ContentTypePredicate> {
+        let handle = ctx.app_data::<CborConfigHandle>().or_else(|| {
+            ctx.app_data::<web::Data<CborConfigHandle>>()
+                .map(|d| d.as_ref())
+        });
+
+        ctx.app_data::<Self>()
+            .and_then(|config| config.content_type.clone())
+            .or_else(|| {
+                ctx.app_data::<web::Data<Self>>()
+                    .and_then(|config| config.content_type.clone())
+            })
+            .or_else(|| handle.and_then(|handle| handle.get().content_type.clone()))
+            .or_else(|| {
+                GLOBAL_CONFIG
+                    .get()
+                    .and_then(|config| config.content_type.clone())
+            })
+    }
+
+    /// Merge the present `layers`, innermost first.
+    fn merge_layers<'a>(layers: &[Option<&'a Self>]) -> Cow<'a, Self> {
+        let mut layers = layers.iter().flatten().copied();
+
+        let config = match layers.next() {
+            Some(config) => config,
+            None => return Cow::Borrowed(&DEFAULT_CONFIG),
+        };
+        match layers.next() {
+            Some(parent) => Cow::Owned(
+                layers.fold(config.clone().inherit(parent), |config, parent| {
+                    config.inherit(parent)
+                }),
+            ),
+            None => Cow::Borrowed(config),
+        }
+    }
+}
+
+/// Shared function choosing the config of a request.
+type ResolverFn = dyn for<'a> Fn(&'a HttpRequest) -> Cow<'a, CborConfig> + Send + Sync;
+
+/// Function choosing the `CborConfig` of each request, for example from a tenant header.
+///
+/// Register the resolver as app data. The settings of the config it returns take precedence over
+/// those of the `CborConfig` app data.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborConfig, CborConfigResolver};
+/// use actix_web::App;
+/// use std::borrow::Cow;
+///
+/// let resolver = CborConfigResolver::new(|req| {
+///     match req.headers().get("x-tenant").map(|tenant| tenant.as_bytes()) {
+///         Some(b"bulk-importer") => Cow::Owned(CborConfig::default().limit(16 << 20)),
+///         _ => Cow::Owned(CborConfig::default()),
+///     }
+/// });
+/// let app = App::new().app_data(resolver);
+/// ```
+#[derive(Clone)]
+pub struct CborConfigResolver(Arc<ResolverFn>);
+
+impl CborConfigResolver {
+    /// Create a resolver calling `f` for each request.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: for<'a> Fn(&'a HttpRequest) -> Cow<'a, CborConfig> + Send + Sync + 'static,
+    {
+        CborConfigResolver(Arc::new(f))
+    }
+
+    /// Extract the resolver from app data. Check both `T` and `Data<T>`, in that order.
+    fn from_req(req: &HttpRequest) -> Option<&Self> {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+    }
+}
+
+/// Shared handle to a `CborConfig` that can be replaced while the server runs.
+///
+/// Register the handle as app data and keep a clone of it, for example in an admin endpoint, to
+/// change the settings of the following requests.
+///
+/// The settings of a request are looked up, in order of precedence, in a
+/// [`CborConfigResolver`](struct.CborConfigResolver.html), the `CborConfig` and
+/// `Data<CborConfig>` app data, the handle, and the global default config. The handle thus only
+/// provides the settings the static configurations leave unset: do not register a `CborConfig`
+/// setting those which should be reloadable.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborConfig, CborConfigHandle};
+/// use actix_web::{web, App, HttpResponse};
+///
+/// async fn set_limit(handle: web::Data<CborConfigHandle>, limit: web::Path<usize>) -> HttpResponse {
+///     handle.update(|config| config.limit(*limit));
+///     HttpResponse::NoContent().finish()
+/// }
+///
+/// let handle = CborConfigHandle::new(CborConfig::default().limit(4096));
+/// let app = App::new()
+///     .app_data(web::Data::new(handle))
+///     .route("/admin/cbor/limit/{limit}", web::put().to(set_limit));
+/// ```
+#[derive(Clone)]
+pub struct CborConfigHandle(Arc<RwLock<Arc<CborConfig>>>);
+
+impl CborConfigHandle {
+    /// Create a handle holding `config`.
+    pub fn new(config: CborConfig) -> Self {
+        CborConfigHandle(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    /// Current configuration, shared with the requests using it.
+    pub fn get(&self) -> Arc<CborConfig> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Replace the configuration.
+    pub fn set(&self, config: CborConfig) {
+        *self.0.write().unwrap() = Arc::new(config);
+    }
+
+    /// Replace the configuration with the result of `f`.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(CborConfig) -> CborConfig,
+    {
+        let mut config = self.0.write().unwrap();
+        *config = Arc::new(f(CborConfig::clone(&config)));
+    }
+
+    /// Extract the handle from app data. Check both `T` and `Data<T>`, in that order.
+    fn from_req(req: &HttpRequest) -> Option<&Self> {
         req.app_data::<Self>()
             .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
-            .unwrap_or_else(|| &DEFAULT_CONFIG)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,90 @@
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Extensions, Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::{ok, Ready};
+
+use crate::CborConfig;
+
+/// Middleware registering a [`CborConfig`](struct.CborConfig.html) on a scope or resource over
+/// the configuration of the enclosing app and scopes.
+///
+/// actix-web only exposes the innermost app data of each type, so a `CborConfig` registered
+/// with `app_data` on a scope hides the one of the app. Wrapped in a layer, the config only
+/// overrides the settings it sets: on each request, it inherits the `CborConfig` seen by the
+/// enclosing service, itself possibly set by an outer layer, and the merged config is
+/// registered for the wrapped services.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborConfig, CborConfigLayer};
+/// use actix_web::{web, App};
+///
+/// async fn import(items: Cbor<Vec<u32>>) -> String {
+///     format!("{} items", items.len())
+/// }
+///
+/// let app = App::new()
+///     .app_data(CborConfig::default().limit(4096).error_handler(|err, _| err.into()))
+///     .service(
+///         // only raises the limit, keeping the error handler of the app
+///         web::scope("/bulk")
+///             .wrap(CborConfigLayer::new(CborConfig::default().limit(1 << 20)))
+///             .route("/import", web::post().to(import)),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct CborConfigLayer(Rc<CborConfig>);
+
+impl CborConfigLayer {
+    /// Register the settings of `config` over those of the enclosing services.
+    pub fn new(config: CborConfig) -> Self {
+        CborConfigLayer(Rc::new(config))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CborConfigLayer
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = CborConfigLayerMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CborConfigLayerMiddleware {
+            service,
+            config: Rc::clone(&self.0),
+        })
+    }
+}
+
+/// Service created by the [`CborConfigLayer`](struct.CborConfigLayer.html) middleware.
+pub struct CborConfigLayerMiddleware<S> {
+    service: S,
+    config: Rc<CborConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for CborConfigLayerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let config =
+            CborConfig::clone(&self.config).inherit(&CborConfig::from_app_data(req.request()));
+        let mut container = Extensions::new();
+        container.insert(config);
+        req.add_data_container(Rc::new(container));
+        self.service.call(req)
+    }
+}
@@ -0,0 +1,76 @@
+//! CBOR diagnostic notation, as described in RFC 8949 section 8.
+
+use std::fmt::Write;
+
+use serde_cbor::Value;
+
+/// Append the diagnostic notation of `value` to `out`.
+pub(crate) fn write_diagnostic(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        Value::Float(f) if f.is_nan() => out.push_str("NaN"),
+        Value::Float(f) if f.is_infinite() => {
+            out.push_str(if *f > 0.0 { "Infinity" } else { "-Infinity" })
+        }
+        Value::Float(f) => {
+            let _ = write!(out, "{:?}", f);
+        }
+        Value::Bytes(bytes) => {
+            out.push_str("h'");
+            for b in bytes {
+                let _ = write!(out, "{:02x}", b);
+            }
+            out.push('\'');
+        }
+        Value::Text(text) => {
+            let _ = write!(out, "{:?}", text);
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_diagnostic(item, out);
+            }
+            out.push(']');
+        }
+        Value::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_diagnostic(key, out);
+                out.push_str(": ");
+                write_diagnostic(value, out);
+            }
+            out.push('}');
+        }
+        Value::Tag(tag, value) => {
+            let _ = write!(out, "{}(", tag);
+            write_diagnostic(value, out);
+            out.push(')');
+        }
+        _ => out.push_str("undefined"),
+    }
+}
+
+/// Diagnostic notation of `value`, cut to at most `max_len` bytes followed by `...`.
+pub(crate) fn diagnostic(value: &Value, max_len: usize) -> String {
+    let mut out = String::new();
+    write_diagnostic(value, &mut out);
+    if out.len() > max_len {
+        let mut end = max_len;
+        while !out.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.truncate(end);
+        out.push_str("...");
+    }
+    out
+}
@@ -1,10 +1,93 @@
-use actix_web::{body::Body, error::PayloadError, http::StatusCode, HttpResponse, ResponseError};
+use actix_web::{
+    body::BoxBody, error::PayloadError, http::StatusCode, HttpResponse, ResponseError,
+};
 use std::error::Error;
 use std::fmt;
 
 #[derive(Debug)]
 pub struct CborError(serde_cbor::Error);
 
+/// Invalid value of a configuration environment variable or setting, see
+/// [`CborConfig::from_env`](struct.CborConfig.html#method.from_env) and
+/// [`CborConfig::limit_str`](struct.CborConfig.html#method.limit_str).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborEnvError {
+    /// Name of the variable, or of the setting
+    pub var: &'static str,
+    /// Value of the variable, lossily decoded
+    pub value: String,
+    /// Description of the values accepted by the variable
+    pub expected: &'static str,
+}
+
+impl fmt::Display for CborEnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid value {:?} for {}: expected {}",
+            self.value, self.var, self.expected
+        )
+    }
+}
+
+impl Error for CborEnvError {}
+
+/// Failure to transcode a document between JSON and CBOR, see
+/// [`transcode_json_to_cbor`](fn.transcode_json_to_cbor.html).
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum CborTranscodeError {
+    /// JSON side of the transcoding error
+    Json(serde_json::Error),
+    /// CBOR side of the transcoding error
+    Cbor(CborError),
+    /// Streamed body could not be read
+    Payload(PayloadError),
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for CborTranscodeError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_cbor::Error> for CborTranscodeError {
+    fn from(e: serde_cbor::Error) -> Self {
+        Self::Cbor(e.into())
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<PayloadError> for CborTranscodeError {
+    fn from(e: PayloadError) -> Self {
+        Self::Payload(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for CborTranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CborTranscodeError::Json(inner) => write!(f, "JSON transcoding error: {}", inner),
+            CborTranscodeError::Cbor(inner) => write!(f, "CBOR transcoding error: {}", inner),
+            CborTranscodeError::Payload(inner) => write!(f, "Payload error: {}", inner),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl Error for CborTranscodeError {}
+
+/// Return `BadRequest` for `CborTranscodeError`, as the transcoded document is at fault
+#[cfg(feature = "json")]
+impl ResponseError for CborTranscodeError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 #[derive(Debug)]
 pub enum CborPayloadError {
     /// Payload size is bigger than allowed. (default: 32kB)
@@ -13,8 +96,31 @@ pub enum CborPayloadError {
     ContentType,
     /// Deserialize error
     Deserialize(CborError),
+    /// Serialize error
+    Serialize(CborError),
+    /// Serialized response is bigger than allowed
+    ResponseOverflow,
     /// Payload error
     Payload(PayloadError),
+    /// Body does not match the schema declared by the request
+    Schema(String),
+    /// Payload is not well-formed CBOR, starting at the given byte offset
+    Malformed(usize),
+    /// Payload holds a map key not consumed by the target type, at the given path
+    UnknownField(String),
+    /// Payload version is missing, or not supported
+    UnsupportedVersion(Option<u64>),
+    /// Request has no body, such as most `GET` requests
+    NoBody,
+    /// Multipart error
+    #[cfg(feature = "multipart")]
+    Multipart(actix_multipart::MultipartError),
+    /// `minicbor` decode error
+    #[cfg(feature = "minicbor")]
+    MiniDecode(minicbor::decode::Error),
+    /// MessagePack decode error
+    #[cfg(feature = "msgpack")]
+    Msgpack(rmp_serde::decode::Error),
 }
 
 impl From<CborError> for CborPayloadError {
@@ -35,6 +141,27 @@ impl From<PayloadError> for CborPayloadError {
     }
 }
 
+#[cfg(feature = "multipart")]
+impl From<actix_multipart::MultipartError> for CborPayloadError {
+    fn from(e: actix_multipart::MultipartError) -> Self {
+        Self::Multipart(e)
+    }
+}
+
+#[cfg(feature = "minicbor")]
+impl From<minicbor::decode::Error> for CborPayloadError {
+    fn from(e: minicbor::decode::Error) -> Self {
+        Self::MiniDecode(e)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::decode::Error> for CborPayloadError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        Self::Msgpack(e)
+    }
+}
+
 impl fmt::Display for CborPayloadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -43,22 +170,62 @@ impl fmt::Display for CborPayloadError {
             CborPayloadError::Deserialize(inner) => {
                 writeln!(f, "CBOR deserialize error: {}", inner)
             }
+            CborPayloadError::Serialize(inner) => {
+                writeln!(f, "CBOR serialize error: {}", inner)
+            }
+            CborPayloadError::ResponseOverflow => {
+                writeln!(f, "Cbor response size is bigger than allowed")
+            }
             CborPayloadError::Payload(inner) => {
                 writeln!(f, "Error that occur during reading payload: {:?}", inner)
             }
+            CborPayloadError::Schema(inner) => {
+                writeln!(f, "CBOR schema validation failed: {}", inner)
+            }
+            CborPayloadError::Malformed(offset) => {
+                writeln!(f, "Malformed CBOR payload at byte {}", offset)
+            }
+            CborPayloadError::UnknownField(path) => {
+                writeln!(f, "Unknown field in CBOR payload: {}", path)
+            }
+            CborPayloadError::UnsupportedVersion(Some(version)) => {
+                writeln!(f, "Unsupported CBOR payload version: {}", version)
+            }
+            CborPayloadError::UnsupportedVersion(None) => {
+                writeln!(f, "Missing CBOR payload version")
+            }
+            CborPayloadError::NoBody => writeln!(f, "Request has no CBOR payload"),
+            #[cfg(feature = "multipart")]
+            CborPayloadError::Multipart(inner) => {
+                writeln!(
+                    f,
+                    "Error that occur during reading multipart field: {}",
+                    inner
+                )
+            }
+            #[cfg(feature = "minicbor")]
+            CborPayloadError::MiniDecode(inner) => {
+                writeln!(f, "CBOR decode error: {}", inner)
+            }
+            #[cfg(feature = "msgpack")]
+            CborPayloadError::Msgpack(inner) => {
+                writeln!(f, "MessagePack deserialize error: {}", inner)
+            }
         }
     }
 }
 
 impl Error for CborPayloadError {}
 
-/// Return `BadRequest` for `CborPayloadError`
+/// Respond with the status of the [`code`](#method.code) of the error, `BadRequest` for most of
+/// them
 impl ResponseError for CborPayloadError {
-    fn error_response(&self) -> HttpResponse<Body> {
-        match *self {
-            CborPayloadError::Overflow => HttpResponse::new(StatusCode::PAYLOAD_TOO_LARGE),
-            _ => HttpResponse::new(StatusCode::BAD_REQUEST),
-        }
+    fn status_code(&self) -> StatusCode {
+        self.code().status()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        HttpResponse::new(self.status_code())
     }
 }
 
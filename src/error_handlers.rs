@@ -0,0 +1,201 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::ServiceResponse,
+    http::{
+        header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE},
+        StatusCode,
+    },
+    middleware::{ErrorHandlerResponse, ErrorHandlers},
+    HttpRequest, HttpResponse,
+};
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+
+use crate::{CborConfig, CborErrorCode, CBOR_CONTENT_TYPE_VALUE};
+
+/// CBOR body of error responses: `{"status": 404, "error": "Not Found"}`, plus a `code` for
+/// rejected payloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CborErrorBody {
+    /// Status code of the response
+    pub status: u16,
+    /// Description of the error
+    pub error: String,
+    /// Machine-readable code of the error, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CborErrorCode>,
+}
+
+impl CborErrorBody {
+    /// Error body for `status`, described by `error`.
+    pub fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        Self {
+            status: status.as_u16(),
+            error: error.into(),
+            code: None,
+        }
+    }
+
+    /// Set the machine-readable code of the error.
+    pub fn code(mut self, code: CborErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+}
+
+/// Configured content type of the error bodies answering `req`, if any.
+pub(crate) fn error_content_type(req: Option<&HttpRequest>) -> Option<Mime> {
+    req.and_then(|req| CborConfig::from_req(req).error_content_type.clone())
+}
+
+/// Response of `status` carrying `body`, as `content_type` or `application/cbor`.
+pub(crate) fn error_body_response(
+    status: StatusCode,
+    body: &CborErrorBody,
+    content_type: Option<Mime>,
+) -> HttpResponse {
+    let mut res = HttpResponse::build(status);
+    match content_type {
+        Some(mime) if mime.type_() == mime::TEXT => res
+            .insert_header((CONTENT_TYPE, mime.to_string()))
+            .body(body.error.clone()),
+        content_type => match serde_cbor::to_vec(body) {
+            Ok(bytes) => {
+                match content_type {
+                    Some(mime) => res.insert_header((CONTENT_TYPE, mime)),
+                    None => res.insert_header((CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone())),
+                };
+                res.body(bytes)
+            }
+            Err(_) => HttpResponse::new(status),
+        },
+    }
+}
+
+/// Whether `headers` describe an error body already rendered by this crate, with the default or
+/// the configured content type.
+fn is_error_body(headers: &HeaderMap, content_type: Option<&Mime>) -> bool {
+    if crate::is_cbor_content_type_header(headers) {
+        return true;
+    }
+    match (content_type, headers.get(CONTENT_TYPE)) {
+        (Some(mime), Some(value)) => mime.type_() != mime::TEXT && value == mime.as_ref(),
+        _ => false,
+    }
+}
+
+/// Plain text carried by an error response, if any.
+fn error_text(res: &HttpResponse<()>, body: BoxBody) -> Option<String> {
+    let is_text = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/plain"));
+
+    match body.try_into_bytes() {
+        Ok(bytes) if is_text && !bytes.is_empty() => {
+            Some(String::from_utf8_lossy(&bytes).trim_end().to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// `ErrorHandlers` handler replacing the body of an error response with a
+/// [`CborErrorBody`](struct.CborErrorBody.html).
+///
+/// The plain text body of extractor errors becomes the `error` field. Other bodies are replaced
+/// by the canonical reason of the status. Responses which already are CBOR are left untouched.
+///
+/// # Example
+/// ```
+/// use actix_cbor::cbor_error_handler;
+/// use actix_web::{http::StatusCode, middleware::ErrorHandlers, web, App};
+///
+/// let app = App::new()
+///     .wrap(ErrorHandlers::new().handler(StatusCode::NOT_FOUND, cbor_error_handler))
+///     .route("/", web::get().to(|| async { "hello" }));
+/// ```
+pub fn cbor_error_handler(
+    res: ServiceResponse<BoxBody>,
+) -> actix_web::Result<ErrorHandlerResponse<BoxBody>> {
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let content_type = error_content_type(Some(&req));
+    if is_error_body(res.headers(), content_type.as_ref()) {
+        let res = ServiceResponse::new(req, res.set_body(body));
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let status = res.status();
+    let error = error_text(&res, body)
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_owned());
+    let (rendered, body) =
+        error_body_response(status, &CborErrorBody::new(status, error), content_type).into_parts();
+
+    let headers = res.headers_mut();
+    headers.remove(CONTENT_LENGTH);
+    if let Some(content_type) = rendered.headers().get(CONTENT_TYPE) {
+        headers.insert(CONTENT_TYPE, content_type.clone());
+    }
+    let res = ServiceResponse::new(req, res.set_body(body));
+    Ok(ErrorHandlerResponse::Response(res.map_into_left_body()))
+}
+
+/// Response to `err` whose body is a [`CborErrorBody`](struct.CborErrorBody.html), described by
+/// the `Display` of the error.
+///
+/// Errors which already respond with CBOR, such as
+/// [`CborRejection`](struct.CborRejection.html), keep their response.
+pub fn cbor_error_response(err: impl Into<actix_web::Error>) -> HttpResponse {
+    error_response_to(err.into(), None)
+}
+
+/// Response to `err` as [`cbor_error_response`](fn.cbor_error_response.html), with the
+/// configured error content type of `req`.
+pub(crate) fn error_response_to(err: actix_web::Error, req: Option<&HttpRequest>) -> HttpResponse {
+    let res = err.error_response();
+    let content_type = error_content_type(req);
+    if is_error_body(res.headers(), content_type.as_ref()) {
+        return res;
+    }
+
+    let status = res.status();
+    let mut error = err.to_string();
+    if error.is_empty() {
+        error = status.canonical_reason().unwrap_or_default().to_owned();
+    }
+    error_body_response(status, &CborErrorBody::new(status, error), content_type)
+}
+
+/// `ErrorHandlers` rendering the client and server errors raised by actix itself as CBOR, with
+/// [`cbor_error_handler`](fn.cbor_error_handler.html).
+///
+/// Covers `400`, `404`, `405`, `406`, `411`, `413`, `415`, `422` and `500`. More statuses can be
+/// registered on the returned middleware.
+///
+/// # Example
+/// ```
+/// use actix_cbor::cbor_error_handlers;
+/// use actix_web::{web, App};
+///
+/// let app = App::new()
+///     .wrap(cbor_error_handlers())
+///     .route("/", web::get().to(|| async { "hello" }));
+/// ```
+pub fn cbor_error_handlers() -> ErrorHandlers<BoxBody> {
+    [
+        StatusCode::BAD_REQUEST,
+        StatusCode::NOT_FOUND,
+        StatusCode::METHOD_NOT_ALLOWED,
+        StatusCode::NOT_ACCEPTABLE,
+        StatusCode::LENGTH_REQUIRED,
+        StatusCode::PAYLOAD_TOO_LARGE,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        StatusCode::INTERNAL_SERVER_ERROR,
+    ]
+    .iter()
+    .fold(ErrorHandlers::new(), |handlers, &status| {
+        handlers.handler(status, cbor_error_handler)
+    })
+}
@@ -0,0 +1,280 @@
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    error::InternalError,
+    http::{
+        header::{ETag, EntityTag, Header, IfMatch, IfNoneMatch, IF_MATCH},
+        Method,
+    },
+    web::Bytes,
+    FromRequest, HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::{ready, Ready};
+use log::error;
+use serde::Serialize;
+
+use crate::head::cbor_body;
+use crate::{CborError, CborProfile};
+
+/// Incremental 64-bit hash of ETags and cache keys: FNV-1a, or XXH3 with the `xxhash` feature.
+#[cfg(not(feature = "xxhash"))]
+pub(crate) struct BodyHasher(u64);
+
+#[cfg(not(feature = "xxhash"))]
+impl BodyHasher {
+    pub(crate) fn new() -> Self {
+        BodyHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Incremental 64-bit hash of ETags and cache keys: FNV-1a, or XXH3 with the `xxhash` feature.
+#[cfg(feature = "xxhash")]
+pub(crate) struct BodyHasher(xxhash_rust::xxh3::Xxh3);
+
+#[cfg(feature = "xxhash")]
+impl BodyHasher {
+    pub(crate) fn new() -> Self {
+        BodyHasher(xxhash_rust::xxh3::Xxh3::new())
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0.digest()
+    }
+}
+
+/// 64-bit hash of `bytes`, see [`BodyHasher`].
+pub(crate) fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = BodyHasher::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Strong entity tag identifying an encoded body.
+pub(crate) fn etag_for_bytes(bytes: &[u8]) -> EntityTag {
+    EntityTag::new_strong(format!("{:016x}", hash_bytes(bytes)))
+}
+
+/// Encodings whose name suffixes the `ETag` of compressed responses.
+const ENCODING_SUFFIXES: &[&str] = &["-br", "-zstd", "-gzip"];
+
+/// Tag of the `encoding` of the representation tagged `etag`, e.g. `"1a2b-gzip"`, so caches do
+/// not mistake it for the identity one.
+#[cfg(feature = "compress")]
+pub(crate) fn encoded_etag(etag: &EntityTag, encoding: &str) -> EntityTag {
+    EntityTag::new(etag.weak, format!("{}-{}", etag.tag(), encoding))
+}
+
+/// Profiles whose name suffixes the `ETag` of the responses they encode.
+const PROFILE_SUFFIXES: &[&str] = &["-packed", "-canonical"];
+
+/// `etag` without any of `suffixes`.
+fn without_suffix(etag: &EntityTag, suffixes: &[&str]) -> EntityTag {
+    let tag = etag.tag();
+    match suffixes.iter().find_map(|suffix| tag.strip_suffix(suffix)) {
+        Some(tag) => EntityTag::new(etag.weak, tag.to_owned()),
+        None => etag.clone(),
+    }
+}
+
+/// `etag` without the suffix of a compressed representation, which stands for the same value.
+fn without_encoding(etag: &EntityTag) -> EntityTag {
+    without_suffix(etag, ENCODING_SUFFIXES)
+}
+
+/// Tag of the value `etag` was derived from, without its encoding and profile suffixes.
+fn value_tag(etag: &EntityTag) -> EntityTag {
+    without_suffix(&without_encoding(etag), PROFILE_SUFFIXES)
+}
+
+/// Strong entity tag of `value` encoded with `profile`: the [`cbor_etag`] of the value, with the
+/// name of the profile as a suffix, e.g. `"1a2b-packed"`. `body` is the encoded value, reused
+/// when it is canonical already.
+pub(crate) fn profile_etag<T: Serialize>(
+    value: &T,
+    body: &[u8],
+    profile: Option<CborProfile>,
+) -> Result<EntityTag, CborError> {
+    let etag = match profile {
+        Some(CborProfile::Canonical) => etag_for_bytes(body),
+        _ => cbor_etag(value)?,
+    };
+    Ok(match profile {
+        Some(profile) => EntityTag::new_strong(format!("{}-{}", etag.tag(), profile.name())),
+        None => etag,
+    })
+}
+
+/// Whether a `GET` or `HEAD` request already holds the representation tagged `etag`.
+pub(crate) fn is_not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return false;
+    }
+
+    match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| without_encoding(tag).weak_eq(etag)),
+        Err(_) => false,
+    }
+}
+
+/// `200 OK` response to an encoded CBOR body tagged with its `ETag`, or `304 Not Modified`
+/// without a body when the client's `If-None-Match` matches.
+pub(crate) fn tagged_response(req: &HttpRequest, body: impl Into<Bytes>) -> HttpResponse {
+    let body = body.into();
+    let etag = etag_for_bytes(&body);
+    response_with_etag(req, body, etag)
+}
+
+/// Same as `tagged_response`, with an already computed `ETag`.
+pub(crate) fn response_with_etag(req: &HttpRequest, body: Bytes, etag: EntityTag) -> HttpResponse {
+    if is_not_modified(req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .finish();
+    }
+
+    cbor_body(req, HttpResponse::Ok().insert_header(ETag(etag)), body)
+}
+
+/// Encode `value` as canonical CBOR, with map keys sorted in length-first order.
+pub(crate) fn to_canonical_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, CborError> {
+    let value = serde_cbor::value::to_value(value)?;
+    Ok(serde_cbor::to_vec(&value)?)
+}
+
+/// Strong entity tag computed from the canonical CBOR encoding of `value`.
+///
+/// This is the tag of [`CborEtag`](struct.CborEtag.html) responses and of `Cbor` responses with
+/// [`CborConfig::etag`](struct.CborConfig.html#method.etag), so the `ETag` a client got from a
+/// `GET` satisfies the [`CborIfMatch`](struct.CborIfMatch.html) precondition of its update.
+/// Map keys are sorted first, so maps whose iteration order varies, such as `HashMap`, get the
+/// same tag in every process. Responses negotiating a [`CborProfile`](enum.CborProfile.html)
+/// carry this tag suffixed with the profile name.
+pub fn cbor_etag<T: Serialize>(value: &T) -> Result<EntityTag, CborError> {
+    Ok(etag_for_bytes(&to_canonical_vec(value)?))
+}
+
+/// Responder serializing `T` as CBOR along with a strong `ETag` hashed from the body.
+///
+/// `GET` and `HEAD` requests whose `If-None-Match` matches the tag get a `304 Not Modified`
+/// response without a body.
+///
+/// Usually created with [`Cbor::with_etag`](struct.Cbor.html#method.with_etag); set
+/// [`CborConfig::etag`](struct.CborConfig.html#method.etag) to tag every `Cbor` response.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborEtag};
+///
+/// async fn index() -> CborEtag<Vec<u32>> {
+///     Cbor(vec![1, 2, 3]).with_etag()
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CborEtag<T>(pub T);
+
+impl<T> CborEtag<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Serialize> Responder for CborEtag<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let encoded = serde_cbor::to_vec(&self.0).map_err(CborError::from);
+        match encoded.and_then(|body| Ok((cbor_etag(&self.0)?, body))) {
+            Ok((etag, body)) => response_with_etag(req, body.into(), etag),
+            Err(e) => {
+                error!("cbor serialization error: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Extractor for the `If-Match` precondition of a request, checked against the current state of
+/// the resource before modifying it.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborIfMatch};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn update(precondition: CborIfMatch, user: Cbor<User>) -> actix_web::Result<String> {
+///     let current = User {
+///         name: "John".to_owned(),
+///     };
+///     // 412 Precondition Failed when the client modified an outdated version
+///     precondition.check(&current)?;
+///     Ok(user.name.clone())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CborIfMatch(Option<IfMatch>);
+
+impl CborIfMatch {
+    /// Whether the precondition holds for a resource tagged `etag`.
+    ///
+    /// Requests without an `If-Match` header always match. Tags of compressed responses (see
+    /// [`CborConfig::compress_above`](struct.CborConfig.html#method.compress_above)) and of
+    /// negotiated profiles match the resource they encode.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match &self.0 {
+            None | Some(IfMatch::Any) => true,
+            Some(IfMatch::Items(tags)) => tags.iter().any(|tag| value_tag(tag).strong_eq(etag)),
+        }
+    }
+
+    /// Check the precondition against the current state of the resource, returning its tag.
+    ///
+    /// Fails with `412 Precondition Failed`, carrying the current `ETag`, on mismatch.
+    pub fn check<T: Serialize>(&self, current: &T) -> Result<EntityTag, actix_web::Error> {
+        let etag = cbor_etag(current)?;
+        if self.matches(&etag) {
+            return Ok(etag);
+        }
+
+        let response = HttpResponse::PreconditionFailed()
+            .insert_header(ETag(etag))
+            .finish();
+        Err(InternalError::from_response("If-Match precondition failed", response).into())
+    }
+}
+
+impl FromRequest for CborIfMatch {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if !req.headers().contains_key(IF_MATCH) {
+            return ready(Ok(CborIfMatch(None)));
+        }
+
+        ready(
+            IfMatch::parse(req)
+                .map(|condition| CborIfMatch(Some(condition)))
+                .map_err(Into::into),
+        )
+    }
+}
@@ -0,0 +1,244 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use actix_web::{dev::Payload, web::BytesMut, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, Ready};
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+
+use crate::scan::{read_head, Malformed};
+use crate::untagged;
+use crate::{
+    extraction_error, is_cbor_content_type, take_payload, BodyStream, CborConfig, CborPayloadError,
+    Framing, SeqFraming, CBOR_SEQ_CONTENT_TYPE,
+};
+
+/// How items are laid out in the body.
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    /// Top-level items delimited by the decoder's framing.
+    Framed,
+    /// Array header not read yet.
+    ArrayHeader,
+    /// Elements of an array, `None` meaning an indefinite one.
+    Array(Option<u64>),
+    /// Array closed, only the end of the body may follow.
+    ArrayEnd,
+}
+
+/// Splits a payload into CBOR items as they arrive, buffering at most one item at a time.
+pub(crate) struct ItemDecoder {
+    stream: BodyStream,
+    buf: BytesMut,
+    framing: Box<dyn Framing>,
+    layout: Layout,
+    limit: usize,
+    consumed: usize,
+    eof: bool,
+}
+
+impl ItemDecoder {
+    /// Decode the top-level items of a body delimited by `framing`.
+    pub(crate) fn framed(stream: BodyStream, framing: impl Framing, limit: usize) -> Self {
+        Self {
+            stream,
+            buf: BytesMut::new(),
+            framing: Box::new(framing),
+            layout: Layout::Framed,
+            limit,
+            consumed: 0,
+            eof: false,
+        }
+    }
+
+    /// Decode the elements of a CBOR array.
+    pub(crate) fn array(stream: BodyStream, limit: usize) -> Self {
+        Self {
+            layout: Layout::ArrayHeader,
+            ..Self::framed(stream, SeqFraming, limit)
+        }
+    }
+
+    /// Decode the next item, reading more of the payload as needed.
+    pub(crate) async fn next_item<T>(&mut self) -> Result<Option<T>, CborPayloadError>
+    where
+        T: DeserializeOwned,
+    {
+        loop {
+            if let Some(frame) = self.next_frame()? {
+                let chunk = self.buf.split_to(frame.end);
+                self.consumed += frame.end;
+                return Ok(Some(untagged::from_slice(&chunk[frame])?));
+            }
+
+            if self.eof {
+                return match self.layout {
+                    Layout::Framed | Layout::ArrayEnd if self.buf.is_empty() => Ok(None),
+                    _ => Err(self.malformed(Malformed {
+                        offset: self.buf.len(),
+                    })),
+                };
+            }
+
+            if self.buf.len() > self.limit {
+                return Err(CborPayloadError::Overflow);
+            }
+
+            match self.stream.next().await {
+                Some(chunk) => self.buf.extend_from_slice(&chunk?),
+                None => self.eof = true,
+            }
+        }
+    }
+
+    /// Location of the next complete item at the start of the buffer, consuming array framing.
+    fn next_frame(&mut self) -> Result<Option<Range<usize>>, CborPayloadError> {
+        loop {
+            match self.layout {
+                Layout::ArrayHeader => {
+                    let head = match read_head(&self.buf, 0).map_err(|e| self.malformed(e))? {
+                        Some(head) => head,
+                        None => return Ok(None),
+                    };
+                    if head.major != 4 {
+                        return Err(self.malformed(Malformed { offset: 0 }));
+                    }
+                    self.advance(head.len);
+                    self.layout = match head.arg {
+                        Some(0) => Layout::ArrayEnd,
+                        remaining => Layout::Array(remaining),
+                    };
+                }
+                Layout::Array(None) if self.buf.first() == Some(&0xff) => {
+                    self.advance(1);
+                    self.layout = Layout::ArrayEnd;
+                }
+                Layout::ArrayEnd => {
+                    if self.buf.is_empty() {
+                        return Ok(None);
+                    }
+                    return Err(self.malformed(Malformed { offset: 0 }));
+                }
+                Layout::Framed | Layout::Array(_) => {
+                    let frame = match self.framing.next_frame(&self.buf) {
+                        Ok(Some(frame)) => frame,
+                        Ok(None) => return Ok(None),
+                        Err(e) => {
+                            return Err(CborPayloadError::Malformed(self.consumed + e.offset))
+                        }
+                    };
+                    if frame.len() > self.limit {
+                        return Err(CborPayloadError::Overflow);
+                    }
+
+                    if let Layout::Array(Some(remaining)) = self.layout {
+                        self.layout = match remaining - 1 {
+                            0 => Layout::ArrayEnd,
+                            remaining => Layout::Array(Some(remaining)),
+                        };
+                    }
+                    return Ok(Some(frame));
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        let _ = self.buf.split_to(len);
+        self.consumed += len;
+    }
+
+    fn malformed(&self, err: Malformed) -> CborPayloadError {
+        CborPayloadError::Malformed(self.consumed + err.offset)
+    }
+}
+
+/// Extractor invoking a callback for every item of a large body, holding one item at a time.
+///
+/// Accepts either an `application/cbor-seq` body, whose top-level items are visited in order, or
+/// an `application/cbor` body holding a (definite or indefinite length) array, whose elements are
+/// visited. The [`CborConfig`](struct.CborConfig.html) limit applies to each item rather than to
+/// the whole body.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborForEach;
+/// use actix_web::Error;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Chunk {
+///     offset: u64,
+///     data: Vec<u8>,
+/// }
+///
+/// async fn upload(chunks: CborForEach<Chunk>) -> Result<String, Error> {
+///     let mut written = 0;
+///     let count = chunks
+///         .for_each(|chunk| {
+///             written += chunk.data.len();
+///             async {}
+///         })
+///         .await?;
+///     Ok(format!("{} chunks, {} bytes", count, written))
+/// }
+/// ```
+pub struct CborForEach<T> {
+    decoder: ItemDecoder,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> CborForEach<T>
+where
+    T: DeserializeOwned,
+{
+    /// Call `f` with every item as soon as it is decoded, returning how many items were visited.
+    pub async fn for_each<F, Fut>(self, mut f: F) -> Result<usize, CborPayloadError>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let mut decoder = self.decoder;
+        let mut count = 0;
+
+        while let Some(item) = decoder.next_item().await? {
+            f(item).await;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+impl<T> FromRequest for CborForEach<T>
+where
+    T: DeserializeOwned,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req);
+        let mime = req.content_type();
+
+        let sequence = if mime == CBOR_SEQ_CONTENT_TYPE {
+            true
+        } else if is_cbor_content_type(mime, config.content_type.as_ref()) {
+            false
+        } else {
+            let err = CborPayloadError::ContentType;
+            return ready(Err(extraction_error(&config, err, req)));
+        };
+
+        let stream = take_payload(req, payload);
+        let decoder = if sequence {
+            ItemDecoder::framed(stream, SeqFraming, config.limit_for(req))
+        } else {
+            ItemDecoder::array(stream, config.limit_for(req))
+        };
+        ready(Ok(CborForEach {
+            decoder,
+            _item: PhantomData,
+        }))
+    }
+}
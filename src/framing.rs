@@ -0,0 +1,252 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix_web::{
+    dev::Payload, http::header::HeaderValue, web::BytesMut, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::{ready, Ready};
+use futures_util::stream::{self, LocalBoxStream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::ser::Error as _;
+
+use crate::scan::{scan_item, Scan, DEFAULT_MAX_DEPTH};
+use crate::seq::CBOR_SEQ_CONTENT_TYPE_VALUE;
+use crate::{
+    extraction_error, take_payload, CborConfig, CborError, CborPayloadError, ItemDecoder,
+    CBOR_SEQ_CONTENT_TYPE,
+};
+
+/// A frame that cannot be decoded, starting at the given offset of the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameError {
+    /// Offset of the offending byte.
+    pub offset: usize,
+}
+
+/// Strategy delimiting CBOR items within a streamed body.
+///
+/// Framed bodies are sent as `application/cbor-seq`, with a `framing` parameter naming the
+/// strategy unless items are simply concatenated.
+pub trait Framing: 'static {
+    /// Value of the `framing` content type parameter, if any.
+    fn name(&self) -> Option<&'static str>;
+
+    /// Append one encoded item, along with its framing, to `dst`, failing when the item cannot
+    /// be framed, e.g. when it is too large.
+    fn write_frame(&self, item: &[u8], dst: &mut BytesMut) -> Result<(), CborError>;
+
+    /// Locate the first item at the start of `buf`, returning `None` while it is incomplete.
+    fn next_frame(&self, buf: &[u8]) -> Result<Option<Range<usize>>, FrameError>;
+
+    /// Content type of bodies using this framing.
+    ///
+    /// The default implementation formats the value from [`name`](#tymethod.name) for every
+    /// response; implementations with a name override it with a static value.
+    fn content_type(&self) -> HeaderValue {
+        match self.name() {
+            Some(name) => {
+                HeaderValue::from_str(&format!("{}; framing={}", CBOR_SEQ_CONTENT_TYPE, name))
+                    .unwrap_or_else(|_| CBOR_SEQ_CONTENT_TYPE_VALUE.clone())
+            }
+            None => CBOR_SEQ_CONTENT_TYPE_VALUE.clone(),
+        }
+    }
+}
+
+/// Plain CBOR sequence: items are concatenated, their boundaries found by parsing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SeqFraming;
+
+impl Framing for SeqFraming {
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn write_frame(&self, item: &[u8], dst: &mut BytesMut) -> Result<(), CborError> {
+        dst.extend_from_slice(item);
+        Ok(())
+    }
+
+    fn next_frame(&self, buf: &[u8]) -> Result<Option<Range<usize>>, FrameError> {
+        match scan_item(buf, DEFAULT_MAX_DEPTH) {
+            Ok(Scan::Complete(len)) => Ok(Some(0..len)),
+            Ok(Scan::Incomplete) => Ok(None),
+            Err(e) => Err(FrameError { offset: e.offset }),
+        }
+    }
+}
+
+/// Each item is preceded by its length as a big-endian `u32`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct U32Framing;
+
+impl Framing for U32Framing {
+    fn name(&self) -> Option<&'static str> {
+        Some("u32")
+    }
+
+    fn write_frame(&self, item: &[u8], dst: &mut BytesMut) -> Result<(), CborError> {
+        let len = u32::try_from(item.len())
+            .map_err(|_| serde_cbor::Error::custom("CBOR item larger than 4GiB"))?;
+        dst.extend_from_slice(&len.to_be_bytes());
+        dst.extend_from_slice(item);
+        Ok(())
+    }
+
+    fn content_type(&self) -> HeaderValue {
+        HeaderValue::from_static("application/cbor-seq; framing=u32")
+    }
+
+    fn next_frame(&self, buf: &[u8]) -> Result<Option<Range<usize>>, FrameError> {
+        if buf.len() < 4 {
+            return Ok(None);
+        }
+
+        let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_add(4))
+            .ok_or(FrameError { offset: 0 })?;
+        Ok(Some(4..end).filter(|frame| frame.end <= buf.len()))
+    }
+}
+
+/// Each item is preceded by its length as an unsigned LEB128 varint.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VarintFraming;
+
+impl Framing for VarintFraming {
+    fn name(&self) -> Option<&'static str> {
+        Some("varint")
+    }
+
+    fn content_type(&self) -> HeaderValue {
+        HeaderValue::from_static("application/cbor-seq; framing=varint")
+    }
+
+    fn write_frame(&self, item: &[u8], dst: &mut BytesMut) -> Result<(), CborError> {
+        let mut len = item.len() as u64;
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                dst.extend_from_slice(&[byte]);
+                break;
+            }
+            dst.extend_from_slice(&[byte | 0x80]);
+        }
+        dst.extend_from_slice(item);
+        Ok(())
+    }
+
+    fn next_frame(&self, buf: &[u8]) -> Result<Option<Range<usize>>, FrameError> {
+        let mut len: u64 = 0;
+        for (idx, byte) in buf.iter().enumerate().take(10) {
+            len |= u64::from(byte & 0x7f) << (7 * idx);
+            if byte & 0x80 == 0 {
+                let start = idx + 1;
+                let end = usize::try_from(len)
+                    .ok()
+                    .and_then(|len| start.checked_add(len))
+                    .ok_or(FrameError { offset: 0 })?;
+                return Ok(Some(start..end).filter(|frame| frame.end <= buf.len()));
+            }
+        }
+
+        match buf.len() {
+            0..=9 => Ok(None),
+            _ => Err(FrameError { offset: 9 }),
+        }
+    }
+}
+
+/// Whether the request content type is `application/cbor-seq` framed with `framing`.
+fn is_framed_content_type(req: &HttpRequest, framing: &dyn Framing) -> bool {
+    let mime = match req.mime_type() {
+        Ok(Some(mime)) => mime,
+        _ => return false,
+    };
+    let param = mime
+        .get_param("framing")
+        .map(|name| name.as_str().to_owned());
+
+    mime.essence_str() == CBOR_SEQ_CONTENT_TYPE && param.as_deref() == framing.name()
+}
+
+/// Extractor streaming the items of a framed `application/cbor-seq` body.
+///
+/// Items are decoded one at a time as they arrive; the [`CborConfig`](struct.CborConfig.html)
+/// limit applies to each item.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborFramed, U32Framing};
+/// use futures_util::StreamExt;
+///
+/// async fn ingest(mut items: CborFramed<u64, U32Framing>) -> String {
+///     let mut total = 0;
+///     while let Some(item) = items.next().await {
+///         match item {
+///             Ok(value) => total += value,
+///             Err(e) => return e.to_string(),
+///         }
+///     }
+///     total.to_string()
+/// }
+/// ```
+pub struct CborFramed<T, F = SeqFraming> {
+    items: LocalBoxStream<'static, Result<T, CborPayloadError>>,
+    _framing: PhantomData<fn() -> F>,
+}
+
+impl<T, F> Stream for CborFramed<T, F> {
+    type Item = Result<T, CborPayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.items.as_mut().poll_next(cx)
+    }
+}
+
+impl<T, F> FromRequest for CborFramed<T, F>
+where
+    T: DeserializeOwned + 'static,
+    F: Framing + Default,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req);
+        let framing = F::default();
+
+        let accepted = is_framed_content_type(req, &framing)
+            || config
+                .content_type
+                .as_ref()
+                .is_some_and(|predicate| predicate(req.content_type()));
+        if !accepted {
+            let err = CborPayloadError::ContentType;
+            return ready(Err(extraction_error(&config, err, req)));
+        }
+
+        let decoder =
+            ItemDecoder::framed(take_payload(req, payload), framing, config.limit_for(req));
+        let items = stream::unfold(Some(decoder), |decoder| async move {
+            let mut decoder = decoder?;
+            match decoder.next_item().await {
+                Ok(Some(item)) => Some((Ok(item), Some(decoder))),
+                Ok(None) => None,
+                // stop after the first error
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+
+        ready(Ok(CborFramed {
+            items: items.boxed_local(),
+            _framing: PhantomData,
+        }))
+    }
+}
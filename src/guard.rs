@@ -0,0 +1,95 @@
+//! Route guards dispatching on CBOR requests.
+
+use actix_web::{
+    guard::{Guard, GuardContext},
+    http::header::{HeaderMap, CONTENT_TYPE},
+};
+
+use crate::body::is_cbor_content_type;
+use crate::{AcceptCbor, CborConfig};
+
+/// Content type of the request, without its parameters.
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+        .unwrap_or("")
+}
+
+/// Guard matching requests whose content type is CBOR, or accepted by the content type
+/// predicate of the [`CborConfig`](../struct.CborConfig.html) app data.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{guard::cbor, Cbor};
+/// use actix_web::{web, App};
+///
+/// async fn create_cbor(item: Cbor<String>) -> String {
+///     item.into_inner()
+/// }
+///
+/// async fn create_json(item: web::Json<String>) -> String {
+///     item.into_inner()
+/// }
+///
+/// let app = App::new().service(
+///     web::resource("/items")
+///         .route(web::post().guard(cbor()).to(create_cbor))
+///         .route(web::post().to(create_json)),
+/// );
+/// ```
+pub fn cbor() -> CborGuard {
+    CborGuard
+}
+
+/// Guard returned by [`cbor`](fn.cbor.html).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CborGuard;
+
+impl Guard for CborGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let predicate = CborConfig::content_type_for_guard(ctx);
+        is_cbor_content_type(content_type(&ctx.head().headers), predicate.as_ref())
+    }
+}
+
+/// Guard matching requests whose `Accept` header allows CBOR responses, see
+/// [`AcceptCbor`](../struct.AcceptCbor.html).
+///
+/// Requests without a valid `Accept` header accept any media type.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{guard::accepts_cbor, Cbor};
+/// use actix_web::{web, App};
+///
+/// async fn list_cbor() -> Cbor<Vec<u32>> {
+///     Cbor(vec![1, 2, 3])
+/// }
+///
+/// async fn list_json() -> web::Json<Vec<u32>> {
+///     web::Json(vec![1, 2, 3])
+/// }
+///
+/// let app = App::new().service(
+///     web::resource("/items")
+///         .route(web::get().guard(accepts_cbor()).to(list_cbor))
+///         .route(web::get().to(list_json)),
+/// );
+/// ```
+pub fn accepts_cbor() -> AcceptsCborGuard {
+    AcceptsCborGuard
+}
+
+/// Guard returned by [`accepts_cbor`](fn.accepts_cbor.html).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AcceptsCborGuard;
+
+impl Guard for AcceptsCborGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        ctx.header::<AcceptCbor>()
+            .is_none_or(|accept| accept.accepts_cbor())
+    }
+}
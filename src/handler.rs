@@ -0,0 +1,54 @@
+//! Support of the code generated by `#[cbor_handler]` and `#[derive(CborResponder)]`.
+
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::error_handlers::error_response_to;
+use crate::Cbor;
+
+/// Respond with the output of a handler which cannot fail.
+pub fn respond_value<T: Serialize + 'static>(value: T, req: &HttpRequest) -> HttpResponse {
+    Cbor(value).respond_to(req)
+}
+
+/// Respond with the output of a fallible handler, rendering errors as CBOR.
+pub fn respond_result<T, E>(result: Result<T, E>, req: &HttpRequest) -> HttpResponse
+where
+    T: Serialize + 'static,
+    E: Into<actix_web::Error>,
+{
+    match result {
+        Ok(value) => respond_value(value, req),
+        Err(e) => error_response_to(e.into(), Some(req)),
+    }
+}
+
+/// Respond for a type deriving `CborResponder`, overriding the `200 OK` status and adding the
+/// extra headers on success. Headers which are not valid are left out.
+pub fn respond_derived<T: Serialize + 'static>(
+    value: T,
+    req: &HttpRequest,
+    status: Option<u16>,
+    headers: &[(&str, &str)],
+) -> HttpResponse {
+    let mut res = respond_value(value, req);
+
+    if let Some(status) = status.and_then(|status| StatusCode::from_u16(status).ok()) {
+        if res.status() == StatusCode::OK {
+            *res.status_mut() = status;
+        }
+    }
+    if res.status().is_success() {
+        for (name, value) in headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                res.headers_mut().insert(name, value);
+            }
+        }
+    }
+    res
+}
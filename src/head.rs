@@ -0,0 +1,56 @@
+use std::io;
+
+use actix_web::{http::Method, web::Bytes, HttpRequest, HttpResponse, HttpResponseBuilder};
+use futures_util::stream;
+use serde::Serialize;
+
+use crate::profile::write_profile;
+use crate::{CborProfile, CBOR_CONTENT_TYPE_VALUE};
+
+/// `io::Write` sink only counting the bytes written to it.
+#[derive(Default)]
+struct Counter(u64);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Size of the CBOR encoding of `value` with `profile`, without buffering it.
+pub(crate) fn encoded_len<T: Serialize>(
+    value: &T,
+    profile: Option<CborProfile>,
+) -> Result<u64, serde_cbor::Error> {
+    let mut counter = Counter::default();
+    write_profile(&mut counter, value, profile)?;
+    Ok(counter.0)
+}
+
+/// Finish a CBOR response announcing a body of `len` bytes, without sending it.
+pub(crate) fn head_response(builder: &mut HttpResponseBuilder, len: u64) -> HttpResponse {
+    builder
+        .content_type(CBOR_CONTENT_TYPE_VALUE.clone())
+        .no_chunking(len)
+        .streaming(stream::empty::<Result<Bytes, io::Error>>())
+}
+
+/// Finish a CBOR response with `body`, which is left out for `HEAD` requests.
+pub(crate) fn cbor_body(
+    req: &HttpRequest,
+    builder: &mut HttpResponseBuilder,
+    body: Bytes,
+) -> HttpResponse {
+    if req.method() == Method::HEAD {
+        return head_response(builder, body.len() as u64);
+    }
+
+    builder
+        .content_type(CBOR_CONTENT_TYPE_VALUE.clone())
+        .body(body)
+}
@@ -0,0 +1,76 @@
+use std::time::Instant;
+
+use futures_util::future::{ready, Ready};
+use serde::{Deserialize, Serialize};
+
+use crate::Cbor;
+
+/// Identity of a service reported by [`health_handler`](fn.health_handler.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthInfo {
+    name: String,
+    version: String,
+    build: Option<String>,
+}
+
+impl HealthInfo {
+    /// Service `name` at `version`.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        HealthInfo {
+            name: name.into(),
+            version: version.into(),
+            build: None,
+        }
+    }
+
+    /// Set the build identifier, such as a commit hash.
+    pub fn build(mut self, build: impl Into<String>) -> Self {
+        self.build = Some(build.into());
+        self
+    }
+}
+
+/// CBOR document answered by [`health_handler`](fn.health_handler.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Always `ok`, the service being able to answer
+    pub status: String,
+    /// Name of the service
+    pub name: String,
+    /// Version of the service
+    pub version: String,
+    /// Build identifier of the service, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    /// Seconds elapsed since the handler was created
+    pub uptime: u64,
+}
+
+/// Handler answering the build, version and uptime of the service as a CBOR
+/// [`HealthReport`](struct.HealthReport.html).
+///
+/// The uptime is counted from the call to `health_handler`, so create the handler once, before
+/// building the `HttpServer`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{health_handler, HealthInfo};
+/// use actix_web::{web, App};
+///
+/// let health = health_handler(
+///     HealthInfo::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).build("4f2a9c1"),
+/// );
+/// let app = App::new().route("/health", web::get().to(health));
+/// ```
+pub fn health_handler(info: HealthInfo) -> impl Fn() -> Ready<Cbor<HealthReport>> + Clone {
+    let started = Instant::now();
+    move || {
+        ready(Cbor(HealthReport {
+            status: "ok".to_owned(),
+            name: info.name.clone(),
+            version: info.version.clone(),
+            build: info.build.clone(),
+            uptime: started.elapsed().as_secs(),
+        }))
+    }
+}
@@ -0,0 +1,123 @@
+use std::cmp::Reverse;
+
+use actix_web::{
+    http::header::{Accept, Header, HeaderValue, Quality, VARY},
+    web::Bytes,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+
+use crate::body::{is_cbor_content_type, BufferedBody};
+use crate::{AcceptCbor, CborConfig};
+
+/// Preference of an `Accept` header for a media type: the quality of the most specific range
+/// matching it, then that specificity, then the position of the range in the header.
+type Rank = (Quality, u8, Reverse<usize>);
+
+/// Rank of `type_/subtype` in `accept`, if any of its ranges matches.
+pub(crate) fn rank(accept: &Accept, type_: &str, subtype: &str) -> Option<Rank> {
+    accept
+        .0
+        .iter()
+        .enumerate()
+        .filter_map(|(index, range)| {
+            let specificity = match (range.item.type_().as_str(), range.item.subtype().as_str()) {
+                (t, s) if t == type_ && s == subtype => 2,
+                (t, "*") if t == type_ => 1,
+                ("*", "*") => 0,
+                _ => return None,
+            };
+            Some((specificity, index, range.quality))
+        })
+        .max_by_key(|&(specificity, index, _)| (specificity, Reverse(index)))
+        .map(|(specificity, index, quality)| (quality, specificity, Reverse(index)))
+}
+
+/// Parsed `Accept` header of `req`, unless it is missing, invalid or empty.
+pub(crate) fn parse_accept(req: &HttpRequest) -> Option<Accept> {
+    Accept::parse(req)
+        .ok()
+        .filter(|accept| !accept.0.is_empty())
+}
+
+/// Add `name` to the `Vary` header of `res`, unless it is already listed.
+pub(crate) fn add_vary(res: &mut HttpResponse, name: &'static str) {
+    let listed = res
+        .headers()
+        .get_all(VARY)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|listed| {
+            let listed = listed.trim();
+            listed == "*" || listed.eq_ignore_ascii_case(name)
+        });
+    if !listed {
+        res.headers_mut()
+            .append(VARY, HeaderValue::from_static(name));
+    }
+}
+
+/// Helpers for CBOR content negotiation on the `HttpRequest`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, HttpRequestExt};
+/// use actix_web::{web, Either, HttpRequest};
+///
+/// async fn index(req: HttpRequest) -> Either<Cbor<Vec<u32>>, web::Json<Vec<u32>>> {
+///     let items = vec![1, 2, 3];
+///     if req.prefers_cbor_over_json() {
+///         Either::Left(Cbor(items))
+///     } else {
+///         Either::Right(web::Json(items))
+///     }
+/// }
+/// ```
+pub trait HttpRequestExt {
+    /// Whether the `Accept` header allows a cbor response
+    ///
+    /// Requests without a valid `Accept` header accept any media type.
+    fn accepts_cbor(&self) -> bool;
+
+    /// Whether the `Accept` header ranks cbor strictly above json
+    ///
+    /// Ties go to json, so requests without a valid `Accept` header do not prefer cbor.
+    fn prefers_cbor_over_json(&self) -> bool;
+
+    /// Whether the request body is cbor, according to the [`CborConfig`](struct.CborConfig.html)
+    /// of the request
+    fn content_type_is_cbor(&self) -> bool;
+
+    /// Raw CBOR body of the request, once read by a teeing extractor
+    ///
+    /// Bodies are kept by [`CachedCbor`](struct.CachedCbor.html), and by every CBOR extractor
+    /// when [`CborConfig::tee`](struct.CborConfig.html#method.tee) is enabled.
+    fn cbor_raw_body(&self) -> Option<Bytes>;
+
+    /// Label of the request, set with
+    /// [`CborConfig::metric_label`](struct.CborConfig.html#method.metric_label)
+    fn cbor_metric_label(&self) -> Option<&'static str>;
+}
+
+impl HttpRequestExt for HttpRequest {
+    fn accepts_cbor(&self) -> bool {
+        AcceptCbor::parse(self).map_or(true, |accept| accept.accepts_cbor())
+    }
+
+    fn prefers_cbor_over_json(&self) -> bool {
+        AcceptCbor::parse(self).is_ok_and(|accept| accept.prefers_cbor_over("application", "json"))
+    }
+
+    fn content_type_is_cbor(&self) -> bool {
+        let config = CborConfig::from_req(self);
+        is_cbor_content_type(self.content_type(), config.content_type.as_ref())
+    }
+
+    fn cbor_raw_body(&self) -> Option<Bytes> {
+        let extensions = self.extensions();
+        extensions.get::<BufferedBody>().map(|body| body.0.clone())
+    }
+
+    fn cbor_metric_label(&self) -> Option<&'static str> {
+        CborConfig::from_req(self).metric_label
+    }
+}
@@ -1,7 +1,17 @@
-use actix_web::{http::header::ContentType, HttpResponse, HttpResponseBuilder};
+use actix_web::{
+    http::header::{ETag, CONTENT_TYPE},
+    web::Bytes,
+    HttpResponse, HttpResponseBuilder,
+};
+use futures_util::stream::{Stream, StreamExt};
 use log::error;
 use serde::Serialize;
 
+use crate::seq::CBOR_SEQ_CONTENT_TYPE_VALUE;
+use crate::{
+    cbor_etag, to_canonical_vec, CborError, CborPayloadError, CborProfile, CBOR_CONTENT_TYPE_VALUE,
+};
+
 /// Allow to serialize in cbor on the `HttpResponseBuilder`.
 pub trait HttpResponseBuilderExt {
     /// Set a cbor body and generate `Response`
@@ -13,6 +23,39 @@ pub trait HttpResponseBuilderExt {
     ///
     /// `ResponseBuilder` can not be used after this call.
     fn cbor2<T: Serialize>(&mut self, value: &T) -> HttpResponse;
+
+    /// Set a canonical cbor body, with map keys sorted in length-first order, and generate
+    /// `Response` labelled with the [`Canonical`](enum.CborProfile.html#variant.Canonical) profile
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    fn cbor_canonical<T: Serialize>(&mut self, value: &T) -> HttpResponse;
+
+    /// Set a packed cbor body, with struct fields keyed by index instead of name, and generate
+    /// `Response` labelled with the [`Packed`](enum.CborProfile.html#variant.Packed) profile
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    fn cbor_packed<T: Serialize>(&mut self, value: &T) -> HttpResponse;
+
+    /// Set a cbor body along with its strong [`cbor_etag`](fn.cbor_etag.html) and generate
+    /// `Response`
+    ///
+    /// `ResponseBuilder` can not be used after this call.
+    fn cbor_with_etag<T: Serialize>(&mut self, value: &T) -> HttpResponse;
+
+    /// Set a cbor body and generate `Response`, returning serialization failures to the caller
+    ///
+    /// `ResponseBuilder` can not be used after a successful call.
+    fn try_cbor<T: Serialize>(&mut self, value: &T) -> Result<HttpResponse, CborPayloadError>;
+
+    /// Set a chunked `application/cbor-seq` body serializing each item of `stream` and generate
+    /// `Response`
+    ///
+    /// Serialization failures abort the response. `ResponseBuilder` can not be used after this
+    /// call.
+    fn cbor_stream<S>(&mut self, stream: S) -> HttpResponse
+    where
+        S: Stream + 'static,
+        S::Item: Serialize;
 }
 
 impl HttpResponseBuilderExt for HttpResponseBuilder {
@@ -23,15 +66,70 @@ impl HttpResponseBuilderExt for HttpResponseBuilder {
     fn cbor2<T: Serialize>(&mut self, value: &T) -> HttpResponse {
         match serde_cbor::to_vec(value) {
             Ok(body) => {
-                self.insert_header(ContentType("application/cbor".parse().unwrap()));
-                self.body(actix_web::dev::Body::from(body)).into()
+                self.insert_header((CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone()));
+                self.body(body)
             }
-            Err(e) => {
-                error!("Serialize error: {}", e);
-                HttpResponse::InternalServerError()
-                    .reason("unable to serialize cbor.")
-                    .finish()
+            Err(e) => serialize_error(e),
+        }
+    }
+
+    fn cbor_canonical<T: Serialize>(&mut self, value: &T) -> HttpResponse {
+        match to_canonical_vec(value) {
+            Ok(body) => {
+                self.insert_header((CONTENT_TYPE, CborProfile::Canonical.content_type()));
+                self.body(body)
+            }
+            Err(e) => serialize_error(e),
+        }
+    }
+
+    fn cbor_packed<T: Serialize>(&mut self, value: &T) -> HttpResponse {
+        match serde_cbor::ser::to_vec_packed(value) {
+            Ok(body) => {
+                self.insert_header((CONTENT_TYPE, CborProfile::Packed.content_type()));
+                self.body(body)
+            }
+            Err(e) => serialize_error(e),
+        }
+    }
+
+    fn cbor_with_etag<T: Serialize>(&mut self, value: &T) -> HttpResponse {
+        let encoded = serde_cbor::to_vec(value).map_err(CborError::from);
+        match encoded.and_then(|body| Ok((cbor_etag(value)?, body))) {
+            Ok((etag, body)) => {
+                self.insert_header(ETag(etag));
+                self.insert_header((CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone()));
+                self.body(body)
             }
+            Err(e) => serialize_error(e),
         }
     }
+
+    fn try_cbor<T: Serialize>(&mut self, value: &T) -> Result<HttpResponse, CborPayloadError> {
+        let body = serde_cbor::to_vec(value)
+            .map_err(|e| CborPayloadError::Serialize(CborError::from(e)))?;
+        self.insert_header((CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone()));
+        Ok(self.body(body))
+    }
+
+    fn cbor_stream<S>(&mut self, stream: S) -> HttpResponse
+    where
+        S: Stream + 'static,
+        S::Item: Serialize,
+    {
+        let body = stream.map(|item| {
+            serde_cbor::to_vec(&item)
+                .map(Bytes::from)
+                .map_err(CborError::from)
+        });
+        self.content_type(CBOR_SEQ_CONTENT_TYPE_VALUE.clone())
+            .streaming(body.boxed_local())
+    }
+}
+
+fn serialize_error(e: impl std::fmt::Display) -> HttpResponse {
+    error!("Serialize error: {}", e);
+    HttpResponse::InternalServerError()
+        .reason("unable to serialize cbor.")
+        .finish()
 }
@@ -0,0 +1,365 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorUnprocessableEntity, PayloadError},
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    web::{Bytes, BytesMut},
+    HttpRequest, HttpResponse,
+};
+use futures_util::future::{ok, ready, LocalBoxFuture, Ready};
+use futures_util::{stream, StreamExt};
+use serde_cbor::Value;
+
+use crate::etag::{to_canonical_vec, BodyHasher};
+use crate::{response_bytes, CborConfig, CborPayloadError};
+
+/// Name of the header carrying the client-chosen key of an idempotent request.
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Name of the header set on responses replayed from an
+/// [`IdempotencyStore`](trait.IdempotencyStore.html).
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// First response to an idempotent request, along with the fingerprint of that request.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    fingerprint: u64,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    /// Hash of the method, URI and canonical CBOR body of the request.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Body of the response.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Store the response to a request fingerprinted `fingerprint`.
+    pub(crate) fn new(
+        fingerprint: u64,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Self {
+        StoredResponse {
+            fingerprint,
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Rebuild the response.
+    pub(crate) fn to_response(&self) -> HttpResponse {
+        let mut res = HttpResponse::with_body(self.status, BoxBody::new(self.body.clone()));
+        for (name, value) in self.headers.iter() {
+            res.headers_mut().append(name.clone(), value.clone());
+        }
+        res
+    }
+}
+
+/// Storage of the first response to each idempotency key.
+///
+/// Storage is asynchronous so implementations may be shared between instances, for example in a
+/// database.
+pub trait IdempotencyStore: Send + Sync {
+    /// Response stored under `key`, if any.
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Option<StoredResponse>>;
+
+    /// Store `response` under `key`, replacing any previous one.
+    fn put(&self, key: String, response: StoredResponse) -> LocalBoxFuture<'_, ()>;
+}
+
+/// Idempotency store kept in memory, whose entries expire after a time to live.
+///
+/// When the store is full, expired entries are dropped first, then the entry closest to
+/// expiring.
+#[derive(Clone)]
+pub struct InMemoryIdempotencyStore {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Arc<Mutex<HashMap<String, (Instant, StoredResponse)>>>,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Create an empty store whose entries expire after `ttl`, holding up to 10000 entries.
+    pub fn new(ttl: Duration) -> Self {
+        InMemoryIdempotencyStore {
+            ttl,
+            max_entries: 10_000,
+            entries: Default::default(),
+        }
+    }
+
+    /// Change the max number of stored responses.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Drop the expired entries, releasing their memory.
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (expires, _)| *expires > now);
+    }
+}
+
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Option<StoredResponse>> {
+        let entries = self.entries.lock().unwrap();
+        let response = entries
+            .get(key)
+            .filter(|(expires, _)| *expires > Instant::now())
+            .map(|(_, response)| response.clone());
+        Box::pin(ready(response))
+    }
+
+    fn put(&self, key: String, response: StoredResponse) -> LocalBoxFuture<'_, ()> {
+        if self.max_entries == 0 {
+            return Box::pin(ready(()));
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, (expires, _)| *expires > now);
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (expires, _))| *expires)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (now + self.ttl, response));
+        Box::pin(ready(()))
+    }
+}
+
+/// `body` re-encoded as canonical CBOR when it is well-formed, so that equivalent encodings
+/// compare equal, else `body` itself.
+pub(crate) fn canonical_body(body: &[u8]) -> Cow<'_, [u8]> {
+    serde_cbor::from_slice::<Value>(body)
+        .ok()
+        .and_then(|value| to_canonical_vec(&value).ok())
+        .map_or(Cow::Borrowed(body), Cow::Owned)
+}
+
+/// Hash of the method, URI and canonical body of a request.
+pub(crate) fn fingerprint(req: &HttpRequest, body: &[u8]) -> u64 {
+    let mut hasher = BodyHasher::new();
+    hasher.update(req.method().as_str().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(req.uri().to_string().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&canonical_body(body));
+    hasher.finish()
+}
+
+/// Buffer `payload`, failing past `limit` bytes.
+pub(crate) async fn read_body(
+    payload: &mut Payload,
+    limit: usize,
+) -> Result<Bytes, CborPayloadError> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > limit {
+            return Err(CborPayloadError::Overflow);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+/// Request made of `req` and the already buffered `body`.
+pub(crate) fn with_body(req: HttpRequest, body: Bytes) -> ServiceRequest {
+    let body = stream::once(ready(Ok::<_, PayloadError>(body)));
+    ServiceRequest::from_parts(
+        req,
+        Payload::Stream {
+            payload: Box::pin(body),
+        },
+    )
+}
+
+/// Middleware replaying the first response to requests sharing an `Idempotency-Key` header.
+///
+/// The request body is buffered, up to the limit of the request
+/// [`CborConfig`](struct.CborConfig.html), and fingerprinted from its canonical CBOR encoding
+/// along with the method and URI. The first response for a key is stored, unless it is a server
+/// error, and replayed on retries with an `Idempotent-Replayed: true` header, without invoking the
+/// handler. Retries whose fingerprint differs are rejected with `422 Unprocessable Entity`.
+///
+/// Streaming responses and responses larger than
+/// [`max_body_size`](struct.Idempotency.html#method.max_body_size), 256kB by default, are sent
+/// without being stored, so their retries run the handler again.
+///
+/// Requests without the header are passed through. Concurrent requests with the same key are not
+/// serialized: each runs the handler until one of them stores its response.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, Idempotency, InMemoryIdempotencyStore};
+/// use actix_web::{web, App};
+/// use std::time::Duration;
+///
+/// #[derive(serde::Deserialize, serde::Serialize)]
+/// struct Payment {
+///     amount: u64,
+/// }
+///
+/// async fn pay(payment: Cbor<Payment>) -> Cbor<Payment> {
+///     payment
+/// }
+///
+/// let store = InMemoryIdempotencyStore::new(Duration::from_secs(24 * 60 * 60));
+/// let app = App::new().service(
+///     web::resource("/payments")
+///         .wrap(Idempotency::new(store))
+///         .route(web::post().to(pay)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Idempotency {
+    store: Arc<dyn IdempotencyStore>,
+    max_body_size: usize,
+}
+
+impl Idempotency {
+    /// Store responses in `store`.
+    pub fn new<S: IdempotencyStore + 'static>(store: S) -> Self {
+        Idempotency {
+            store: Arc::new(store),
+            max_body_size: 262_144,
+        }
+    }
+
+    /// Change the max size of a stored response body.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+            store: self.store.clone(),
+            max_body_size: self.max_body_size,
+        })
+    }
+}
+
+/// Service created by the [`Idempotency`](struct.Idempotency.html) middleware.
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+    store: Arc<dyn IdempotencyStore>,
+    max_body_size: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let key = match req.headers().get(IDEMPOTENCY_KEY).map(|v| v.to_str()) {
+            Some(Ok(key)) => key.to_owned(),
+            _ => {
+                let fut = self.service.call(req);
+                return Box::pin(async move { Ok(fut.await?.map_into_left_body()) });
+            }
+        };
+        let service = self.service.clone();
+        let store = self.store.clone();
+        let max_body_size = self.max_body_size;
+
+        Box::pin(async move {
+            let (req, mut payload) = req.into_parts();
+            let limit = CborConfig::from_req(&req).limit_for(&req);
+            let body = match read_body(&mut payload, limit).await {
+                Ok(body) => body,
+                Err(e) => return Ok(ServiceResponse::from_err(e, req).map_into_right_body()),
+            };
+            let fingerprint = fingerprint(&req, &body);
+
+            if let Some(stored) = store.get(&key).await {
+                if stored.fingerprint != fingerprint {
+                    let e =
+                        ErrorUnprocessableEntity("Idempotency-Key reused with a different request");
+                    return Ok(ServiceResponse::from_err(e, req).map_into_right_body());
+                }
+                let mut res = stored.to_response();
+                res.headers_mut()
+                    .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+                return Ok(ServiceResponse::new(req, res).map_into_right_body());
+            }
+
+            let res = service.call(with_body(req, body.clone())).await?;
+            let storable = match res.response().body().size() {
+                BodySize::Sized(len) => len <= max_body_size as u64,
+                BodySize::None => true,
+                BodySize::Stream => false,
+            };
+            if res.status().is_server_error() || !storable {
+                return Ok(res.map_into_left_body());
+            }
+
+            let status = res.status();
+            let headers = res.headers().clone();
+            let (res, body) = response_bytes(res, max_body_size).await?;
+            let stored = StoredResponse::new(fingerprint, status, headers, body.clone());
+            store.put(key, stored).await;
+            Ok(res
+                .map_body(|_, ()| BoxBody::new(body))
+                .map_into_right_body())
+        })
+    }
+}
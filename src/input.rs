@@ -0,0 +1,266 @@
+use std::fmt;
+use std::ops::Deref;
+
+use actix_web::{
+    dev::Payload,
+    http::{header::CONTENT_TYPE, StatusCode},
+    FromRequest, HttpRequest, HttpResponse, ResponseError,
+};
+use futures_util::future::LocalBoxFuture;
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+
+use crate::error_handlers::{error_body_response, error_content_type};
+use crate::{
+    CborErrorBody, CborErrorCode, CborPayloadError, CborRejection, CBOR_CONTENT_TYPE_VALUE,
+};
+
+/// Extractor running every extractor of a tuple and, when some of them fail, rejecting the
+/// request with a single [`CborInputError`](struct.CborInputError.html) listing each failed
+/// component, instead of the error of the first one in its own format.
+///
+/// Tuples of one to six extractors are supported. They are run in order, as actix-web does.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborInput};
+/// use actix_web::web::{Path, Query};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Filter {
+///     limit: u32,
+/// }
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     quantity: u32,
+/// }
+///
+/// async fn update(
+///     input: CborInput<(Path<u64>, Query<Filter>, Cbor<Order>)>,
+/// ) -> String {
+///     let (id, filter, order) = input.into_inner();
+///     format!("{} {} {}", id, filter.limit, order.quantity)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborInput<T>(pub T);
+
+impl<T> CborInput<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborInput<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Failure of one component of a [`CborInput`](struct.CborInput.html).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CborInputFailure {
+    /// Position of the extractor in the tuple
+    pub index: usize,
+    /// Type of the extractor, without module paths, e.g. `Path<u64>`
+    pub component: String,
+    /// Status the extractor would have responded with
+    pub status: u16,
+    /// Description of the error
+    pub error: String,
+    /// Machine-readable code of the error, for rejected CBOR payloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<CborErrorCode>,
+}
+
+impl CborInputFailure {
+    /// Failure of the extractor `T` at `index`, rejected with `err`.
+    fn new<T>(index: usize, err: actix_web::Error) -> Self {
+        let status = err.as_response_error().status_code();
+        let code = match err.as_error::<CborPayloadError>() {
+            Some(err) => Some(err.code()),
+            None => err.as_error::<CborRejection>().map(CborRejection::code),
+        };
+        let mut error = err.to_string().trim_end().to_owned();
+        if error.is_empty() {
+            error = status.canonical_reason().unwrap_or_default().to_owned();
+        }
+
+        Self {
+            index,
+            component: short_type_name(std::any::type_name::<T>()),
+            status: status.as_u16(),
+            error,
+            code,
+        }
+    }
+}
+
+/// CBOR body of [`CborInputError`](struct.CborInputError.html) responses:
+/// `{"status": 400, "error": "...", "errors": [...]}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CborInputErrorBody {
+    /// Status code of the response
+    pub status: u16,
+    /// Description of the error
+    pub error: String,
+    /// Failed components, in the order of the tuple
+    pub errors: Vec<CborInputFailure>,
+}
+
+/// Error of a [`CborInput`](struct.CborInput.html) whose extractors failed.
+///
+/// The status is the one shared by all failures, or `400 Bad Request` when they differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborInputError {
+    failures: Vec<CborInputFailure>,
+    content_type: Option<Mime>,
+}
+
+impl CborInputError {
+    /// Failed components, in the order of the tuple.
+    pub fn failures(&self) -> &[CborInputFailure] {
+        &self.failures
+    }
+
+    /// Body of the response to this error.
+    pub fn body(&self) -> CborInputErrorBody {
+        CborInputErrorBody {
+            status: self.status_code().as_u16(),
+            error: self.to_string(),
+            errors: self.failures.clone(),
+        }
+    }
+}
+
+impl fmt::Display for CborInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid request input:")?;
+        for failure in &self.failures {
+            write!(f, " {}: {};", failure.component, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CborInputError {}
+
+impl ResponseError for CborInputError {
+    fn status_code(&self) -> StatusCode {
+        let mut statuses = self.failures.iter().map(|failure| failure.status);
+        match statuses.next() {
+            Some(first) if statuses.all(|status| status == first) => {
+                StatusCode::from_u16(first).unwrap_or(StatusCode::BAD_REQUEST)
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        match &self.content_type {
+            // plain text error bodies only carry the message
+            Some(mime) if mime.type_() == mime::TEXT => {
+                let body = CborErrorBody::new(status, self.to_string());
+                error_body_response(status, &body, self.content_type.clone())
+            }
+            _ => input_error_response(status, &self.body(), self.content_type.clone()),
+        }
+    }
+}
+
+/// Response of `status` carrying `body`, as `content_type` or `application/cbor`.
+fn input_error_response(
+    status: StatusCode,
+    body: &CborInputErrorBody,
+    content_type: Option<Mime>,
+) -> HttpResponse {
+    let mut res = HttpResponse::build(status);
+    match serde_cbor::to_vec(body) {
+        Ok(bytes) => {
+            match content_type {
+                Some(mime) => res.insert_header((CONTENT_TYPE, mime)),
+                None => res.insert_header((CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone())),
+            };
+            res.body(bytes)
+        }
+        Err(_) => HttpResponse::new(status),
+    }
+}
+
+/// `name` without the module paths of the types it names.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(pos) = rest.find("::") {
+        let (head, tail) = rest.split_at(pos);
+        // drop the path segment just before `::`
+        let start = head
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |i| i + 1);
+        short.push_str(&head[..start]);
+        rest = &tail[2..];
+    }
+    short.push_str(rest);
+    short
+}
+
+macro_rules! input_tuple {
+    ($($ty:ident $fut:ident $res:ident $index:tt),+) => {
+        impl<$($ty),+> FromRequest for CborInput<($($ty,)+)>
+        where
+            $(
+                $ty: FromRequest + 'static,
+                $ty::Future: 'static,
+            )+
+        {
+            type Error = CborInputError;
+            type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+            fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+                let content_type = error_content_type(Some(req));
+                $(let $fut = $ty::from_request(req, payload);)+
+
+                Box::pin(async move {
+                    $(let $res = $fut.await;)+
+                    let mut failures = Vec::new();
+                    let ($($res,)+) = ($(
+                        match $res {
+                            Ok(value) => Some(value),
+                            Err(err) => {
+                                failures.push(CborInputFailure::new::<$ty>($index, err.into()));
+                                None
+                            }
+                        },
+                    )+);
+
+                    match ($($res,)+) {
+                        ($(Some($res),)+) => Ok(CborInput(($($res,)+))),
+                        _ => Err(CborInputError {
+                            failures,
+                            content_type,
+                        }),
+                    }
+                })
+            }
+        }
+    };
+}
+
+input_tuple!(A a_fut a_res 0);
+input_tuple!(A a_fut a_res 0, B b_fut b_res 1);
+input_tuple!(A a_fut a_res 0, B b_fut b_res 1, C c_fut c_res 2);
+input_tuple!(A a_fut a_res 0, B b_fut b_res 1, C c_fut c_res 2, D d_fut d_res 3);
+input_tuple!(A a_fut a_res 0, B b_fut b_res 1, C c_fut c_res 2, D d_fut d_res 3, E e_fut e_res 4);
+input_tuple!(
+    A a_fut a_res 0,
+    B b_fut b_res 1,
+    C c_fut c_res 2,
+    D d_fut d_res 3,
+    E e_fut e_res 4,
+    F f_fut f_res 5
+);
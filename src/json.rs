@@ -0,0 +1,45 @@
+use actix_web::web::Json;
+
+use crate::{Cbor, CborTranscodeError};
+
+impl<T> From<Json<T>> for Cbor<T> {
+    fn from(json: Json<T>) -> Self {
+        Cbor(json.into_inner())
+    }
+}
+
+impl<T> From<Cbor<T>> for Json<T> {
+    fn from(cbor: Cbor<T>) -> Self {
+        Json(cbor.into_inner())
+    }
+}
+
+/// Transcode a JSON document into CBOR, without knowing its schema, available with the `json`
+/// feature.
+///
+/// # Example
+/// ```
+/// let cbor = actix_cbor::transcode_json_to_cbor(br#"{"id":7}"#).unwrap();
+/// assert_eq!(cbor, [0xa1, 0x62, b'i', b'd', 0x07]);
+/// ```
+pub fn transcode_json_to_cbor(json: &[u8]) -> Result<Vec<u8>, CborTranscodeError> {
+    let value: serde_json::Value = serde_json::from_slice(json)?;
+    Ok(serde_cbor::to_vec(&value)?)
+}
+
+/// Transcode a CBOR data item into JSON, without knowing its schema, available with the `json`
+/// feature.
+///
+/// Tags are dropped, byte strings become arrays of numbers and non-finite floats become `null`.
+/// Integer map keys become strings, and maps with other keys than text strings and integers
+/// cannot be transcoded.
+///
+/// # Example
+/// ```
+/// let json = actix_cbor::transcode_cbor_to_json(&[0xa1, 0x62, b'i', b'd', 0x07]).unwrap();
+/// assert_eq!(json, br#"{"id":7}"#);
+/// ```
+pub fn transcode_cbor_to_json(cbor: &[u8]) -> Result<Vec<u8>, CborTranscodeError> {
+    let value: serde_cbor::Value = serde_cbor::from_slice(cbor)?;
+    Ok(serde_json::to_vec(&value)?)
+}
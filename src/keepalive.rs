@@ -0,0 +1,55 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::rt::time::{sleep, Instant, Sleep};
+use actix_web::web::Bytes;
+use futures_util::stream::Stream;
+
+/// Body stream emitting a fixed frame whenever the inner stream stays idle for `interval`.
+pub(crate) struct KeepAlive<S> {
+    inner: S,
+    frame: Bytes,
+    interval: Duration,
+    timer: Pin<Box<Sleep>>,
+}
+
+impl<S> KeepAlive<S> {
+    pub(crate) fn new(inner: S, interval: Duration, frame: Bytes) -> Self {
+        Self {
+            inner,
+            frame,
+            interval,
+            timer: Box::pin(sleep(interval)),
+        }
+    }
+
+    fn restart_timer(&mut self) {
+        let deadline = Instant::now() + self.interval;
+        self.timer.as_mut().reset(deadline);
+    }
+}
+
+impl<S, E> Stream for KeepAlive<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(item) => {
+                self.restart_timer();
+                Poll::Ready(item)
+            }
+            Poll::Pending => match self.timer.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    self.restart_timer();
+                    Poll::Ready(Some(Ok(self.frame.clone())))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
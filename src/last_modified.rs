@@ -0,0 +1,102 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+    body::BoxBody,
+    http::{
+        header::{
+            Header, HttpDate, IfModifiedSince, LastModified, TryIntoHeaderValue, IF_NONE_MATCH,
+            LAST_MODIFIED,
+        },
+        Method,
+    },
+    HttpRequest, HttpResponse, Responder,
+};
+use serde::Serialize;
+
+use crate::Cbor;
+
+/// Truncate to whole seconds, the precision of HTTP dates.
+fn truncate(time: SystemTime) -> SystemTime {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Whether a `GET` or `HEAD` request already holds the representation last modified at
+/// `modified`, according to its `If-Modified-Since`.
+///
+/// `If-None-Match` takes precedence when present, in which case the date is not checked.
+pub(crate) fn is_not_modified_since(req: &HttpRequest, modified: SystemTime) -> bool {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return false;
+    }
+    if req.headers().contains_key(IF_NONE_MATCH) {
+        return false;
+    }
+
+    match IfModifiedSince::parse(req) {
+        Ok(IfModifiedSince(since)) => truncate(modified) <= SystemTime::from(since),
+        Err(_) => false,
+    }
+}
+
+/// Responder serializing `T` as CBOR along with its `Last-Modified` date.
+///
+/// `GET` and `HEAD` requests whose `If-Modified-Since` is not older than the modification date
+/// get a `304 Not Modified` response, without serializing the value. `If-None-Match` takes
+/// precedence when present, in which case the date is not checked.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborLastModified;
+/// use std::time::SystemTime;
+///
+/// async fn index() -> CborLastModified<Vec<u32>> {
+///     CborLastModified::new(vec![1, 2, 3], SystemTime::now())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CborLastModified<T> {
+    value: T,
+    modified: SystemTime,
+}
+
+impl<T> CborLastModified<T> {
+    /// Respond with `value`, last modified at `modified`.
+    pub fn new(value: T, modified: SystemTime) -> Self {
+        CborLastModified { value, modified }
+    }
+
+    /// Date the value was last modified.
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize + 'static> Responder for CborLastModified<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let last_modified = LastModified(HttpDate::from(self.modified));
+        if is_not_modified_since(req, self.modified) {
+            return HttpResponse::NotModified()
+                .insert_header(last_modified)
+                .finish();
+        }
+
+        let mut res = Cbor(self.value).respond_to(req);
+        if res.status().is_success() {
+            if let Ok(value) = last_modified.try_into_value() {
+                res.headers_mut().insert(LAST_MODIFIED, value);
+            }
+        }
+        res
+    }
+}
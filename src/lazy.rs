@@ -0,0 +1,106 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use actix_web::{dev::Payload, web::Bytes, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+
+use crate::body::body_decoder;
+use crate::{extraction_error, is_cbor_content_type, CborBody, CborConfig, HttpRequestExt};
+
+/// Extractor checking the content type and buffering a CBOR body, but decoding it only once
+/// [`parse`](#method.parse) is called.
+///
+/// Handlers which often answer before looking at the body, e.g. on failed authorization, skip
+/// the decoding cost. Payloads are checked against the request
+/// [`CborConfig`](struct.CborConfig.html) as with [`Cbor`](struct.Cbor.html), and the raw body
+/// stays available to the extractors that run after it.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborLazy;
+/// use actix_web::HttpRequest;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     item: String,
+/// }
+///
+/// async fn order(req: HttpRequest, order: CborLazy<Order>) -> actix_web::Result<String> {
+///     if req.headers().get("authorization").is_none() {
+///         return Ok("unauthorized".to_owned());
+///     }
+///     Ok(order.parse()?.item)
+/// }
+/// ```
+pub struct CborLazy<T> {
+    req: HttpRequest,
+    body: Bytes,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> CborLazy<T>
+where
+    T: DeserializeOwned,
+{
+    /// Decode the buffered body, failing as `Cbor` would.
+    pub fn parse(&self) -> Result<T, actix_web::Error> {
+        let config = CborConfig::from_req(&self.req);
+        let decode = body_decoder::<T>(
+            config.deny_unknown_fields == Some(true),
+            config.lossy_utf8 == Some(true),
+        );
+        decode(&self.body).map_err(|e| extraction_error(&config, e, &self.req))
+    }
+}
+
+impl<T> CborLazy<T> {
+    /// The raw body.
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Deconstruct to the raw body
+    pub fn into_bytes(self) -> Bytes {
+        self.body
+    }
+}
+
+impl<T> fmt::Debug for CborLazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborLazy: {:?}", self.body)
+    }
+}
+
+impl<T> FromRequest for CborLazy<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req).into_owned();
+        let ctype = config.content_type.clone();
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |_| Ok(()),
+        )
+        .tee(&req)
+        .limit(config.limit_for(&req));
+
+        Box::pin(async move {
+            body.await.map_err(|e| extraction_error(&config, e, &req))?;
+            let body = req.cbor_raw_body().unwrap_or_default();
+            Ok(CborLazy {
+                req,
+                body,
+                _value: PhantomData,
+            })
+        })
+    }
+}
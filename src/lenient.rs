@@ -0,0 +1,101 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_cbor::Value;
+
+use crate::{
+    decode_item, decode_item_strict, extraction_error, CborBody, CborConfig, CborPayloadError,
+};
+
+/// Fill the map entries missing from `value` with those of `defaults`, recursively.
+fn fill_defaults(value: &mut Value, defaults: Value) {
+    if let (Value::Map(map), Value::Map(defaults)) = (value, defaults) {
+        for (key, default) in defaults {
+            match map.get_mut(&key) {
+                Some(value) => fill_defaults(value, default),
+                None => {
+                    map.insert(key, default);
+                }
+            }
+        }
+    }
+}
+
+/// Extractor behaving like [`Cbor`](struct.Cbor.html), except that fields missing from the
+/// payload are taken from `T::default()` instead of failing.
+///
+/// Missing fields of nested maps are filled from the nested defaults. This suits tolerant
+/// ingestion endpoints receiving payloads from older clients that do not know about newer fields.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborLenient;
+///
+/// #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// struct Reading {
+///     value: f64,
+///     // not sent by older firmware
+///     battery: u8,
+/// }
+///
+/// async fn ingest(reading: CborLenient<Reading>) -> String {
+///     format!("{} ({}%)", reading.value, reading.battery)
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CborLenient<T>(pub T);
+
+impl<T> CborLenient<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborLenient<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborLenient<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> FromRequest for CborLenient<T>
+where
+    T: Default + Serialize + DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+        let decode = match config.deny_unknown_fields == Some(true) {
+            true => decode_item_strict::<T>,
+            false => decode_item::<T>,
+        };
+
+        CborBody::<Value>::new(req, payload, config.content_type.clone())
+            .limit(config.limit_for(req))
+            .map(move |res| {
+                let mut value = res?;
+                fill_defaults(&mut value, serde_cbor::value::to_value(T::default())?);
+                decode(&serde_cbor::to_vec(&value)?)
+            })
+            .map(move |res: Result<T, CborPayloadError>| match res {
+                Err(e) => Err(extraction_error(&config, e, &req2)),
+                Ok(data) => Ok(CborLenient(data)),
+            })
+            .boxed_local()
+    }
+}
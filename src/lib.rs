@@ -25,39 +25,283 @@
 #[macro_use]
 extern crate serde;
 
+use std::any::TypeId;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 
-#[cfg(feature = "compress")]
-use actix_web::dev::Decompress;
 use actix_web::{
-    dev::Payload, http::StatusCode, FromRequest, HttpRequest, HttpResponse, Responder,
+    body::BoxBody,
+    dev::Payload,
+    http::{Method, StatusCode},
+    web::Bytes,
+    FromRequest, HttpRequest, HttpResponse, Responder,
 };
-use futures_util::future::LocalBoxFuture;
+use futures_util::future::{ready, LocalBoxFuture};
 use futures_util::FutureExt;
 use log::error;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use body::decode_item;
+#[cfg(feature = "compress")]
+use compress::compress_above;
+use head::{cbor_body, encoded_len, head_response};
+use limit::{check_len, serialize_error, to_vec_limited};
+use profile::{negotiate_profile, set_profile};
+use spool::Spools;
+
+pub use accept_cbor::*;
+pub use accept_patch::*;
+/// Turn a plain `async fn` handler into an actix-web handler speaking CBOR.
+///
+/// The last argument is decoded from a [`Cbor`](struct.Cbor.html) body, and the other ones are
+/// extracted from the request unchanged. The output is serialized as with `Cbor`, honoring the
+/// request [`CborConfig`](struct.CborConfig.html). Handlers returning a `Result` answer their
+/// errors with a [`cbor_error_response`](fn.cbor_error_response.html), so the error type only
+/// needs to convert into `actix_web::Error`, as any `ResponseError` does.
+///
+/// # Example
+/// ```
+/// use actix_cbor::cbor_handler;
+/// use actix_web::{error::ErrorNotFound, web, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Rename {
+///     name: String,
+/// }
+///
+/// #[derive(serde::Serialize)]
+/// struct User {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// #[cbor_handler]
+/// async fn rename(id: web::Path<u32>, rename: Rename) -> actix_web::Result<User> {
+///     match id.into_inner() {
+///         0 => Err(ErrorNotFound("no such user")),
+///         id => Ok(User { id, name: rename.name }),
+///     }
+/// }
+///
+/// let app = App::new().route("/users/{id}", web::put().to(rename));
+/// ```
+#[cfg(feature = "macros")]
+pub use actix_cbor_macros::cbor_handler;
+/// Derive `Responder` and [`CborResponder`](trait.CborResponder.html) for a `Serialize` type, so
+/// that handlers return it directly as CBOR.
+///
+/// The response is the one of [`Cbor`](struct.Cbor.html). The optional `cbor_responder`
+/// attribute sets the status replacing `200 OK` with `status = ...`, and adds a header to
+/// successful responses with each `header("name", "value")`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborResponder;
+///
+/// #[derive(serde::Serialize, CborResponder)]
+/// #[cbor_responder(status = 201, header("X-Api-Version", "2"))]
+/// struct Created {
+///     id: u64,
+/// }
+///
+/// async fn create() -> Created {
+///     Created { id: 7 }
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use actix_cbor_macros::CborResponder;
+pub use arc::*;
+pub use backpressure::Watermarks;
+pub use batch::*;
 pub use body::*;
+pub use body_writer::*;
+pub use cached::*;
+pub use cached_cbor::*;
+pub use cbor_with::*;
+pub use cddl::*;
 pub use config::*;
+pub use config_layer::*;
 pub use error::*;
+pub use error_handlers::*;
+pub use etag::*;
+pub use for_each::*;
+pub use framing::*;
+pub use health::*;
+pub use http_request_ext::*;
 pub use http_response_builder_ext::*;
+pub use idempotency::*;
+pub use input::*;
+#[cfg(feature = "json")]
+pub use json::*;
+pub use last_modified::*;
+pub use lazy::*;
+pub use lenient::*;
+pub use memo::*;
+pub use meta::*;
+#[cfg(feature = "minicbor")]
+pub use mini_cbor::*;
+#[cfg(feature = "msgpack")]
+pub use msgpack::*;
+pub use multi_doc::*;
+pub use multi_status::*;
+#[cfg(feature = "multipart")]
+pub use multipart::*;
+pub use named_file::*;
+pub use page::*;
+pub use patch::*;
+pub use preencoded::*;
+pub use prefer::*;
+pub use profile::*;
+pub use rejection::*;
+pub use responder::*;
+pub use response_body::*;
+pub use response_cache::*;
+pub use sample_log::*;
+pub use schema_registry::*;
+pub use seq::*;
+#[cfg(feature = "session")]
+pub use session::*;
+pub use shared::*;
+pub use shutdown::*;
+pub use soft::*;
+pub use spooled::*;
+pub use sse::*;
+pub use status::*;
+pub use stream::*;
+pub use streaming::*;
+#[cfg(feature = "json")]
+pub use transcode_stream::*;
+pub use try_cbor::*;
+pub use versioned::*;
+pub use well_formed::*;
 
+mod accept_cbor;
+mod accept_patch;
+mod arc;
+mod backpressure;
+mod batch;
+#[cfg(feature = "tags")]
+pub mod bignum;
 mod body;
+mod body_writer;
+mod cached;
+mod cached_cbor;
+mod cbor_with;
+mod cddl;
+#[cfg(feature = "compress")]
+mod compress;
 mod config;
+mod config_layer;
+mod diag;
 mod error;
+mod error_handlers;
+mod etag;
+mod for_each;
+mod framing;
+pub mod guard;
+#[cfg(feature = "macros")]
+mod handler;
+mod head;
+mod health;
+mod http_request_ext;
 mod http_response_builder_ext;
+mod idempotency;
+mod input;
+#[cfg(feature = "json")]
+mod json;
+mod keepalive;
+mod last_modified;
+mod lazy;
+mod lenient;
+mod limit;
+mod memo;
+mod meta;
+#[cfg(feature = "minicbor")]
+mod mini_cbor;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod multi_doc;
+mod multi_status;
+#[cfg(feature = "multipart")]
+mod multipart;
+mod named_file;
+mod page;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod preencoded;
+mod prefer;
+mod profile;
+mod rejection;
+mod responder;
+mod response_body;
+mod response_cache;
+mod sample_log;
+mod scan;
+mod schema_registry;
+mod seq;
+#[cfg(feature = "session")]
+mod session;
+mod shared;
+mod shutdown;
+mod soft;
+mod spool;
+mod spooled;
+mod sse;
+mod status;
+mod stream;
+mod streaming;
+#[cfg(feature = "tags")]
+pub mod time_tags;
+#[cfg(feature = "json")]
+mod transcode_stream;
+mod try_cbor;
+#[cfg(feature = "tags")]
+pub mod typed_array;
+mod untagged;
+mod utf8;
+mod versioned;
+mod well_formed;
 
 #[cfg(test)]
 mod tests;
 
+// Lets the code generated by `actix-cbor-macros` name this crate from its own tests.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as actix_cbor;
+
 /// Extractor/Responder for CBOR encoded data.
 ///
 /// This will encode data with the content-type `application/cbor`.
 ///
 /// By default, it expects to receive data with that content-type as well.
 ///
+/// Derived structs are decoded from their named representation as well as from the packed one
+/// (maps keyed by field index, or arrays of fields), so clients can migrate from one to the other
+/// while the server accepts both. Types relying on `#[serde(flatten)]` or internally tagged
+/// enums only accept the named representation.
+///
+/// Requests without a body, or with an empty one, are rejected with
+/// `CborPayloadError::NoBody`. Extract an `Option<Cbor<T>>` when the body is optional.
+///
+/// Payloads over the limit are rejected, even with
+/// [`CborConfig::spool_to_disk`](struct.CborConfig.html#method.spool_to_disk); extract a
+/// [`CborSpooled`](struct.CborSpooled.html) to decode them from a temporary file.
+///
+/// Responses to `HEAD` requests carry the `Content-Length` of the encoded value, but no body.
+/// `Cbor(())` responds with `204 No Content` and no body, rather than a CBOR `null`.
+///
+/// Tags are stripped from decoded values. With the `tags` feature, `Cbor<serde_cbor::Value>`
+/// keeps them as `Value::Tag`, and responding with that value emits them again, so payloads can
+/// be inspected and forwarded unchanged. Typed extractors still ignore the tags of items read
+/// with a type hint, so a `u64` field accepts `1(1600000000)` and bodies may start with the
+/// self-described CBOR tag 55799. Items read without one (`serde_cbor::Value`,
+/// `serde_cbor::tags::Tagged<T>`, untagged enums, flattened fields) and newtype structs see their
+/// tag, as the [`bignum`](bignum/index.html), [`time_tags`](time_tags/index.html) and
+/// [`typed_array`](typed_array/index.html) helpers need.
+///
 /// # Example
 /// ```
 /// use actix_cbor::Cbor;
@@ -80,6 +324,18 @@ mod tests;
 ///     Cbor(Greeting { inner })
 /// }
 /// ```
+///
+/// The status can be changed inline by responding with a `(Cbor<T>, StatusCode)` tuple, and
+/// headers added through `Responder::with_header`:
+///
+/// ```
+/// use actix_cbor::Cbor;
+/// use actix_web::{http::StatusCode, Responder};
+///
+/// async fn create() -> impl Responder {
+///     (Cbor(vec![1, 2, 3]), StatusCode::CREATED).with_header(("x-version", "1.2.3"))
+/// }
+/// ```
 #[derive(Default, Clone)]
 pub struct Cbor<T>(pub T);
 
@@ -88,6 +344,57 @@ impl<T> Cbor<T> {
     pub fn into_inner(self) -> T {
         self.0
     }
+
+    /// Respond with a strong `ETag` hashed from the serialized body
+    pub fn with_etag(self) -> CborEtag<T> {
+        CborEtag(self.0)
+    }
+}
+
+impl<T> Cbor<T>
+where
+    T: DeserializeOwned,
+{
+    /// Decode a single CBOR item spanning `body`, as request bodies are decoded.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::Cbor;
+    ///
+    /// let numbers = Cbor::<Vec<u32>>::from_slice(&[0x82, 0x01, 0x02]).unwrap();
+    /// assert_eq!(*numbers, [1, 2]);
+    /// ```
+    pub fn from_slice(body: &[u8]) -> Result<Self, CborPayloadError> {
+        decode_item(body).map(Cbor)
+    }
+}
+
+impl<T> Cbor<T>
+where
+    T: Serialize,
+{
+    /// Encode the inner value, as response bodies are encoded.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::Cbor;
+    ///
+    /// assert_eq!(Cbor(vec![1, 2]).to_vec().unwrap(), [0x82, 0x01, 0x02]);
+    /// ```
+    pub fn to_vec(&self) -> Result<Vec<u8>, CborPayloadError> {
+        to_vec_limited(&self.0, None, None)
+    }
+}
+
+impl<T> TryFrom<Bytes> for Cbor<T>
+where
+    T: DeserializeOwned,
+{
+    type Error = CborPayloadError;
+
+    fn try_from(body: Bytes) -> Result<Self, Self::Error> {
+        Self::from_slice(&body)
+    }
 }
 
 impl<T> Deref for Cbor<T> {
@@ -115,18 +422,102 @@ where
 
 impl<T> Responder for Cbor<T>
 where
-    T: Serialize,
+    T: Serialize + 'static,
 {
-    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
-        match serde_cbor::to_vec(&self.0) {
-            Ok(body) => HttpResponse::build(StatusCode::OK)
-                .content_type("application/cbor")
-                .body(body),
-            Err(e) => {
-                error!("cbor serialization error: {}", e);
-                HttpResponse::InternalServerError().finish()
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        if TypeId::of::<T>() == TypeId::of::<()>() {
+            return HttpResponse::NoContent().finish();
+        }
+        respond_cbor(&self.0, req).unwrap_or_else(|e| response_error(e, req))
+    }
+}
+
+/// Log a failed response and render it, through the configured error handler when the response
+/// limit is exceeded.
+pub(crate) fn response_error(err: CborPayloadError, req: &HttpRequest) -> HttpResponse {
+    match err {
+        CborPayloadError::ResponseOverflow => {
+            error!("cbor response limit exceeded. Request path: {}", req.path());
+            let config = CborConfig::from_req(req);
+            match &config.err_handler {
+                Some(handler) => {
+                    HttpResponse::from_error((*handler)(CborPayloadError::ResponseOverflow, req))
+                }
+                None => HttpResponse::InternalServerError().finish(),
             }
         }
+        e => {
+            error!("cbor serialization error: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Respond with `value` according to the request and the configuration.
+pub(crate) fn respond_cbor<T: Serialize>(
+    value: &T,
+    req: &HttpRequest,
+) -> Result<HttpResponse, CborPayloadError> {
+    let config = CborConfig::from_req(req);
+    if config.return_minimal == Some(true) && prefers_minimal(req) {
+        return Ok(minimal_response());
+    }
+    let etag = config.etag == Some(true);
+    let profile = negotiate_profile(req);
+
+    // the size is enough to answer HEAD requests, unless the body must be hashed
+    let mut res = if req.method() == Method::HEAD && !etag {
+        let len = encoded_len(value, profile).map_err(serialize_error)?;
+        let len = check_len(len, config.response_limit)?;
+        head_response(&mut HttpResponse::build(StatusCode::OK), len)
+    } else {
+        let body = to_vec_limited(value, config.response_limit, profile)?;
+        match etag {
+            true => {
+                let etag =
+                    profile_etag(value, &body, profile).map_err(CborPayloadError::Serialize)?;
+                response_with_etag(req, body.into(), etag)
+            }
+            false => cbor_body(req, &mut HttpResponse::build(StatusCode::OK), body.into()),
+        }
+    };
+    set_profile(&mut res, profile);
+    #[cfg(feature = "compress")]
+    let res = compress_above(req, res, config.compress_threshold);
+    Ok(res)
+}
+
+impl<T> Cbor<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    /// Extract from the request as `FromRequest` does, following `config` instead of the
+    /// request `CborConfig`, so middleware and custom extractors reuse the same limits, content
+    /// type check and error mapping.
+    ///
+    /// # Example
+    /// ```
+    /// use actix_cbor::{Cbor, CborConfig};
+    /// use actix_web::{dev::Payload, HttpRequest};
+    ///
+    /// async fn extract_small(
+    ///     req: &HttpRequest,
+    ///     payload: &mut Payload,
+    /// ) -> actix_web::Result<Cbor<Vec<u32>>> {
+    ///     Cbor::extract(req, payload, &CborConfig::default().limit(1024)).await
+    /// }
+    /// ```
+    pub fn extract(
+        req: &HttpRequest,
+        payload: &mut Payload,
+        config: &CborConfig,
+    ) -> LocalBoxFuture<'static, Result<Self, actix_web::Error>> {
+        let strict = config.deny_unknown_fields == Some(true);
+        extract_cbor(req, payload, config, config.limit_for(req), strict, None)
+            .map(|res| res.map(Cbor))
+            .boxed_local()
     }
 }
 
@@ -136,34 +527,110 @@ where
 {
     type Error = actix_web::Error;
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
-    type Config = CborConfig;
 
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let req2 = req.clone();
-        let config = CborConfig::from_req(req);
+        Cbor::extract(req, payload, &CborConfig::from_req(req))
+    }
+}
 
-        let limit = config.limit;
-        let ctype = config.content_type.clone();
-        let err_handler = config.err_handler.clone();
+/// Buffer and decode a CBOR body of at most `limit` bytes, according to `config`.
+///
+/// When `strict`, map keys the target type does not consume are rejected. Payloads over the
+/// limit are decoded from a temporary file when `spools` are given.
+pub(crate) fn extract_cbor<T>(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    config: &CborConfig,
+    limit: usize,
+    strict: bool,
+    spools: Option<Spools<T>>,
+) -> LocalBoxFuture<'static, Result<T, actix_web::Error>>
+where
+    T: DeserializeOwned + 'static,
+{
+    // requests without a body, unless another extractor buffered it
+    if matches!(payload, Payload::None) && req.cbor_raw_body().is_none() {
+        let err = extraction_error(config, CborPayloadError::NoBody, req);
+        return ready(Err(err)).boxed_local();
+    }
 
-        CborBody::new(req, payload, ctype)
+    let req2 = req.clone();
+    let ctype = config.content_type.clone();
+    let sniff = config.sniff == Some(true);
+    let lossy = config.lossy_utf8 == Some(true);
+    let decode = body_decoder::<T>(strict, lossy);
+    let (spool, spool_validated) = match spools {
+        Some(spools) => (Some(spools.plain), Some(spools.validated)),
+        None => (None, None),
+    };
+
+    let schema_id = match &config.schema_registry {
+        Some(_) => request_schema_id(req, config.require_schema_id == Some(true)),
+        None => Ok(None),
+    };
+    let schema_id = match schema_id {
+        Ok(id) => id,
+        Err(e) => return ready(Err(extraction_error(config, e, req))).boxed_local(),
+    };
+
+    let accept = |mime: &str| is_cbor_content_type(mime, ctype.as_ref());
+    let body = match (&config.schema_registry, schema_id) {
+        (Some(registry), Some(id)) => validate_body(
+            CborBody::with_decoder(
+                req,
+                payload,
+                config,
+                accept,
+                validated_decoder(strict, lossy),
+            )
             .limit(limit)
-            .map(move |res| match res {
-                Err(e) => {
-                    log::debug!(
-                        "Failed to deserialize CBOR from payload. \
-                         Request path: {}",
-                        req2.path()
-                    );
-
-                    if let Some(err) = err_handler {
-                        Err((*err)(e, &req2))
-                    } else {
-                        Err(e.into())
-                    }
-                }
-                Ok(data) => Ok(Cbor(data)),
-            })
-            .boxed_local()
+            .sniff(sniff)
+            .spool(spool_validated),
+            registry.clone(),
+            id,
+        )
+        .boxed_local(),
+        _ => CborBody::with_decoder(req, payload, config, accept, decode)
+            .limit(limit)
+            .sniff(sniff)
+            .spool(spool)
+            .boxed_local(),
+    };
+
+    let config = config.clone();
+    body.map(move |res| res.map_err(|e| extraction_error(&config, e, &req2)))
+        .boxed_local()
+}
+
+/// Log a failed extraction and convert it through the error handler of `config`, if any.
+pub(crate) fn extraction_error(
+    config: &CborConfig,
+    err: CborPayloadError,
+    req: &HttpRequest,
+) -> actix_web::Error {
+    log::debug!(
+        "Failed to deserialize CBOR from payload. \
+         Request path: {}, label: {}",
+        req.path(),
+        config.metric_label.unwrap_or("-")
+    );
+
+    match &config.err_handler {
+        Some(handler) => (*handler)(err, req),
+        None => match &config.messages {
+            Some(catalog) => {
+                let content_type = config.error_content_type.clone();
+                CborRejection::localized(err, catalog, content_type, req).into()
+            }
+            None => err.into(),
+        },
     }
 }
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "macros")]
+    pub use crate::handler::{respond_derived, respond_result, respond_value};
+    pub use actix_web::{body::BoxBody, HttpRequest, HttpResponse, Responder};
+    pub use serde::Serialize;
+}
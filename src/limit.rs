@@ -0,0 +1,58 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::profile::write_profile;
+use crate::{CborPayloadError, CborProfile};
+
+/// `io::Write` sink buffering up to `limit` bytes, failing past it.
+struct Limited {
+    buf: Vec<u8>,
+    limit: usize,
+    overflow: bool,
+}
+
+impl io::Write for Limited {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > self.limit {
+            self.overflow = true;
+            return Err(io::Error::other("cbor response limit exceeded"));
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Encode `value` with `profile`, giving up as soon as the encoding grows past `limit` bytes.
+pub(crate) fn to_vec_limited<T: Serialize>(
+    value: &T,
+    limit: Option<usize>,
+    profile: Option<CborProfile>,
+) -> Result<Vec<u8>, CborPayloadError> {
+    let mut writer = Limited {
+        buf: Vec::new(),
+        limit: limit.unwrap_or(usize::MAX),
+        overflow: false,
+    };
+    match write_profile(&mut writer, value, profile) {
+        Ok(()) => Ok(writer.buf),
+        Err(_) if writer.overflow => Err(CborPayloadError::ResponseOverflow),
+        Err(e) => Err(serialize_error(e)),
+    }
+}
+
+/// Check an encoding of `len` bytes against `limit`.
+pub(crate) fn check_len(len: u64, limit: Option<usize>) -> Result<u64, CborPayloadError> {
+    match limit {
+        Some(limit) if len > limit as u64 => Err(CborPayloadError::ResponseOverflow),
+        _ => Ok(len),
+    }
+}
+
+pub(crate) fn serialize_error(e: serde_cbor::Error) -> CborPayloadError {
+    CborPayloadError::Serialize(e.into())
+}
@@ -0,0 +1,208 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::BoxBody, http::header::EntityTag, web::Bytes, HttpRequest, HttpResponse, Responder,
+};
+use serde::Serialize;
+
+use crate::{etag_for_bytes, response_with_etag, CborError};
+
+type Key = (TypeId, String);
+
+struct Entry {
+    body: Bytes,
+    etag: EntityTag,
+    expires: Instant,
+}
+
+/// Cache of serialized CBOR responses, keyed by a caller-provided key and the type of the cached
+/// value.
+///
+/// Entries live until their time to live elapses or they are invalidated, so that repeated
+/// requests skip both producing and serializing the value. The cache is cheap to clone and can be
+/// shared across workers.
+///
+/// The cache holds up to [`max_entries`](#method.max_entries) entries, 10000 by default. When it
+/// is full, expired entries are dropped first, then the entry closest to expiring.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborMemo, CborMemoized};
+/// use actix_web::{web, App};
+/// use std::time::Duration;
+///
+/// #[derive(serde::Serialize)]
+/// struct Product {
+///     name: String,
+/// }
+///
+/// async fn load_catalog() -> Vec<Product> {
+///     vec![Product {
+///         name: "Chair".to_owned(),
+///     }]
+/// }
+///
+/// async fn catalog(memo: web::Data<CborMemo>) -> actix_web::Result<CborMemoized> {
+///     Ok(memo.get_or_insert_with("catalog", load_catalog).await?)
+/// }
+///
+/// let memo = CborMemo::new(Duration::from_secs(30));
+/// let app = App::new()
+///     .app_data(web::Data::new(memo))
+///     .route("/catalog", web::get().to(catalog));
+/// ```
+#[derive(Clone)]
+pub struct CborMemo {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Arc<Mutex<HashMap<Key, Entry>>>,
+}
+
+impl CborMemo {
+    /// Create an empty cache whose entries expire after `ttl`, holding up to 10000 entries.
+    pub fn new(ttl: Duration) -> Self {
+        CborMemo {
+            ttl,
+            max_entries: 10_000,
+            entries: Default::default(),
+        }
+    }
+
+    /// Change the max number of cached responses.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Cached response for `key`, if it did not expire.
+    pub fn get<T: 'static>(&self, key: &str) -> Option<CborMemoized> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&(TypeId::of::<T>(), key.to_owned()))
+            .filter(|entry| entry.expires > Instant::now())
+            .map(|entry| CborMemoized {
+                body: entry.body.clone(),
+                etag: entry.etag.clone(),
+            })
+    }
+
+    /// Serialize and cache `value` under `key`, replacing any previous entry.
+    pub fn insert<T>(&self, key: impl Into<String>, value: &T) -> Result<CborMemoized, CborError>
+    where
+        T: Serialize + 'static,
+    {
+        let body = Bytes::from(serde_cbor::to_vec(value)?);
+        let etag = etag_for_bytes(&body);
+        let memoized = CborMemoized {
+            body: body.clone(),
+            etag: etag.clone(),
+        };
+        if self.max_entries == 0 {
+            return Ok(memoized);
+        }
+
+        let now = Instant::now();
+        let key = (TypeId::of::<T>(), key.into());
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let expires = now + self.ttl;
+        entries.insert(
+            key,
+            Entry {
+                body,
+                etag,
+                expires,
+            },
+        );
+        Ok(memoized)
+    }
+
+    /// Cached response for `key`, producing and caching the value with `f` on a miss.
+    ///
+    /// Concurrent misses may each run `f`; the last one to complete is kept.
+    pub async fn get_or_insert_with<T, F, Fut>(
+        &self,
+        key: impl Into<String>,
+        f: F,
+    ) -> Result<CborMemoized, CborError>
+    where
+        T: Serialize + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let key = key.into();
+        match self.get::<T>(&key) {
+            Some(memoized) => Ok(memoized),
+            None => self.insert(key, &f().await),
+        }
+    }
+
+    /// Drop the entry of type `T` cached under `key`.
+    pub fn invalidate<T: 'static>(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&(TypeId::of::<T>(), key.to_owned()));
+    }
+
+    /// Drop every entry of type `T`.
+    pub fn invalidate_type<T: 'static>(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|(type_id, _), _| *type_id != TypeId::of::<T>());
+    }
+
+    /// Drop every entry.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Drop the expired entries, releasing their memory.
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires > now);
+    }
+}
+
+/// Responder for a body served from a [`CborMemo`](struct.CborMemo.html).
+///
+/// Responses carry the `ETag` computed when the value was cached, and `GET` requests whose
+/// `If-None-Match` matches it get a `304 Not Modified` response.
+#[derive(Debug, Clone)]
+pub struct CborMemoized {
+    body: Bytes,
+    etag: EntityTag,
+}
+
+impl CborMemoized {
+    /// The encoded payload.
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Tag of the encoded payload.
+    pub fn etag(&self) -> &EntityTag {
+        &self.etag
+    }
+}
+
+impl Responder for CborMemoized {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        response_with_etag(req, self.body, self.etag)
+    }
+}
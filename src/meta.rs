@@ -0,0 +1,130 @@
+use std::fmt;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use mime::Mime;
+use serde::de::DeserializeOwned;
+
+use crate::body::body_decoder;
+use crate::etag::hash_bytes;
+use crate::{extraction_error, is_cbor_content_type, CborBody, CborConfig, HttpRequestExt};
+
+/// Extractor decoding a CBOR body like [`Cbor`](struct.Cbor.html), along with metadata about
+/// the payload: its content type, length, hash and decoding time.
+///
+/// The hash is the 64-bit hash of the body also used for `ETag`s: FNV-1a, or XXH3 with the
+/// `xxhash` feature. It tells payloads apart, e.g. to spot duplicates, but is not
+/// cryptographic: it cannot vouch for the integrity of a payload, as a `Content-Digest` would.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborMeta;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     item: String,
+/// }
+///
+/// async fn order(order: CborMeta<Order>) -> String {
+///     format!("{} ({} bytes, hash {:016x})", order.item, order.len(), order.hash())
+/// }
+/// ```
+pub struct CborMeta<T> {
+    value: T,
+    content_type: Option<Mime>,
+    len: usize,
+    hash: u64,
+    decode_time: Duration,
+}
+
+impl<T> CborMeta<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Content type of the request, if valid.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Length of the raw body, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the raw body is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Non-cryptographic 64-bit hash of the raw body.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Time spent decoding the body, excluding the time spent receiving it.
+    pub fn decode_time(&self) -> Duration {
+        self.decode_time
+    }
+}
+
+impl<T> Deref for CborMeta<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CborMeta<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborMeta: {:?}", self.value)
+    }
+}
+
+impl<T> FromRequest for CborMeta<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req).into_owned();
+        let ctype = config.content_type.clone();
+        let decode = body_decoder::<T>(
+            config.deny_unknown_fields == Some(true),
+            config.lossy_utf8 == Some(true),
+        );
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |_| Ok(()),
+        )
+        .tee(&req)
+        .limit(config.limit_for(&req));
+
+        Box::pin(async move {
+            body.await.map_err(|e| extraction_error(&config, e, &req))?;
+            let body = req.cbor_raw_body().unwrap_or_default();
+
+            let start = Instant::now();
+            let decoded = decode(&body);
+            let decode_time = start.elapsed();
+
+            Ok(CborMeta {
+                value: decoded.map_err(|e| extraction_error(&config, e, &req))?,
+                content_type: req.mime_type().ok().flatten(),
+                len: body.len(),
+                hash: hash_bytes(&body),
+                decode_time,
+            })
+        })
+    }
+}
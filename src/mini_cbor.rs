@@ -0,0 +1,145 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{
+    body::BoxBody, dev::Payload, http::StatusCode, FromRequest, HttpRequest, HttpResponse,
+    Responder,
+};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use log::error;
+
+use crate::head::cbor_body;
+use crate::limit::check_len;
+use crate::{
+    extraction_error, is_cbor_content_type, minimal_response, prefers_minimal, response_error,
+    tagged_response, CborBody, CborConfig, CborPayloadError,
+};
+
+/// Extractor/Responder for CBOR encoded data, through the `minicbor` `Decode` and `Encode`
+/// traits instead of serde.
+///
+/// Payloads are checked against the limits, content type and error handler of the request
+/// [`CborConfig`](struct.CborConfig.html), and responses honor its response limit, `ETag` and
+/// `Prefer: return=minimal` settings, as with [`Cbor`](struct.Cbor.html).
+///
+/// # Example
+/// ```
+/// use actix_cbor::MiniCbor;
+/// use minicbor::{Decode, Encode};
+///
+/// #[derive(Decode, Encode)]
+/// struct Point {
+///     #[n(0)] x: i32,
+///     #[n(1)] y: i32,
+/// }
+///
+/// async fn mirror(point: MiniCbor<Point>) -> MiniCbor<Point> {
+///     let Point { x, y } = point.into_inner();
+///     MiniCbor(Point { x: -x, y: -y })
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct MiniCbor<T>(pub T);
+
+impl<T> MiniCbor<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for MiniCbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MiniCbor<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for MiniCbor<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MiniCbor: {:?}", self.0)
+    }
+}
+
+/// Decode a single `minicbor` item spanning the whole body.
+fn decode_mini<U>(body: &[u8]) -> Result<U, CborPayloadError>
+where
+    U: for<'b> minicbor::Decode<'b, ()>,
+{
+    let mut decoder = minicbor::Decoder::new(body);
+    let value = decoder.decode()?;
+    if decoder.position() != body.len() {
+        let err = minicbor::decode::Error::message("trailing data").at(decoder.position());
+        return Err(err.into());
+    }
+    Ok(value)
+}
+
+impl<T> FromRequest for MiniCbor<T>
+where
+    T: for<'b> minicbor::Decode<'b, ()> + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+        let ctype = config.content_type.clone();
+
+        CborBody::with_decoder(
+            req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            decode_mini::<T>,
+        )
+        .limit(config.limit_for(req))
+        .map(move |res| match res {
+            Ok(value) => Ok(MiniCbor(value)),
+            Err(e) => Err(extraction_error(&config, e, &req2)),
+        })
+        .boxed_local()
+    }
+}
+
+impl<T> Responder for MiniCbor<T>
+where
+    T: minicbor::Encode<()>,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let config = CborConfig::from_req(req);
+        if config.return_minimal == Some(true) && prefers_minimal(req) {
+            return minimal_response();
+        }
+
+        let body = match minicbor::to_vec(&self.0) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("cbor serialization error: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        if let Err(e) = check_len(body.len() as u64, config.response_limit) {
+            return response_error(e, req);
+        }
+
+        match config.etag == Some(true) {
+            true => tagged_response(req, body),
+            false => cbor_body(req, &mut HttpResponse::build(StatusCode::OK), body.into()),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    http::{
+        header::{HeaderValue, Quality, CONTENT_TYPE},
+        StatusCode,
+    },
+    FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use log::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::head::cbor_body;
+use crate::http_request_ext::{add_vary, parse_accept, rank};
+use crate::limit::check_len;
+use crate::{
+    extract_cbor, extraction_error, minimal_response, prefers_minimal, respond_cbor,
+    response_error, tagged_response, CborBody, CborConfig, CborPayloadError,
+};
+
+/// Content type of MessagePack bodies.
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Whether a content type is MessagePack, including the legacy `x-` name.
+fn is_msgpack_content_type(mime: &str) -> bool {
+    mime == MSGPACK_CONTENT_TYPE || mime == "application/x-msgpack"
+}
+
+/// Whether the `Accept` header of `req` ranks MessagePack strictly above CBOR.
+fn prefers_msgpack(req: &HttpRequest) -> bool {
+    let accept = match parse_accept(req) {
+        Some(accept) => accept,
+        None => return false,
+    };
+
+    let msgpack =
+        rank(&accept, "application", "msgpack").max(rank(&accept, "application", "x-msgpack"));
+    msgpack.is_some_and(|(quality, ..)| quality > Quality::ZERO)
+        && msgpack > rank(&accept, "application", "cbor")
+}
+
+/// Decode a MessagePack body, with structs encoded as maps or arrays.
+fn decode_msgpack<U: DeserializeOwned>(body: &[u8]) -> Result<U, CborPayloadError> {
+    Ok(rmp_serde::from_slice(body)?)
+}
+
+/// Extractor/Responder for CBOR or MessagePack encoded data, so a single handler serves both
+/// encodings.
+///
+/// Request bodies are decoded according to their `Content-Type`: `application/msgpack` (or
+/// `application/x-msgpack`) bodies as MessagePack, other bodies as
+/// [`Cbor`](struct.Cbor.html) does. Both go through the limits and error handler of the request
+/// [`CborConfig`](struct.CborConfig.html).
+///
+/// Responses are encoded as MessagePack when the `Accept` header ranks it strictly above CBOR,
+/// and as `Cbor` otherwise.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborOrMsgpack;
+///
+/// #[derive(serde::Deserialize, serde::Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn echo(user: CborOrMsgpack<User>) -> CborOrMsgpack<User> {
+///     user
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct CborOrMsgpack<T>(pub T);
+
+impl<T> CborOrMsgpack<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborOrMsgpack<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborOrMsgpack<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for CborOrMsgpack<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborOrMsgpack: {:?}", self.0)
+    }
+}
+
+impl<T> FromRequest for CborOrMsgpack<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req).into_owned();
+        if !is_msgpack_content_type(req.content_type()) {
+            let strict = config.deny_unknown_fields == Some(true);
+            return extract_cbor(req, payload, &config, config.limit_for(req), strict, None)
+                .map(|res| res.map(CborOrMsgpack))
+                .boxed_local();
+        }
+
+        let req2 = req.clone();
+        CborBody::with_decoder(req, payload, &config, |_| true, decode_msgpack::<T>)
+            .limit(config.limit_for(req))
+            .map(move |res| match res {
+                Ok(value) => Ok(CborOrMsgpack(value)),
+                Err(e) => Err(extraction_error(&config, e, &req2)),
+            })
+            .boxed_local()
+    }
+}
+
+impl<T> Responder for CborOrMsgpack<T>
+where
+    T: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let mut res = self.respond_negotiated(req);
+        // either format may be chosen for the same URL
+        add_vary(&mut res, "accept");
+        res
+    }
+}
+
+impl<T: Serialize> CborOrMsgpack<T> {
+    /// Response in the format preferred by the `Accept` header of `req`.
+    fn respond_negotiated(self, req: &HttpRequest) -> HttpResponse {
+        if !prefers_msgpack(req) {
+            return respond_cbor(&self.0, req).unwrap_or_else(|e| response_error(e, req));
+        }
+
+        let config = CborConfig::from_req(req);
+        if config.return_minimal == Some(true) && prefers_minimal(req) {
+            return minimal_response();
+        }
+
+        let body = match rmp_serde::to_vec_named(&self.0) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("msgpack serialization error: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        if let Err(e) = check_len(body.len() as u64, config.response_limit) {
+            return response_error(e, req);
+        }
+
+        let mut res = match config.etag == Some(true) {
+            true => tagged_response(req, body),
+            false => cbor_body(req, &mut HttpResponse::build(StatusCode::OK), body.into()),
+        };
+        if res.status() != StatusCode::NOT_MODIFIED {
+            res.headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static(MSGPACK_CONTENT_TYPE));
+        }
+        res
+    }
+}
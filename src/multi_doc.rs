@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use actix_web::{dev::Payload, web::Bytes, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::Deserialize;
+
+use crate::body::body_decoder;
+use crate::{
+    extraction_error, is_cbor_content_type, CborBody, CborConfig, CborPayloadError, HttpRequestExt,
+};
+
+/// Extractor for envelopes: payloads whose top-level map holds named CBOR documents, each one
+/// embedded as a byte string.
+///
+/// Documents are kept as slices of the body, and only decoded when asked for with
+/// [`decode`](#method.decode), following the [`CborConfig`](struct.CborConfig.html) of the
+/// request. Indefinite-length byte strings, whose chunks are not contiguous in the body, are
+/// copied. Envelopes holding anything else than byte strings are rejected.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborMultiDoc;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Manifest {
+///     version: String,
+/// }
+///
+/// async fn update(docs: CborMultiDoc) -> actix_web::Result<String> {
+///     let manifest: Manifest = match docs.decode("manifest") {
+///         Some(manifest) => manifest?,
+///         None => return Ok("missing manifest".to_owned()),
+///     };
+///     let image = docs.get("image").map_or(0, |image| image.len());
+///     Ok(format!("{}: {} bytes", manifest.version, image))
+/// }
+/// ```
+pub struct CborMultiDoc {
+    docs: BTreeMap<String, Bytes>,
+    decode_strict: bool,
+    lossy_utf8: bool,
+}
+
+impl CborMultiDoc {
+    /// Raw document named `name`.
+    pub fn get(&self, name: &str) -> Option<&Bytes> {
+        self.docs.get(name)
+    }
+
+    /// Decode the document named `name`, if any, failing as `Cbor` would.
+    pub fn decode<T: DeserializeOwned>(&self, name: &str) -> Option<Result<T, CborPayloadError>> {
+        let decode = body_decoder::<T>(self.decode_strict, self.lossy_utf8);
+        self.docs.get(name).map(|doc| decode(doc))
+    }
+
+    /// Names and raw documents, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Bytes)> {
+        self.docs.iter().map(|(name, doc)| (name.as_str(), doc))
+    }
+
+    /// Number of documents.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Whether the envelope holds no document.
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Deconstruct to the raw documents, by name
+    pub fn into_inner(self) -> BTreeMap<String, Bytes> {
+        self.docs
+    }
+}
+
+impl fmt::Debug for CborMultiDoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborMultiDoc: {:?}", self.docs)
+    }
+}
+
+/// Document of an envelope: a slice of the body, or a copy of the chunks of an
+/// indefinite-length byte string.
+enum Doc<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Vec<u8>),
+}
+
+impl<'de> Deserialize<'de> for Doc<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(DocVisitor)
+    }
+}
+
+struct DocVisitor;
+
+impl<'de> Visitor<'de> for DocVisitor {
+    type Value = Doc<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte string")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, bytes: &'de [u8]) -> Result<Doc<'de>, E> {
+        Ok(Doc::Borrowed(bytes))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Doc<'de>, E> {
+        Ok(Doc::Owned(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Doc<'de>, E> {
+        Ok(Doc::Owned(bytes))
+    }
+}
+
+/// Split an envelope into its documents, sharing the memory of `body` when they are contiguous.
+fn split_docs(body: &Bytes) -> Result<BTreeMap<String, Bytes>, CborPayloadError> {
+    if body.is_empty() {
+        return Err(CborPayloadError::NoBody);
+    }
+    let docs: BTreeMap<String, Doc<'_>> = serde_cbor::from_slice(body)?;
+    Ok(docs
+        .into_iter()
+        .map(|(name, doc)| match doc {
+            Doc::Borrowed(doc) => (name, body.slice_ref(doc)),
+            Doc::Owned(doc) => (name, Bytes::from(doc)),
+        })
+        .collect())
+}
+
+impl FromRequest for CborMultiDoc {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req).into_owned();
+        let ctype = config.content_type.clone();
+        let decode_strict = config.deny_unknown_fields == Some(true);
+        let lossy_utf8 = config.lossy_utf8 == Some(true);
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |_| Ok(()),
+        )
+        .tee(&req)
+        .limit(config.limit_for(&req));
+
+        Box::pin(async move {
+            let docs = async {
+                body.await?;
+                split_docs(&req.cbor_raw_body().unwrap_or_default())
+            };
+            let docs = docs.await.map_err(|e| extraction_error(&config, e, &req))?;
+            Ok(CborMultiDoc {
+                docs,
+                decode_strict,
+                lossy_utf8,
+            })
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use std::iter::FromIterator;
+
+use actix_web::{body::BoxBody, http::StatusCode, HttpRequest, HttpResponse, Responder};
+use log::error;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+
+use crate::CBOR_CONTENT_TYPE_VALUE;
+
+/// Responder reporting the outcome of every item of a bulk request with `207 Multi-Status`.
+///
+/// The body is a CBOR array with one map per item: `{"status": 201, "body": ...}` for successes
+/// and `{"status": 422, "error": ...}` for failures.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborBatch, CborMultiStatus};
+///
+/// #[derive(serde::Deserialize, serde::Serialize)]
+/// struct Reading {
+///     value: f64,
+/// }
+///
+/// async fn ingest(batch: CborBatch<Reading>) -> CborMultiStatus<Reading, String> {
+///     batch
+///         .into_results()
+///         .into_iter()
+///         .map(|res| res.map_err(|e| e.to_string()))
+///         .collect()
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CborMultiStatus<T, E> {
+    outcomes: Vec<(StatusCode, Result<T, E>)>,
+}
+
+impl<T, E> Default for CborMultiStatus<T, E> {
+    fn default() -> Self {
+        Self {
+            outcomes: Vec::new(),
+        }
+    }
+}
+
+impl<T, E> CborMultiStatus<T, E> {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the outcome of the next item with an explicit status.
+    pub fn push(&mut self, status: StatusCode, outcome: Result<T, E>) {
+        self.outcomes.push((status, outcome));
+    }
+
+    /// Record a successful item.
+    pub fn push_ok(&mut self, status: StatusCode, item: T) {
+        self.push(status, Ok(item));
+    }
+
+    /// Record a failed item.
+    pub fn push_err(&mut self, status: StatusCode, error: E) {
+        self.push(status, Err(error));
+    }
+
+    /// Number of recorded outcomes.
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    /// Whether no outcome was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+}
+
+/// Collects outcomes as `200 OK` for successes and `422 Unprocessable Entity` for failures.
+impl<T, E> FromIterator<Result<T, E>> for CborMultiStatus<T, E> {
+    fn from_iter<I: IntoIterator<Item = Result<T, E>>>(iter: I) -> Self {
+        let outcomes = iter
+            .into_iter()
+            .map(|res| match res {
+                Ok(_) => (StatusCode::OK, res),
+                Err(_) => (StatusCode::UNPROCESSABLE_ENTITY, res),
+            })
+            .collect();
+        Self { outcomes }
+    }
+}
+
+impl<T, E> Serialize for CborMultiStatus<T, E>
+where
+    T: Serialize,
+    E: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.outcomes.len()))?;
+        for (status, outcome) in &self.outcomes {
+            seq.serialize_element(&Outcome(*status, outcome))?;
+        }
+        seq.end()
+    }
+}
+
+/// Single element of the multi-status array.
+struct Outcome<'a, T, E>(StatusCode, &'a Result<T, E>);
+
+impl<T, E> Serialize for Outcome<'_, T, E>
+where
+    T: Serialize,
+    E: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("status", &self.0.as_u16())?;
+        match self.1 {
+            Ok(body) => map.serialize_entry("body", body)?,
+            Err(error) => map.serialize_entry("error", error)?,
+        }
+        map.end()
+    }
+}
+
+impl<T, E> Responder for CborMultiStatus<T, E>
+where
+    T: Serialize,
+    E: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        match serde_cbor::to_vec(&self) {
+            Ok(body) => HttpResponse::build(StatusCode::MULTI_STATUS)
+                .content_type(CBOR_CONTENT_TYPE_VALUE.clone())
+                .body(body),
+            Err(e) => {
+                error!("cbor serialization error: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
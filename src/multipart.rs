@@ -0,0 +1,250 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::{
+    dev::Payload,
+    web::{Bytes, BytesMut},
+    FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use futures_util::StreamExt;
+use mime::Mime;
+use serde::de::DeserializeOwned;
+
+use crate::untagged;
+use crate::{extraction_error, is_cbor_content_type, take_payload, CborConfig, CborPayloadError};
+
+/// Default size limit of a multipart field (32kB).
+const DEFAULT_FIELD_LIMIT: usize = 32_768;
+
+/// Content type of a multipart field, `application/octet-stream` when it has none.
+fn field_mime(field: &Field) -> &str {
+    field
+        .content_type()
+        .map_or("application/octet-stream", |mime| mime.essence_str())
+}
+
+/// CBOR value read from a single `actix-multipart` field.
+///
+/// Parts without a `Content-Type` are accepted as well as `application/cbor` ones.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborField;
+/// use actix_multipart::Multipart;
+/// use futures_util::TryStreamExt;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Metadata {
+///     name: String,
+/// }
+///
+/// async fn upload(mut parts: Multipart) -> actix_web::Result<String> {
+///     let mut field = parts.try_next().await?.unwrap();
+///     let meta = CborField::<Metadata>::from_field(&mut field).await?;
+///     Ok(meta.name.clone())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CborField<T>(pub T);
+
+impl<T> CborField<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned> CborField<T> {
+    /// Buffer `field`, up to 32kB, and deserialize it.
+    pub async fn from_field(field: &mut Field) -> Result<Self, CborPayloadError> {
+        Self::from_field_with_limit(field, DEFAULT_FIELD_LIMIT).await
+    }
+
+    /// Buffer `field`, up to `limit` bytes, and deserialize it.
+    pub async fn from_field_with_limit(
+        field: &mut Field,
+        limit: usize,
+    ) -> Result<Self, CborPayloadError> {
+        let mime = field_mime(field);
+        if mime != "application/octet-stream" && !is_cbor_content_type(mime, None) {
+            return Err(CborPayloadError::ContentType);
+        }
+
+        read_field(field, limit).await.map(CborField)
+    }
+}
+
+/// Buffer a field, up to `limit` bytes, and deserialize it.
+async fn read_field<T: DeserializeOwned>(
+    field: &mut Field,
+    limit: usize,
+) -> Result<T, CborPayloadError> {
+    Ok(untagged::from_slice(&buffer_field(field, limit).await?)?)
+}
+
+/// Buffer a field, up to `limit` bytes.
+async fn buffer_field(field: &mut Field, limit: usize) -> Result<BytesMut, CborPayloadError> {
+    let mut body = BytesMut::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > limit {
+            return Err(CborPayloadError::Overflow);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// `Content-ID` of a field, or `start` parameter, without its quotes.
+fn unquote(id: &str) -> &str {
+    id.trim().trim_matches('"')
+}
+
+impl<T> Deref for CborField<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborField<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// Part of a `multipart/related` request sent before its root, buffered in memory.
+#[derive(Debug, Clone)]
+pub struct RelatedPart {
+    /// `Content-ID` of the part, if any
+    pub content_id: Option<String>,
+    /// Content type of the part, if any
+    pub content_type: Option<Mime>,
+    /// Content of the part
+    pub body: Bytes,
+}
+
+/// Extractor for `multipart/related` requests whose root part is a CBOR document.
+///
+/// As in [RFC 2387](https://tools.ietf.org/html/rfc2387), the root is the part whose
+/// `Content-ID` matches the `start` parameter of the request content type, or the first part
+/// without one. Parts sent before the root are buffered and available from
+/// [`preceding`](#method.preceding); together with the root, they are subject to the
+/// [`CborConfig`](struct.CborConfig.html) limit. The parts following the root, such as
+/// attachments, are left unread and streamed from [`parts`](#method.parts).
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborRelated;
+/// use futures_util::TryStreamExt;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Document {
+///     title: String,
+/// }
+///
+/// async fn upload(mut doc: CborRelated<Document>) -> actix_web::Result<String> {
+///     let mut attachments = 0;
+///     while let Some(mut part) = doc.parts().try_next().await? {
+///         while part.try_next().await?.is_some() {}
+///         attachments += 1;
+///     }
+///     Ok(format!("{} with {} attachments", doc.root().title, attachments))
+/// }
+/// ```
+pub struct CborRelated<T> {
+    root: T,
+    preceding: Vec<RelatedPart>,
+    parts: Multipart,
+}
+
+impl<T> CborRelated<T> {
+    /// The decoded root document.
+    pub fn root(&self) -> &T {
+        &self.root
+    }
+
+    /// The parts sent before the root, in order.
+    pub fn preceding(&self) -> &[RelatedPart] {
+        &self.preceding
+    }
+
+    /// Stream of the parts following the root.
+    pub fn parts(&mut self) -> &mut Multipart {
+        &mut self.parts
+    }
+
+    /// Deconstruct to the root document and the remaining parts.
+    pub fn into_parts(self) -> (T, Multipart) {
+        (self.root, self.parts)
+    }
+}
+
+impl<T> FromRequest for CborRelated<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+        let ctype = config.content_type.clone();
+        let limit = config.limit_for(req);
+
+        let is_related = req.content_type() == "multipart/related";
+        let start = match req.mime_type() {
+            Ok(Some(mime)) => mime
+                .get_param("start")
+                .map(|start| unquote(start.as_str()).to_owned()),
+            _ => None,
+        };
+        let mut parts = Multipart::new(req.headers(), take_payload(req, payload));
+
+        async move {
+            if !is_related {
+                return Err(CborPayloadError::ContentType);
+            }
+
+            let mut preceding = Vec::new();
+            let mut remaining = limit;
+            let mut field = loop {
+                let mut field = match parts.next().await {
+                    Some(field) => field?,
+                    None => return Err(MultipartError::Incomplete.into()),
+                };
+                let content_id = field
+                    .headers()
+                    .get("content-id")
+                    .and_then(|id| id.to_str().ok())
+                    .map(|id| unquote(id).to_owned());
+                if start.is_none() || start == content_id {
+                    break field;
+                }
+
+                let body = buffer_field(&mut field, remaining).await?;
+                remaining -= body.len();
+                preceding.push(RelatedPart {
+                    content_id,
+                    content_type: field.content_type().cloned(),
+                    body: body.freeze(),
+                });
+            };
+            if !is_cbor_content_type(field_mime(&field), ctype.as_ref()) {
+                return Err(CborPayloadError::ContentType);
+            }
+
+            let root = read_field(&mut field, remaining).await?;
+            Ok(CborRelated {
+                root,
+                preceding,
+                parts,
+            })
+        }
+        .map(move |res| res.map_err(|e| extraction_error(&config, e, &req2)))
+        .boxed_local()
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+    body::BoxBody,
+    http::header::{ETag, EntityTag, HttpDate, LastModified},
+    rt::task::spawn_blocking,
+    web::{Bytes, BytesMut},
+    HttpRequest, HttpResponse, Responder,
+};
+use futures_util::stream::{self, StreamExt};
+
+use crate::scan::{Sniffer, DEFAULT_MAX_DEPTH};
+use crate::{is_not_modified, is_not_modified_since, CborPayloadError, CBOR_CONTENT_TYPE_VALUE};
+
+/// Size of the chunks files are streamed in.
+const CHUNK_SIZE: usize = 65_536;
+
+/// Responder serving a CBOR file from disk, like `actix-files`' `NamedFile`.
+///
+/// Responses carry the `application/cbor` content type along with an `ETag` and a
+/// `Last-Modified` date derived from the file metadata, and conditional `GET` requests are
+/// answered with `304 Not Modified`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::NamedCborFile;
+///
+/// async fn export() -> std::io::Result<NamedCborFile> {
+///     NamedCborFile::open("exports/catalog.cbor").await
+/// }
+/// ```
+pub struct NamedCborFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl NamedCborFile {
+    /// Open the file at `path`, off the async runtime.
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        blocking(move || Self::open_blocking(path)).await
+    }
+
+    /// Open the file at `path`, checking that it holds a single well-formed CBOR item.
+    ///
+    /// The file is scanned chunk by chunk, off the async runtime, without being held in memory,
+    /// and then streamed from disk like with [`open`](#method.open).
+    pub async fn open_validated<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        blocking(move || Self::open_validated_blocking(path)).await
+    }
+
+    fn open_blocking(path: PathBuf) -> io::Result<Self> {
+        let file = File::open(&path)?;
+        let metadata = file.metadata()?;
+
+        Ok(NamedCborFile {
+            path,
+            file,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open_validated_blocking(path: PathBuf) -> io::Result<Self> {
+        let mut named = Self::open_blocking(path)?;
+        let mut sniffer = Sniffer::new(usize::MAX, DEFAULT_MAX_DEPTH);
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut read = 0;
+        let scanned = loop {
+            let len = match named.file.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            read += len;
+            if let Err(e) = sniffer.feed(&buf[..len]) {
+                break Err(e);
+            }
+        };
+
+        let offset = match scanned {
+            Ok(()) if sniffer.is_complete() => None,
+            Err(CborPayloadError::Malformed(offset)) => Some(offset),
+            _ => Some(read),
+        };
+        if let Some(offset) = offset {
+            let msg = format!(
+                "{} is not well-formed CBOR at byte {}",
+                named.path.display(),
+                offset
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+
+        named.file.seek(SeekFrom::Start(0))?;
+        named.len = read as u64;
+        Ok(named)
+    }
+
+    /// Path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Date the file was last modified, if the platform reports it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Tag of the file, derived from its size and modification date.
+    pub fn etag(&self) -> EntityTag {
+        let modified = self
+            .modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        EntityTag::new_strong(format!(
+            "{:x}-{:x}.{:x}",
+            self.len,
+            modified.as_secs(),
+            modified.subsec_nanos()
+        ))
+    }
+}
+
+/// Run a file operation on the blocking thread pool.
+async fn blocking<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+/// Read the next chunk of `file`, off the async runtime, `None` at the end of the file.
+async fn read_chunk(mut file: File) -> io::Result<Option<(Bytes, File)>> {
+    let chunk = blocking(move || {
+        let mut buf = BytesMut::new();
+        buf.resize(CHUNK_SIZE, 0);
+        let len = file.read(&mut buf)?;
+        buf.truncate(len);
+        Ok((buf.freeze(), file))
+    })
+    .await?;
+
+    Ok(Some(chunk).filter(|(chunk, _)| !chunk.is_empty()))
+}
+
+impl Responder for NamedCborFile {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let etag = self.etag();
+        let not_modified = is_not_modified(req, &etag)
+            || self
+                .modified
+                .is_some_and(|modified| is_not_modified_since(req, modified));
+
+        let mut builder = if not_modified {
+            HttpResponse::NotModified()
+        } else {
+            HttpResponse::Ok()
+        };
+        builder.insert_header(ETag(etag));
+        if let Some(modified) = self.modified {
+            builder.insert_header(LastModified(HttpDate::from(modified)));
+        }
+        if not_modified {
+            return builder.finish();
+        }
+
+        builder.content_type(CBOR_CONTENT_TYPE_VALUE.clone());
+        let chunks = stream::unfold(Some(self.file), |file| async move {
+            // stop after the first error
+            match read_chunk(file?).await {
+                Ok(Some((chunk, file))) => Some((Ok(chunk), Some(file))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        builder
+            .no_chunking(self.len)
+            .streaming(chunks.boxed_local())
+    }
+}
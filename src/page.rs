@@ -0,0 +1,130 @@
+use actix_web::{
+    body::BoxBody,
+    http::header::{HeaderName, HeaderValue, LINK},
+    HttpRequest, HttpResponse, Responder,
+};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::Cbor;
+
+/// Name of the header carrying the total number of items across all pages.
+pub const X_TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Responder for one page of a list.
+///
+/// The body is a CBOR map holding the `items` along with the `total`, `next` and `prev`
+/// metadata that is set. Links to the neighbouring pages are also sent as
+/// [RFC 8288](https://tools.ietf.org/html/rfc8288) `Link` headers, and the total as
+/// `X-Total-Count`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborPage;
+///
+/// async fn list() -> CborPage<u32> {
+///     CborPage::new(vec![11, 12, 13])
+///         .total(42)
+///         .prev("/items?page=3")
+///         .next("/items?page=5")
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CborPage<T> {
+    items: Vec<T>,
+    total: Option<u64>,
+    next: Option<String>,
+    prev: Option<String>,
+}
+
+impl<T> CborPage<T> {
+    /// Create a page of `items`, without metadata.
+    pub fn new(items: Vec<T>) -> Self {
+        CborPage {
+            items,
+            total: None,
+            next: None,
+            prev: None,
+        }
+    }
+
+    /// Set the total number of items across all pages.
+    pub fn total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// Set the URL of the next page.
+    pub fn next(mut self, url: impl Into<String>) -> Self {
+        self.next = Some(url.into());
+        self
+    }
+
+    /// Set the URL of the previous page.
+    pub fn prev(mut self, url: impl Into<String>) -> Self {
+        self.prev = Some(url.into());
+        self
+    }
+
+    /// Items of the page.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Deconstruct to the items of the page
+    pub fn into_inner(self) -> Vec<T> {
+        self.items
+    }
+
+    /// Value of the `Link` header, if the page has neighbours.
+    fn link(&self) -> Option<String> {
+        let links = [("next", &self.next), ("prev", &self.prev)]
+            .iter()
+            .filter_map(|(rel, url)| {
+                url.as_ref()
+                    .map(|url| format!("<{}>; rel=\"{}\"", url, rel))
+            })
+            .collect::<Vec<_>>();
+
+        Some(links.join(", ")).filter(|link| !link.is_empty())
+    }
+}
+
+impl<T: Serialize> Serialize for CborPage<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("items", &self.items)?;
+        if let Some(total) = self.total {
+            map.serialize_entry("total", &total)?;
+        }
+        if let Some(next) = &self.next {
+            map.serialize_entry("next", next)?;
+        }
+        if let Some(prev) = &self.prev {
+            map.serialize_entry("prev", prev)?;
+        }
+        map.end()
+    }
+}
+
+impl<T: Serialize + 'static> Responder for CborPage<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let link = self.link();
+        let total = self.total;
+
+        let mut res = Cbor(self).respond_to(req);
+        if !res.status().is_success() {
+            return res;
+        }
+
+        if let Some(value) = link.and_then(|link| HeaderValue::from_str(&link).ok()) {
+            res.headers_mut().insert(LINK, value);
+        }
+        if let Some(total) = total {
+            res.headers_mut().insert(X_TOTAL_COUNT, total.into());
+        }
+        res
+    }
+}
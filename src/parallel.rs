@@ -0,0 +1,113 @@
+//! Decoding of large arrays and sequences off the async runtime, on the rayon thread pool.
+
+use std::io;
+
+use actix_web::{error::PayloadError, rt::task::spawn_blocking, web::Bytes};
+use futures_util::future::{ready, LocalBoxFuture};
+use futures_util::FutureExt;
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::batch::{decode_batch, BatchResults};
+use crate::body::Decoder;
+use crate::scan::{array_items, split_items};
+use crate::seq::decode_seq;
+use crate::untagged;
+use crate::{CborBody, CborError, CborPayloadError};
+
+/// Number of items below which bodies are decoded on the current thread, where the overhead of
+/// the thread pool outweighs its gains.
+pub(crate) const PARALLEL_MIN_ITEMS: usize = 1024;
+
+/// Decode the elements of a top-level array, in parallel when there are enough of them, keeping
+/// the outcome of every element.
+pub(crate) fn decode_batch_par<T>(body: &[u8]) -> BatchResults<T>
+where
+    T: DeserializeOwned + Send,
+{
+    match array_items(body).filter(|items| items.len() >= PARALLEL_MIN_ITEMS) {
+        Some(items) => Ok(items
+            .par_iter()
+            .map(|item| untagged::from_slice(item).map_err(CborError::from))
+            .collect()),
+        None => decode_batch(body),
+    }
+}
+
+/// Decode the items of a CBOR sequence, in parallel when there are enough of them.
+pub(crate) fn decode_seq_par<T>(body: &[u8]) -> Result<Vec<T>, CborPayloadError>
+where
+    T: DeserializeOwned + Send,
+{
+    let items = split_items(body, None)
+        .filter(|items| items.iter().map(|item| item.len()).sum::<usize>() == body.len())
+        .filter(|items| items.len() >= PARALLEL_MIN_ITEMS);
+    match items {
+        Some(items) => {
+            let items: Result<Vec<T>, CborError> = items
+                .par_iter()
+                .map(|item| untagged::from_slice(item).map_err(CborError::from))
+                .collect();
+            Ok(items?)
+        }
+        None => decode_seq(body),
+    }
+}
+
+impl<U: Send + 'static> CborBody<U> {
+    /// Decode the buffered body on the blocking thread pool rather than in the extractor future,
+    /// once it may hold enough items for the rayon thread pool to be used.
+    pub(crate) fn off_thread(mut self) -> Self {
+        self.offload = Some(decode_off_thread::<U>);
+        self
+    }
+}
+
+fn decode_off_thread<U: Send + 'static>(
+    body: Bytes,
+    decode: Decoder<U>,
+) -> LocalBoxFuture<'static, Result<U, CborPayloadError>> {
+    // every item takes a byte at least
+    if body.len() < PARALLEL_MIN_ITEMS {
+        return ready(decode(&body)).boxed_local();
+    }
+
+    spawn_blocking(move || decode(&body).map_err(OffThreadError::from))
+        .map(|res| match res {
+            Ok(res) => res.map_err(Into::into),
+            Err(_) => Err(CborPayloadError::Payload(PayloadError::Io(
+                io::Error::other("decode task failed"),
+            ))),
+        })
+        .boxed_local()
+}
+
+/// Failure to decode a body, sent back from the blocking thread pool, where not every
+/// `CborPayloadError` can be sent from.
+enum OffThreadError {
+    Decode(CborError),
+    Malformed(usize),
+    Other(String),
+}
+
+impl From<CborPayloadError> for OffThreadError {
+    fn from(e: CborPayloadError) -> Self {
+        match e {
+            CborPayloadError::Deserialize(e) => OffThreadError::Decode(e),
+            CborPayloadError::Malformed(offset) => OffThreadError::Malformed(offset),
+            e => OffThreadError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<OffThreadError> for CborPayloadError {
+    fn from(e: OffThreadError) -> Self {
+        match e {
+            OffThreadError::Decode(e) => CborPayloadError::Deserialize(e),
+            OffThreadError::Malformed(offset) => CborPayloadError::Malformed(offset),
+            OffThreadError::Other(e) => {
+                CborPayloadError::Payload(PayloadError::Io(io::Error::other(e)))
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_cbor::Value;
+
+use crate::accept_patch::with_accept_patch;
+use crate::untagged;
+use crate::{extraction_error, CborBody, CborConfig, CborError, CborPayloadError};
+
+/// Content type of a CBOR merge patch.
+pub const MERGE_PATCH_CBOR_CONTENT_TYPE: &str = "application/merge-patch+cbor";
+
+/// Merge `patch` into `target`, following [RFC 7396](https://tools.ietf.org/html/rfc7396).
+///
+/// Map entries are merged recursively and removed by a `null` value; any other value replaces
+/// the target.
+pub fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let entries = match patch {
+        Value::Map(entries) => entries,
+        _ => {
+            *target = patch.clone();
+            return;
+        }
+    };
+
+    if !matches!(target, Value::Map(_)) {
+        *target = Value::Map(Default::default());
+    }
+    if let Value::Map(target) = target {
+        for (key, value) in entries {
+            match value {
+                Value::Null => {
+                    target.remove(key);
+                }
+                _ => apply_merge_patch(target.entry(key.clone()).or_insert(Value::Null), value),
+            }
+        }
+    }
+}
+
+/// Compute the smallest merge patch turning `original` into `updated`.
+///
+/// Merge patches cannot set a map entry to `null`: such entries of `updated` are removed
+/// instead when the patch is applied.
+pub fn diff_merge_patch(original: &Value, updated: &Value) -> Value {
+    let (original, updated) = match (original, updated) {
+        (Value::Map(original), Value::Map(updated)) => (original, updated),
+        _ => return updated.clone(),
+    };
+
+    let mut patch = BTreeMap::new();
+    for key in original.keys() {
+        if !updated.contains_key(key) {
+            patch.insert(key.clone(), Value::Null);
+        }
+    }
+    for (key, value) in updated {
+        match original.get(key) {
+            Some(previous) if previous == value => {}
+            Some(previous) => {
+                patch.insert(key.clone(), diff_merge_patch(previous, value));
+            }
+            None => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Map(patch)
+}
+
+/// Decode a merge patch spanning the whole body.
+fn decode_patch(body: &[u8]) -> Result<Value, CborPayloadError> {
+    Ok(untagged::from_slice(body)?)
+}
+
+/// Extractor for `application/merge-patch+cbor` bodies, applicable onto a `T`.
+///
+/// Unless an error handler is configured, other content types are rejected with a
+/// `415 Unsupported Media Type` response carrying an `Accept-Patch` header.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborPatch};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User {
+///     name: String,
+///     email: Option<String>,
+/// }
+///
+/// async fn update(patch: CborPatch<User>) -> actix_web::Result<Cbor<User>> {
+///     let user = User {
+///         name: "John".to_owned(),
+///         email: None,
+///     };
+///     Ok(Cbor(patch.apply(&user)?))
+/// }
+/// ```
+pub struct CborPatch<T> {
+    patch: Value,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T> CborPatch<T> {
+    /// Create a patch from a decoded value.
+    pub fn new(patch: Value) -> Self {
+        CborPatch {
+            patch,
+            _target: PhantomData,
+        }
+    }
+
+    /// The decoded patch document.
+    pub fn patch(&self) -> &Value {
+        &self.patch
+    }
+
+    /// Deconstruct to the decoded patch document
+    pub fn into_inner(self) -> Value {
+        self.patch
+    }
+}
+
+impl<T> CborPatch<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Return a copy of `target` with the patch applied.
+    pub fn apply(&self, target: &T) -> Result<T, CborError> {
+        let mut value = serde_cbor::value::to_value(target)?;
+        apply_merge_patch(&mut value, &self.patch);
+        Ok(serde_cbor::value::from_value(value)?)
+    }
+
+    /// Apply the patch onto `target` in place.
+    ///
+    /// `target` is left untouched if the patched value does not deserialize into a `T`.
+    pub fn apply_to(&self, target: &mut T) -> Result<(), CborError> {
+        *target = self.apply(target)?;
+        Ok(())
+    }
+}
+
+impl<T> Clone for CborPatch<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.patch.clone())
+    }
+}
+
+impl<T> fmt::Debug for CborPatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborPatch: {:?}", self.patch)
+    }
+}
+
+impl<T> FromRequest for CborPatch<T>
+where
+    T: 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+
+        CborBody::with_decoder(
+            req,
+            payload,
+            &config,
+            |mime| {
+                mime == MERGE_PATCH_CBOR_CONTENT_TYPE
+                    || config
+                        .content_type
+                        .as_ref()
+                        .is_some_and(|predicate| predicate(mime))
+            },
+            decode_patch,
+        )
+        .limit(config.limit_for(req))
+        .map(move |res| match res {
+            Err(e @ CborPayloadError::ContentType) => {
+                Err(with_accept_patch(extraction_error(&config, e, &req2)))
+            }
+            Err(e) => Err(extraction_error(&config, e, &req2)),
+            Ok(patch) => Ok(CborPatch::new(patch)),
+        })
+        .boxed_local()
+    }
+}
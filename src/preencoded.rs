@@ -0,0 +1,43 @@
+use actix_web::{body::BoxBody, web::Bytes, HttpRequest, HttpResponse, Responder};
+
+use crate::{tagged_response, CborConfig, CBOR_CONTENT_TYPE_VALUE};
+
+/// Responder for bytes already encoded as CBOR, sent as is with the `application/cbor` content
+/// type.
+///
+/// The payload is trusted to be valid CBOR and is not checked.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborPreencoded;
+/// use actix_web::web::Bytes;
+///
+/// async fn proxy() -> CborPreencoded {
+///     // e.g. the body of an upstream CBOR response
+///     let upstream = Bytes::from_static(&[0x83, 0x01, 0x02, 0x03]);
+///     CborPreencoded(upstream)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborPreencoded(pub Bytes);
+
+impl CborPreencoded {
+    /// Deconstruct to the encoded payload
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Responder for CborPreencoded {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        if CborConfig::from_req(req).etag == Some(true) {
+            return tagged_response(req, self.0);
+        }
+
+        HttpResponse::Ok()
+            .content_type(CBOR_CONTENT_TYPE_VALUE.clone())
+            .body(self.0)
+    }
+}
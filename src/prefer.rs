@@ -0,0 +1,42 @@
+use actix_web::{
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method,
+    },
+    HttpRequest, HttpResponse,
+};
+
+/// Name of the header carrying the preferences of a request.
+pub const PREFER: HeaderName = HeaderName::from_static("prefer");
+
+/// Name of the header listing the preferences honored by a response.
+pub const PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+/// Whether a request modifying a resource asks for `Prefer: return=minimal`.
+pub(crate) fn prefers_minimal(req: &HttpRequest) -> bool {
+    if req.method() == Method::GET || req.method() == Method::HEAD {
+        return false;
+    }
+
+    req.headers()
+        .get_all(PREFER)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|preference| preference.split(';').next())
+        .any(|preference| {
+            let mut parts = preference.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let value = parts.next().unwrap_or_default().trim().trim_matches('"');
+            name.eq_ignore_ascii_case("return") && value.eq_ignore_ascii_case("minimal")
+        })
+}
+
+/// `204 No Content` response applying `return=minimal`.
+pub(crate) fn minimal_response() -> HttpResponse {
+    HttpResponse::NoContent()
+        .insert_header((
+            PREFERENCE_APPLIED,
+            HeaderValue::from_static("return=minimal"),
+        ))
+        .finish()
+}
@@ -0,0 +1,111 @@
+use std::io;
+
+use actix_web::{
+    http::header::{HeaderValue, Quality, CONTENT_TYPE},
+    HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+
+use crate::http_request_ext::{add_vary, parse_accept};
+
+/// Encoding profile of a CBOR body, negotiated with the `profile` parameter of
+/// `application/cbor`.
+///
+/// `Cbor` responses use the profile ranked highest by the `Accept` header of the request, such as
+/// `Accept: application/cbor; profile=packed`, and echo it in their `Content-Type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CborProfile {
+    /// Struct fields keyed by index and enum variants by number, instead of by name
+    Packed,
+    /// Map keys sorted in length-first order, so equal values have equal encodings
+    Canonical,
+}
+
+impl CborProfile {
+    /// Value of the `profile` parameter naming this profile.
+    pub fn name(self) -> &'static str {
+        match self {
+            CborProfile::Packed => "packed",
+            CborProfile::Canonical => "canonical",
+        }
+    }
+
+    /// Profile named `name`, if known.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "packed" => Some(CborProfile::Packed),
+            "canonical" => Some(CborProfile::Canonical),
+            _ => None,
+        }
+    }
+
+    /// `Content-Type` of a body encoded with this profile.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            CborProfile::Packed => "application/cbor; profile=packed",
+            CborProfile::Canonical => "application/cbor; profile=canonical",
+        }
+    }
+}
+
+/// Profile of the `application/cbor` range ranked highest by the `Accept` header of `req`.
+///
+/// Ranges with an unknown profile, or refused with `q=0`, are ignored. Ties go to the range
+/// listed first.
+pub(crate) fn negotiate_profile(req: &HttpRequest) -> Option<CborProfile> {
+    let accept = parse_accept(req)?;
+    let mut best = None;
+
+    for range in &accept.0 {
+        if range.item.essence_str() != "application/cbor" || range.quality == Quality::ZERO {
+            continue;
+        }
+        let profile = match range.item.get_param("profile") {
+            Some(name) => match CborProfile::from_name(name.as_str()) {
+                Some(profile) => Some(profile),
+                None => continue,
+            },
+            None => None,
+        };
+        if best.is_none_or(|(quality, _)| range.quality > quality) {
+            best = Some((range.quality, profile));
+        }
+    }
+
+    best.and_then(|(_, profile)| profile)
+}
+
+/// Encode `value` into `writer` with `profile`, or the default encoding.
+pub(crate) fn write_profile<W, T>(
+    writer: W,
+    value: &T,
+    profile: Option<CborProfile>,
+) -> Result<(), serde_cbor::Error>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    match profile {
+        None => serde_cbor::to_writer(writer, value),
+        Some(CborProfile::Packed) => {
+            let mut serializer =
+                serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(writer)).packed_format();
+            value.serialize(&mut serializer)
+        }
+        Some(CborProfile::Canonical) => {
+            serde_cbor::to_writer(writer, &serde_cbor::value::to_value(value)?)
+        }
+    }
+}
+
+/// Announce `profile` in the `Content-Type` of `res`, if it has a body, and that the encoding
+/// depends on the `Accept` header.
+pub(crate) fn set_profile(res: &mut HttpResponse, profile: Option<CborProfile>) {
+    if let Some(profile) = profile {
+        if res.headers().contains_key(CONTENT_TYPE) {
+            let value = HeaderValue::from_static(profile.content_type());
+            res.headers_mut().insert(CONTENT_TYPE, value);
+        }
+        add_vary(res, "accept");
+    }
+}
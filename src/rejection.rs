@@ -0,0 +1,179 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+use actix_web::{
+    http::{
+        header::{AcceptLanguage, Header, Quality},
+        StatusCode,
+    },
+    HttpRequest, HttpResponse, ResponseError,
+};
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+
+use crate::config::MessageCatalog;
+use crate::error_handlers::error_body_response;
+use crate::{CborErrorBody, CborPayloadError};
+
+/// Stable, machine-readable code of a rejected CBOR payload, serialized in snake case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CborErrorCode {
+    /// Payload is bigger than allowed
+    Overflow,
+    /// Payload is not CBOR
+    UnsupportedMediaType,
+    /// Payload could not be decoded to the target type
+    DecodeError,
+    /// Payload holds a field the target type does not consume
+    UnknownField,
+    /// Payload version is missing, or not supported
+    UnsupportedVersion,
+    /// Request has no body
+    NoBody,
+    /// Payload does not match the schema declared by the request
+    SchemaMismatch,
+    /// Payload could not be read
+    PayloadError,
+    /// Response could not be serialized
+    SerializeError,
+    /// Response is bigger than allowed
+    ResponseOverflow,
+}
+
+impl CborErrorCode {
+    /// Status of responses rejecting a payload with this code.
+    pub fn status(self) -> StatusCode {
+        match self {
+            CborErrorCode::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            CborErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            CborErrorCode::SchemaMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            CborErrorCode::SerializeError | CborErrorCode::ResponseOverflow => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl CborPayloadError {
+    /// Machine-readable code of this error.
+    pub fn code(&self) -> CborErrorCode {
+        match self {
+            CborPayloadError::Overflow => CborErrorCode::Overflow,
+            CborPayloadError::ContentType => CborErrorCode::UnsupportedMediaType,
+            CborPayloadError::Serialize(_) => CborErrorCode::SerializeError,
+            CborPayloadError::ResponseOverflow => CborErrorCode::ResponseOverflow,
+            CborPayloadError::Payload(_) => CborErrorCode::PayloadError,
+            CborPayloadError::Schema(_) => CborErrorCode::SchemaMismatch,
+            CborPayloadError::UnknownField(_) => CborErrorCode::UnknownField,
+            CborPayloadError::UnsupportedVersion(_) => CborErrorCode::UnsupportedVersion,
+            CborPayloadError::NoBody => CborErrorCode::NoBody,
+            #[cfg(feature = "multipart")]
+            CborPayloadError::Multipart(_) => CborErrorCode::PayloadError,
+            _ => CborErrorCode::DecodeError,
+        }
+    }
+}
+
+/// Error rejecting a CBOR payload, answered with a [`CborErrorBody`](struct.CborErrorBody.html)
+/// carrying its [`CborErrorCode`](enum.CborErrorCode.html), so clients can branch on the code
+/// instead of the message.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborConfig, CborRejection};
+///
+/// let config = CborConfig::default().error_handler(|err, _req| CborRejection::from(err).into());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborRejection {
+    code: CborErrorCode,
+    message: String,
+    content_type: Option<Mime>,
+}
+
+impl CborRejection {
+    /// Rejection with `code`, described by `message`.
+    pub fn new(code: CborErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            content_type: None,
+        }
+    }
+
+    /// Rejection of `err`, with the message `catalog` gives for the `Accept-Language` header of
+    /// `req` and answered as `content_type`, see
+    /// [`CborConfig::messages`](struct.CborConfig.html#method.messages).
+    pub(crate) fn localized(
+        err: CborPayloadError,
+        catalog: &MessageCatalog,
+        content_type: Option<Mime>,
+        req: &HttpRequest,
+    ) -> Self {
+        let code = err.code();
+        let mut languages = AcceptLanguage::parse(req)
+            .map(|al| al.0)
+            .unwrap_or_default();
+        languages.sort_by_key(|lang| Reverse(lang.quality));
+
+        let message = languages
+            .iter()
+            .filter(|lang| lang.quality > Quality::ZERO)
+            .map(|lang| lang.item.to_string())
+            .filter(|tag| tag != "*")
+            .find_map(|tag| {
+                let primary = tag.split('-').next().unwrap_or_default();
+                catalog(code, &tag).or_else(|| match primary != tag {
+                    true => catalog(code, primary),
+                    false => None,
+                })
+            });
+
+        let rejection = match message {
+            Some(message) => Self::new(code, message),
+            None => Self::from(err),
+        };
+        Self {
+            content_type,
+            ..rejection
+        }
+    }
+
+    /// Machine-readable code of the rejection.
+    pub fn code(&self) -> CborErrorCode {
+        self.code
+    }
+
+    /// Human-readable description of the rejection.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<CborPayloadError> for CborRejection {
+    fn from(err: CborPayloadError) -> Self {
+        Self::new(err.code(), err.to_string().trim_end())
+    }
+}
+
+impl fmt::Display for CborRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CborRejection {}
+
+impl ResponseError for CborRejection {
+    fn status_code(&self) -> StatusCode {
+        self.code.status()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let body = CborErrorBody::new(self.status_code(), self.message.clone()).code(self.code);
+        error_body_response(self.status_code(), &body, self.content_type.clone())
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::Cbor;
+
+/// Marker of the types which handlers return directly, responding as [`Cbor`](struct.Cbor.html)
+/// would.
+///
+/// `Responder` belongs to actix-web, so it cannot be implemented for every `CborResponder`.
+/// Types opt in with the [`cbor_responder!`](macro.cbor_responder.html) macro instead, which
+/// implements both traits in one line, or with `#[derive(CborResponder)]` and the `macros`
+/// feature.
+///
+/// # Example
+/// ```
+/// use actix_cbor::cbor_responder;
+///
+/// #[derive(serde::Serialize)]
+/// struct Order {
+///     id: u64,
+/// }
+///
+/// cbor_responder!(Order);
+///
+/// async fn order() -> Order {
+///     Order { id: 7 }
+/// }
+/// ```
+pub trait CborResponder: Serialize {
+    /// Respond with `self` as `Cbor(self)` would.
+    fn respond_cbor(self, req: &HttpRequest) -> HttpResponse
+    where
+        Self: Sized + 'static,
+    {
+        Cbor(self).respond_to(req)
+    }
+}
+
+/// Implement [`CborResponder`](trait.CborResponder.html) and `Responder` for each of the given
+/// `Serialize` types.
+#[macro_export]
+macro_rules! cbor_responder {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::CborResponder for $ty {}
+
+            impl $crate::__private::Responder for $ty {
+                type Body = $crate::__private::BoxBody;
+
+                fn respond_to(
+                    self,
+                    req: &$crate::__private::HttpRequest,
+                ) -> $crate::__private::HttpResponse {
+                    $crate::CborResponder::respond_cbor(self, req)
+                }
+            }
+        )+
+    };
+}
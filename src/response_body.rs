@@ -0,0 +1,114 @@
+use std::io;
+
+use actix_web::{
+    body::{to_bytes_limited, BoxBody, MessageBody},
+    dev::ServiceResponse,
+    error::PayloadError,
+    http::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, ETAG},
+    web::Bytes,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::body::is_cbor_content_type;
+use crate::limit::serialize_error;
+use crate::untagged;
+use crate::{CborPayloadError, CBOR_CONTENT_TYPE_VALUE};
+
+/// Whether the `Content-Type` of a response is `application/cbor`, with or without parameters.
+pub fn is_cbor_response<B>(res: &ServiceResponse<B>) -> bool {
+    is_cbor_content_type_header(res.headers())
+}
+
+/// Whether a `Content-Type` header is `application/cbor`, with or without parameters.
+pub(crate) fn is_cbor_content_type_header(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| is_cbor_content_type(mime.essence_str(), None))
+}
+
+/// Buffer the body of a response, failing with `ResponseOverflow` past `limit` bytes.
+///
+/// The response is handed back without its body, ready for
+/// [`with_cbor_body`](fn.with_cbor_body.html).
+pub async fn response_bytes<B: MessageBody>(
+    res: ServiceResponse<B>,
+    limit: usize,
+) -> Result<(ServiceResponse<()>, Bytes), CborPayloadError> {
+    let (req, res) = res.into_parts();
+    let (head, body) = res.into_parts();
+
+    match to_bytes_limited(body, limit).await {
+        Ok(Ok(body)) => Ok((ServiceResponse::new(req, head), body)),
+        Ok(Err(e)) => {
+            let e = io::Error::other(e.into().to_string());
+            Err(PayloadError::Io(e).into())
+        }
+        Err(_) => Err(CborPayloadError::ResponseOverflow),
+    }
+}
+
+/// Buffer the CBOR body of a response, up to `limit` bytes, and decode it to `T`.
+///
+/// Decode to `serde_cbor::Value` to rewrite bodies of any shape. Responses which are not CBOR
+/// fail with `ContentType`, before their body is read.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{is_cbor_response, response_cbor, with_cbor_body};
+/// use actix_web::{dev::Service, web, App};
+///
+/// // Redacts the `secret` field of every CBOR response.
+/// let app = App::new()
+///     .wrap_fn(|req, srv| {
+///         let fut = srv.call(req);
+///         async move {
+///             let res = fut.await?;
+///             if !is_cbor_response(&res) {
+///                 return Ok(res);
+///             }
+///             let (res, mut value) =
+///                 response_cbor::<serde_cbor::Value, _>(res, 65_536).await?;
+///             if let serde_cbor::Value::Map(map) = &mut value {
+///                 map.remove(&serde_cbor::Value::Text("secret".into()));
+///             }
+///             Ok(with_cbor_body(res, &value)?)
+///         }
+///     })
+///     .route("/", web::get().to(|| async { "hello" }));
+/// ```
+pub async fn response_cbor<T, B>(
+    res: ServiceResponse<B>,
+    limit: usize,
+) -> Result<(ServiceResponse<()>, T), CborPayloadError>
+where
+    T: DeserializeOwned,
+    B: MessageBody,
+{
+    if !is_cbor_response(&res) {
+        return Err(CborPayloadError::ContentType);
+    }
+
+    let (res, body) = response_bytes(res, limit).await?;
+    Ok((res, untagged::from_slice(&body)?))
+}
+
+/// Give a response `value` as its CBOR body.
+///
+/// The `Content-Length` and `ETag` headers of the previous body are removed, and the
+/// `Content-Type` is set to `application/cbor`.
+pub fn with_cbor_body<T: Serialize>(
+    res: ServiceResponse<()>,
+    value: &T,
+) -> Result<ServiceResponse<BoxBody>, CborPayloadError> {
+    let body = serde_cbor::to_vec(value).map_err(serialize_error)?;
+    Ok(res.map_body(|head, ()| {
+        head.headers.remove(CONTENT_LENGTH);
+        head.headers.remove(ETAG);
+        head.headers
+            .insert(CONTENT_TYPE, CBOR_CONTENT_TYPE_VALUE.clone());
+        BoxBody::new(body)
+    }))
+}
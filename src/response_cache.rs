@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, COOKIE, SET_COOKIE},
+        Method,
+    },
+    HttpRequest,
+};
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+
+use crate::etag::BodyHasher;
+use crate::idempotency::{canonical_body, read_body, with_body};
+use crate::{response_bytes, CborConfig, StoredResponse};
+
+/// Name of the header telling whether a response was served from a
+/// [`CborResponseCache`](struct.CborResponseCache.html), `HIT`, or by the handler, `MISS`.
+pub const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+/// Parts of a request a cached response answers.
+#[derive(PartialEq, Eq)]
+struct RequestKey {
+    method: Method,
+    uri: String,
+    accept: Option<HeaderValue>,
+    body: Vec<u8>,
+}
+
+impl RequestKey {
+    fn new(req: &HttpRequest, body: &[u8]) -> Self {
+        RequestKey {
+            method: req.method().clone(),
+            uri: req.uri().to_string(),
+            accept: req.headers().get(ACCEPT).cloned(),
+            body: canonical_body(body).into_owned(),
+        }
+    }
+
+    /// Index of the entry in the cache. Requests sharing it are told apart by comparing the
+    /// whole key, so a colliding request cannot be served another one's response.
+    fn hash(&self) -> u64 {
+        let mut hasher = BodyHasher::new();
+        for part in [self.method.as_str().as_bytes(), self.uri.as_bytes()] {
+            hasher.update(part);
+            hasher.update(&[0]);
+        }
+        if let Some(accept) = &self.accept {
+            hasher.update(accept.as_bytes());
+        }
+        hasher.update(&[0]);
+        hasher.update(&self.body);
+        hasher.finish()
+    }
+}
+
+struct Entry {
+    expires: Instant,
+    request: RequestKey,
+    response: StoredResponse,
+}
+
+/// Middleware caching the responses of idempotent endpoints, keyed by the method, URI, `Accept`
+/// header and canonical CBOR body of the request.
+///
+/// Cached responses are served without invoking the handler until their time to live elapses.
+/// Only successful responses whose body is known to fit in the size limit are cached. When the
+/// cache is full, expired entries are dropped first, then the entry closest to expiring.
+///
+/// Responses may depend on who asks, so requests carrying an `Authorization` or `Cookie` header
+/// are passed to the handler without being looked up or cached, and responses setting a cookie
+/// are never cached.
+///
+/// Request bodies are buffered, up to the limit of the request
+/// [`CborConfig`](struct.CborConfig.html), so that equivalent encodings of the same value share
+/// an entry. Entries keep the canonical body of their request, which is compared along with the
+/// method, URI and `Accept` header before serving them. The cache is cheap to clone, and clones
+/// share their entries.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborResponseCache};
+/// use actix_web::{web, App};
+/// use std::time::Duration;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Query {
+///     term: String,
+/// }
+///
+/// async fn search(query: Cbor<Query>) -> Cbor<Vec<String>> {
+///     Cbor(vec![query.into_inner().term])
+/// }
+///
+/// let cache = CborResponseCache::new(Duration::from_secs(60))
+///     .max_entries(1_000)
+///     .max_body_size(64 * 1024);
+/// let app = App::new().service(
+///     web::resource("/search")
+///         .wrap(cache)
+///         .route(web::post().to(search)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct CborResponseCache {
+    ttl: Duration,
+    max_entries: usize,
+    max_body_size: usize,
+    entries: Arc<Mutex<HashMap<u64, Entry>>>,
+}
+
+impl CborResponseCache {
+    /// Create an empty cache whose entries expire after `ttl`, holding up to 1024 entries of at
+    /// most 256kB each.
+    pub fn new(ttl: Duration) -> Self {
+        CborResponseCache {
+            ttl,
+            max_entries: 1024,
+            max_body_size: 262_144,
+            entries: Default::default(),
+        }
+    }
+
+    /// Change the max number of cached responses.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Change the max size of a cached response body.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Drop every entry.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Cached response for `request`, if it did not expire.
+    fn get(&self, request: &RequestKey) -> Option<StoredResponse> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&request.hash())
+            .filter(|entry| entry.request == *request && entry.expires > Instant::now())
+            .map(|entry| entry.response.clone())
+    }
+
+    /// Cache `response` to `request`, making room for it if needed.
+    fn insert(&self, request: RequestKey, response: StoredResponse) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let key = request.hash();
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                expires: now + self.ttl,
+                request,
+                response,
+            },
+        );
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CborResponseCache
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = CborResponseCacheMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CborResponseCacheMiddleware {
+            service: Rc::new(service),
+            cache: self.clone(),
+        })
+    }
+}
+
+/// Service created by the [`CborResponseCache`](struct.CborResponseCache.html) middleware.
+pub struct CborResponseCacheMiddleware<S> {
+    service: Rc<S>,
+    cache: CborResponseCache,
+}
+
+impl<S, B> Service<ServiceRequest> for CborResponseCacheMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let cache = self.cache.clone();
+
+        let headers = req.headers();
+        if headers.contains_key(AUTHORIZATION) || headers.contains_key(COOKIE) {
+            let fut = service.call(req);
+            return Box::pin(async move { Ok(fut.await?.map_into_left_body()) });
+        }
+
+        Box::pin(async move {
+            let (req, mut payload) = req.into_parts();
+            let limit = CborConfig::from_req(&req).limit_for(&req);
+            let body = match read_body(&mut payload, limit).await {
+                Ok(body) => body,
+                Err(e) => return Ok(ServiceResponse::from_err(e, req).map_into_right_body()),
+            };
+
+            let request = RequestKey::new(&req, &body);
+            if let Some(cached) = cache.get(&request) {
+                let mut res = cached.to_response();
+                res.headers_mut()
+                    .insert(X_CACHE, HeaderValue::from_static("HIT"));
+                return Ok(ServiceResponse::new(req, res).map_into_right_body());
+            }
+
+            let mut res = service.call(with_body(req, body)).await?;
+            res.headers_mut()
+                .insert(X_CACHE, HeaderValue::from_static("MISS"));
+            let cacheable = match res.response().body().size() {
+                BodySize::Sized(len) => len <= cache.max_body_size as u64,
+                BodySize::None => true,
+                BodySize::Stream => false,
+            };
+            if !res.status().is_success() || !cacheable || res.headers().contains_key(SET_COOKIE) {
+                return Ok(res.map_into_left_body());
+            }
+
+            let status = res.status();
+            let headers = res.headers().clone();
+            let (res, body) = response_bytes(res, cache.max_body_size).await?;
+            cache.insert(
+                request,
+                StoredResponse::new(0, status, headers, body.clone()),
+            );
+            Ok(res
+                .map_body(|_, ()| BoxBody::new(body))
+                .map_into_right_body())
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_web::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    HttpMessage,
+};
+use futures_util::future::{ok, FutureExt, LocalBoxFuture, Ready};
+use log::info;
+use serde_cbor::Value;
+
+use crate::diag::diagnostic;
+use crate::idempotency::{read_body, with_body};
+use crate::{is_cbor_content_type, is_cbor_response, response_bytes, CborConfig};
+
+/// Hook rewriting a decoded body before it is logged.
+type Redaction = Arc<dyn Fn(&mut Value) + Send + Sync>;
+
+/// Middleware logging a sample of the CBOR bodies of successful exchanges in diagnostic
+/// notation, at the `info` level of the `actix_cbor::sample` target.
+///
+/// One request out of [`every`](#method.every) is sampled. The request body is buffered, up to
+/// the limit of the request [`CborConfig`](struct.CborConfig.html), and the response body when
+/// its size is known. Bodies bigger than [`max_body_size`](#method.max_body_size) are logged by
+/// their size only, and the notation is cut after [`max_len`](#method.max_len) bytes. Sensitive
+/// fields are hidden with [`redact_field`](#method.redact_field) or [`redact`](#method.redact).
+///
+/// Failed exchanges are not logged, as extractors and error handlers already report them.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{Cbor, CborSampleLog};
+/// use actix_web::{web, App};
+///
+/// async fn echo(value: Cbor<serde_cbor::Value>) -> Cbor<serde_cbor::Value> {
+///     value
+/// }
+///
+/// let app = App::new().service(
+///     web::resource("/echo")
+///         .wrap(CborSampleLog::every(100).redact_field("password"))
+///         .route(web::post().to(echo)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct CborSampleLog {
+    every: u64,
+    counter: Arc<AtomicU64>,
+    max_body_size: usize,
+    max_len: usize,
+    redactions: Vec<Redaction>,
+}
+
+impl CborSampleLog {
+    /// Log one exchange out of `n`, or every exchange when `n` is 0 or 1.
+    pub fn every(n: u64) -> Self {
+        CborSampleLog {
+            every: n.max(1),
+            counter: Arc::new(AtomicU64::new(0)),
+            max_body_size: 65_536,
+            max_len: 1_024,
+            redactions: Vec::new(),
+        }
+    }
+
+    /// Log the size only of bodies bigger than `size` bytes. Defaults to 64KiB.
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Cut the notation of each body after `len` bytes. Defaults to 1024.
+    pub fn max_len(mut self, len: usize) -> Self {
+        self.max_len = len;
+        self
+    }
+
+    /// Hide the value of the map entries whose key is the text `name`, at any depth.
+    pub fn redact_field(self, name: impl Into<String>) -> Self {
+        let name = Value::Text(name.into());
+        self.redact(move |value| redact_field(value, &name))
+    }
+
+    /// Rewrite decoded bodies with `hook` before logging them.
+    pub fn redact<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut Value) + Send + Sync + 'static,
+    {
+        self.redactions.push(Arc::new(hook));
+        self
+    }
+
+    /// Whether the next exchange is sampled.
+    fn sample(&self) -> bool {
+        self.counter
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.every)
+    }
+
+    /// Redacted diagnostic notation of `body`.
+    pub(crate) fn render(&self, body: &[u8]) -> String {
+        if body.is_empty() {
+            return "<empty>".to_owned();
+        }
+        if body.len() > self.max_body_size {
+            return format!("<{} bytes>", body.len());
+        }
+        match serde_cbor::from_slice::<Value>(body) {
+            Ok(mut value) => {
+                for redaction in &self.redactions {
+                    redaction(&mut value);
+                }
+                diagnostic(&value, self.max_len)
+            }
+            Err(_) => format!("<{} bytes, not well-formed>", body.len()),
+        }
+    }
+}
+
+/// Replace the values keyed by `name` in every map of `value`.
+fn redact_field(value: &mut Value, name: &Value) {
+    match value {
+        Value::Map(entries) => {
+            for (key, value) in entries.iter_mut() {
+                if key == name {
+                    *value = Value::Text("<redacted>".to_owned());
+                } else {
+                    redact_field(value, name);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact_field(item, name)),
+        Value::Tag(_, value) => redact_field(value, name),
+        _ => {}
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CborSampleLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = CborSampleLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CborSampleLogMiddleware {
+            service: Rc::new(service),
+            log: self.clone(),
+        })
+    }
+}
+
+/// Service created by the [`CborSampleLog`](struct.CborSampleLog.html) middleware.
+pub struct CborSampleLogMiddleware<S> {
+    service: Rc<S>,
+    log: CborSampleLog,
+}
+
+impl<S, B> Service<ServiceRequest> for CborSampleLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let config = CborConfig::from_req(req.request());
+        let cbor = is_cbor_content_type(req.content_type(), config.content_type.as_ref());
+        if !self.log.sample() || !cbor {
+            return self
+                .service
+                .call(req)
+                .map(|res| res.map(ServiceResponse::map_into_left_body))
+                .boxed_local();
+        }
+
+        let service = self.service.clone();
+        let log = self.log.clone();
+
+        Box::pin(async move {
+            let (req, mut payload) = req.into_parts();
+            let config = CborConfig::from_req(&req);
+            let (limit, label) = (config.limit_for(&req), config.metric_label.unwrap_or("-"));
+            let body = match read_body(&mut payload, limit).await {
+                Ok(body) => body,
+                Err(e) => return Ok(ServiceResponse::from_err(e, req).map_into_right_body()),
+            };
+            let request = log.render(&body);
+
+            let res = service.call(with_body(req, body)).await?;
+            if !res.status().is_success() {
+                return Ok(res.map_into_left_body());
+            }
+
+            let sized = match res.response().body().size() {
+                BodySize::Sized(len) => len <= log.max_body_size as u64,
+                BodySize::None => true,
+                BodySize::Stream => false,
+            };
+            let (method, path, status) = (
+                res.request().method().clone(),
+                res.request().path().to_owned(),
+                res.status(),
+            );
+            if !sized || !is_cbor_response(&res) {
+                info!(
+                    target: "actix_cbor::sample",
+                    "{} {} {} {}: request {}",
+                    method,
+                    path,
+                    status.as_u16(),
+                    label,
+                    request
+                );
+                return Ok(res.map_into_left_body());
+            }
+
+            let (res, body) = response_bytes(res, log.max_body_size).await?;
+            info!(
+                target: "actix_cbor::sample",
+                "{} {} {} {}: request {}, response {}",
+                method,
+                path,
+                status.as_u16(),
+                label,
+                request,
+                log.render(&body)
+            );
+            Ok(res
+                .map_body(|_, ()| BoxBody::new(body))
+                .map_into_right_body())
+        })
+    }
+}
@@ -0,0 +1,322 @@
+//! Structural scanning of CBOR data items, without decoding them.
+
+use std::convert::TryFrom;
+
+use crate::CborPayloadError;
+
+/// Nesting depth accepted when no other limit is configured, matching serde_cbor's recursion limit.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Result of scanning the start of a buffer for one data item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scan {
+    /// A complete item spanning this many bytes.
+    Complete(usize),
+    /// The buffer ends before the item does.
+    Incomplete,
+}
+
+/// The buffer cannot be the start of a well-formed CBOR item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Malformed {
+    /// Offset of the offending byte.
+    pub(crate) offset: usize,
+}
+
+/// Container still waiting for nested items.
+enum Frame {
+    /// Definite container (or tag) expecting this many more items.
+    Items(u64),
+    /// Indefinite container, closed by a break code. Indefinite strings may only contain
+    /// definite chunks of their own major type.
+    Indefinite { chunk_major: Option<u8> },
+}
+
+/// Header of a data item.
+pub(crate) struct Head {
+    pub(crate) major: u8,
+    /// Argument, or `None` for the indefinite-length marker.
+    pub(crate) arg: Option<u64>,
+    /// Bytes taken by the initial byte and its argument.
+    pub(crate) len: usize,
+}
+
+/// Read the header of the item starting at `buf[pos]`.
+pub(crate) fn read_head(buf: &[u8], pos: usize) -> Result<Option<Head>, Malformed> {
+    let initial = match buf.get(pos) {
+        Some(b) => *b,
+        None => return Ok(None),
+    };
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+
+    let (arg, len) = match info {
+        0..=23 => (Some(u64::from(info)), 1),
+        24..=27 => {
+            let size = 1 << (info - 24);
+            let bytes = match buf.get(pos + 1..pos + 1 + size) {
+                Some(bytes) => bytes,
+                None => return Ok(None),
+            };
+            let arg = bytes.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+            (Some(arg), 1 + size)
+        }
+        31 if major >= 2 => (None, 1),
+        _ => return Err(Malformed { offset: pos }),
+    };
+
+    Ok(Some(Head { major, arg, len }))
+}
+
+/// Effect of an item header on the containers being scanned.
+enum Step {
+    /// The item is complete.
+    Done,
+    /// The item is a container, or a tag, waiting for nested items.
+    Open,
+    /// The item is a definite string, complete after this many bytes.
+    String(u64),
+}
+
+/// Account for the header of the item at `start` in `stack`, nesting at most `max_depth`
+/// containers deep.
+fn enter(
+    stack: &mut Vec<Frame>,
+    head: &Head,
+    start: usize,
+    max_depth: usize,
+) -> Result<Step, Malformed> {
+    if let Some(Frame::Indefinite {
+        chunk_major: Some(major),
+    }) = stack.last()
+    {
+        let is_break = head.major == 7 && head.arg.is_none();
+        if !is_break && (head.major != *major || head.arg.is_none()) {
+            return Err(Malformed { offset: start });
+        }
+    }
+
+    let step = match (head.major, head.arg) {
+        (0, _) | (1, _) => Step::Done,
+        (2, Some(n)) | (3, Some(n)) => Step::String(n),
+        (2, None) | (3, None) => {
+            stack.push(Frame::Indefinite {
+                chunk_major: Some(head.major),
+            });
+            Step::Open
+        }
+        (4, Some(0)) | (5, Some(0)) => Step::Done,
+        (4, Some(n)) => {
+            stack.push(Frame::Items(n));
+            Step::Open
+        }
+        (5, Some(n)) => {
+            let n = n.checked_mul(2).ok_or(Malformed { offset: start })?;
+            stack.push(Frame::Items(n));
+            Step::Open
+        }
+        (4, None) | (5, None) => {
+            stack.push(Frame::Indefinite { chunk_major: None });
+            Step::Open
+        }
+        (6, Some(_)) => {
+            stack.push(Frame::Items(1));
+            Step::Open
+        }
+        (7, Some(_)) => Step::Done,
+        (7, None) => match stack.pop() {
+            Some(Frame::Indefinite { .. }) => Step::Done,
+            _ => return Err(Malformed { offset: start }),
+        },
+        _ => return Err(Malformed { offset: start }),
+    };
+
+    if stack.len() > max_depth {
+        return Err(Malformed { offset: start });
+    }
+    Ok(step)
+}
+
+/// Account for a completed item in `stack`, returning whether the outermost item is complete.
+fn close(stack: &mut Vec<Frame>) -> bool {
+    loop {
+        match stack.last_mut() {
+            None => return true,
+            Some(Frame::Items(remaining)) => {
+                *remaining -= 1;
+                if *remaining > 0 {
+                    return false;
+                }
+                stack.pop();
+            }
+            Some(Frame::Indefinite { .. }) => return false,
+        }
+    }
+}
+
+/// Find the extent of the first data item in `buf`, nesting at most `max_depth` containers deep.
+pub(crate) fn scan_item(buf: &[u8], max_depth: usize) -> Result<Scan, Malformed> {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let head = match read_head(buf, pos)? {
+            Some(head) => head,
+            None => return Ok(Scan::Incomplete),
+        };
+
+        let start = pos;
+        pos += head.len;
+
+        let done = match enter(&mut stack, &head, start, max_depth)? {
+            Step::Done => true,
+            Step::Open => false,
+            Step::String(n) => {
+                let end = usize::try_from(n)
+                    .ok()
+                    .and_then(|n| pos.checked_add(n))
+                    .ok_or(Malformed { offset: start })?;
+                if end > buf.len() {
+                    return Ok(Scan::Incomplete);
+                }
+                pos = end;
+                true
+            }
+        };
+
+        if done && close(&mut stack) {
+            return Ok(Scan::Complete(pos));
+        }
+    }
+}
+
+/// Split a buffer into consecutive data items, or `None` if an item is malformed or truncated.
+pub(crate) fn split_items(buf: &[u8], max_items: Option<u64>) -> Option<Vec<&[u8]>> {
+    let mut items = Vec::new();
+    let mut pos = 0;
+
+    while pos < buf.len() && max_items.is_none_or(|max| (items.len() as u64) < max) {
+        if max_items.is_none() && buf[pos] == 0xff {
+            break;
+        }
+        match scan_item(&buf[pos..], DEFAULT_MAX_DEPTH) {
+            Ok(Scan::Complete(len)) => {
+                items.push(&buf[pos..pos + len]);
+                pos += len;
+            }
+            _ => return None,
+        }
+    }
+
+    Some(items)
+}
+
+/// Items of a top-level CBOR array spanning the whole body, or `None` if the body is not one.
+pub(crate) fn array_items(body: &[u8]) -> Option<Vec<&[u8]>> {
+    let head = read_head(body, 0).ok()??;
+    if head.major != 4 {
+        return None;
+    }
+
+    let rest = &body[head.len..];
+    let items = split_items(rest, head.arg)?;
+    let used = items.iter().map(|item| item.len()).sum::<usize>();
+    let complete = match head.arg {
+        Some(n) => items.len() as u64 == n && used == rest.len(),
+        None => used + 1 == rest.len() && rest[used] == 0xff,
+    };
+    Some(items).filter(|_| complete)
+}
+
+/// Incremental check of a body holding a single data item, fed chunk by chunk as it is
+/// received, so that payloads which cannot be valid CBOR are rejected before being buffered
+/// entirely.
+///
+/// Besides malformed headers and structures, it rejects containers and strings whose declared
+/// length cannot fit in `limit` bytes, and any byte following the item.
+pub(crate) struct Sniffer {
+    stack: Vec<Frame>,
+    /// Bytes of a header split across chunks.
+    head: Vec<u8>,
+    /// Bytes of the current string still to come.
+    skip: u64,
+    /// Bytes fed before the current chunk.
+    offset: usize,
+    complete: bool,
+    max_depth: usize,
+    limit: usize,
+}
+
+impl Sniffer {
+    pub(crate) fn new(limit: usize, max_depth: usize) -> Self {
+        Self {
+            stack: Vec::new(),
+            head: Vec::with_capacity(9),
+            skip: 0,
+            offset: 0,
+            complete: false,
+            max_depth,
+            limit,
+        }
+    }
+
+    /// Check the next `chunk` of the body.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<(), CborPayloadError> {
+        let mut i = 0;
+        while i < chunk.len() {
+            if self.complete {
+                // trailing bytes after the item
+                return Err(CborPayloadError::Malformed(self.offset + i));
+            }
+
+            if self.skip > 0 {
+                let take = self.skip.min((chunk.len() - i) as u64);
+                i += take as usize;
+                self.skip -= take;
+                if self.skip == 0 {
+                    self.complete = close(&mut self.stack);
+                }
+                continue;
+            }
+
+            self.head.push(chunk[i]);
+            i += 1;
+            let start = self.offset + i - self.head.len();
+            let head = match read_head(&self.head, 0) {
+                Ok(Some(head)) => head,
+                Ok(None) => continue,
+                Err(_) => return Err(CborPayloadError::Malformed(start)),
+            };
+            self.head.clear();
+
+            // least number of bytes the body still needs, nested items taking at least one
+            let end = (self.offset + i) as u64;
+            let step = enter(&mut self.stack, &head, start, self.max_depth)
+                .map_err(|e| CborPayloadError::Malformed(e.offset))?;
+            let needed = match step {
+                Step::String(n) => n,
+                Step::Open => match self.stack.last() {
+                    Some(Frame::Items(n)) => *n,
+                    _ => 0,
+                },
+                Step::Done => 0,
+            };
+            if end.saturating_add(needed) > self.limit as u64 {
+                return Err(CborPayloadError::Overflow);
+            }
+
+            match step {
+                Step::Done | Step::String(0) => self.complete = close(&mut self.stack),
+                Step::String(n) => self.skip = n,
+                Step::Open => {}
+            }
+        }
+        self.offset += chunk.len();
+        Ok(())
+    }
+
+    /// Whether the item ended with the bytes fed so far.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use actix_web::{
+    body::BoxBody,
+    http::header::{HeaderName, HeaderValue},
+    HttpRequest, HttpResponse, Responder,
+};
+use futures_util::future::{ready, LocalBoxFuture};
+use log::error;
+use serde::de::DeserializeOwned;
+use serde_cbor::Value;
+
+use crate::body::{decode_item, decode_item_strict};
+use crate::spool::{decode_reader, decode_reader_strict, rewind, ReaderDecoder};
+use crate::utf8::repair_utf8;
+use crate::{CborBody, CborPayloadError};
+
+/// Header carrying the identifier of the schema a CBOR body conforms to.
+pub const SCHEMA_ID_HEADER: &str = "cbor-schema-id";
+
+/// Validator registered for a single schema identifier.
+type SchemaValidator = Arc<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+/// Source of schemas that request bodies are checked against, keyed by their
+/// [`SCHEMA_ID_HEADER`](constant.SCHEMA_ID_HEADER.html) value.
+///
+/// Validation is asynchronous so implementations may look schemas up remotely.
+pub trait SchemaRegistry: Send + Sync {
+    /// Check `value` against the schema named `id`, returning a description of the mismatch.
+    fn validate<'a>(
+        &'a self,
+        id: &'a str,
+        value: &'a Value,
+    ) -> LocalBoxFuture<'a, Result<(), String>>;
+}
+
+/// Schema registry kept in memory.
+#[derive(Clone, Default)]
+pub struct InMemorySchemaRegistry {
+    schemas: HashMap<String, SchemaValidator>,
+}
+
+impl InMemorySchemaRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a validator for a schema identifier.
+    pub fn register<F>(mut self, id: impl Into<String>, validator: F) -> Self
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.schemas.insert(id.into(), Arc::new(validator));
+        self
+    }
+
+    /// Register a schema identifier whose bodies must deserialize into `T`.
+    pub fn register_type<T>(self, id: impl Into<String>) -> Self
+    where
+        T: DeserializeOwned,
+    {
+        self.register(id, |value| {
+            serde_cbor::value::from_value::<T>(value.clone())
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+impl SchemaRegistry for InMemorySchemaRegistry {
+    fn validate<'a>(
+        &'a self,
+        id: &'a str,
+        value: &'a Value,
+    ) -> LocalBoxFuture<'a, Result<(), String>> {
+        let res = match self.schemas.get(id) {
+            Some(validator) => validator(value),
+            None => Err(format!("unknown schema `{}`", id)),
+        };
+        Box::pin(ready(res))
+    }
+}
+
+/// Schema identifier declared by the request, if any.
+///
+/// When `required`, a missing or unreadable header is an error rather than `None`.
+pub(crate) fn request_schema_id(
+    req: &HttpRequest,
+    required: bool,
+) -> Result<Option<String>, CborPayloadError> {
+    match req.headers().get(SCHEMA_ID_HEADER).map(|v| v.to_str()) {
+        Some(Ok(id)) => Ok(Some(id.to_owned())),
+        Some(Err(_)) if required => Err(CborPayloadError::Schema(format!(
+            "invalid `{}` header",
+            SCHEMA_ID_HEADER
+        ))),
+        None if required => Err(CborPayloadError::Schema(format!(
+            "missing `{}` header",
+            SCHEMA_ID_HEADER
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// Body decoded both as the `Value` checked against a schema and as the `T` it binds to.
+pub(crate) type Validated<T> = (Value, T);
+
+/// Decoder of a whole body to a `Value` and to `T`, both read from the bytes the client sent,
+/// rejecting unknown map keys of `T` when `strict` and repairing invalid UTF-8 when `lossy`.
+pub(crate) fn validated_decoder<T: DeserializeOwned>(
+    strict: bool,
+    lossy: bool,
+) -> fn(&[u8]) -> Result<Validated<T>, CborPayloadError> {
+    match (strict, lossy) {
+        (false, false) => |body| Ok((decode_item(body)?, decode_item(body)?)),
+        (true, false) => |body| Ok((decode_item(body)?, decode_item_strict(body)?)),
+        (false, true) => |body| {
+            let body = repair_utf8(body);
+            Ok((decode_item(&body)?, decode_item(&body)?))
+        },
+        (true, true) => |body| {
+            let body = repair_utf8(body);
+            Ok((decode_item(&body)?, decode_item_strict(&body)?))
+        },
+    }
+}
+
+/// Decoder of a spooled body to a `Value` and to `T`, reading the file twice.
+pub(crate) fn validated_reader_decoder<T: DeserializeOwned>(
+    strict: bool,
+) -> ReaderDecoder<Validated<T>> {
+    match strict {
+        true => |reader| {
+            let value = decode_reader(reader)?;
+            rewind(reader)?;
+            Ok((value, decode_reader_strict(reader)?))
+        },
+        false => |reader| {
+            let value = decode_reader(reader)?;
+            rewind(reader)?;
+            Ok((value, decode_reader(reader)?))
+        },
+    }
+}
+
+/// Validate the `Value` of a body against the registry, returning the `T` decoded with it.
+pub(crate) async fn validate_body<T>(
+    body: CborBody<Validated<T>>,
+    registry: Arc<dyn SchemaRegistry>,
+    id: String,
+) -> Result<T, CborPayloadError>
+where
+    T: 'static,
+{
+    let (value, item) = body.await?;
+    registry
+        .validate(&id, &value)
+        .await
+        .map_err(CborPayloadError::Schema)?;
+    Ok(item)
+}
+
+/// Responder wrapper adding the [`SCHEMA_ID_HEADER`](constant.SCHEMA_ID_HEADER.html) header.
+///
+/// An identifier which is not a valid header value is a bug of the handler, answered with
+/// `500 Internal Server Error` rather than a response missing its schema.
+///
+/// ```
+/// use actix_cbor::{Cbor, WithSchemaId};
+///
+/// async fn handler() -> WithSchemaId<Cbor<u32>> {
+///     WithSchemaId::new(Cbor(7), "counter.v1")
+/// }
+/// ```
+pub struct WithSchemaId<R> {
+    inner: R,
+    id: String,
+}
+
+impl<R> WithSchemaId<R> {
+    /// Wrap a responder, tagging its response with a schema identifier.
+    pub fn new(inner: R, id: impl Into<String>) -> Self {
+        Self {
+            inner,
+            id: id.into(),
+        }
+    }
+}
+
+impl<R: Responder> Responder for WithSchemaId<R> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let value = match HeaderValue::from_str(&self.id) {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Invalid schema id {:?}: {}", self.id, e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let mut res = self.inner.respond_to(req).map_into_boxed_body();
+        res.headers_mut()
+            .insert(HeaderName::from_static(SCHEMA_ID_HEADER), value);
+        res
+    }
+}
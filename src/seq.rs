@@ -0,0 +1,111 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, http::header::HeaderValue, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+
+use crate::untagged;
+use crate::{extraction_error, CborBody, CborConfig, CborPayloadError, MaybeSend};
+
+/// Content type of a CBOR sequence ([RFC 8742](https://tools.ietf.org/html/rfc8742)).
+pub const CBOR_SEQ_CONTENT_TYPE: &str = "application/cbor-seq";
+
+/// `Content-Type` of CBOR sequence responses, cloned instead of being parsed for every response.
+pub(crate) static CBOR_SEQ_CONTENT_TYPE_VALUE: HeaderValue =
+    HeaderValue::from_static(CBOR_SEQ_CONTENT_TYPE);
+
+/// Whether a content type is a CBOR sequence, or accepted by the configured predicate.
+pub(crate) fn is_cbor_seq_content_type(mime: &str, config: &CborConfig) -> bool {
+    mime == CBOR_SEQ_CONTENT_TYPE
+        || config
+            .content_type
+            .as_ref()
+            .is_some_and(|predicate| predicate(mime))
+}
+
+/// Decode every item of a buffered CBOR sequence.
+pub(crate) fn decode_seq<T: DeserializeOwned>(body: &[u8]) -> Result<Vec<T>, CborPayloadError> {
+    Ok(untagged::seq_from_slice(body)?)
+}
+
+/// Extractor buffering an `application/cbor-seq` body and decoding each item into a `Vec<T>`.
+///
+/// The whole body is subject to the [`CborConfig`](struct.CborConfig.html) limit.
+///
+/// With the `rayon` feature, bodies which may hold 1024 items or more are decoded off the async
+/// runtime, on the blocking thread pool, and sequences of 1024 items or more are split at item
+/// boundaries to be decoded in parallel on the rayon thread pool. Items must then be `Send`, see
+/// [`MaybeSend`](trait.MaybeSend.html).
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborSeqVec;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Reading {
+///     value: f64,
+/// }
+///
+/// async fn ingest(readings: CborSeqVec<Reading>) -> String {
+///     format!("{} readings", readings.len())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CborSeqVec<T>(pub Vec<T>);
+
+impl<T> CborSeqVec<T> {
+    /// Deconstruct to the decoded items
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for CborSeqVec<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborSeqVec<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.0
+    }
+}
+
+impl<T> FromRequest for CborSeqVec<T>
+where
+    T: DeserializeOwned + MaybeSend + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+
+        #[cfg(feature = "rayon")]
+        let decode = crate::parallel::decode_seq_par::<T>;
+        #[cfg(not(feature = "rayon"))]
+        let decode = decode_seq::<T>;
+
+        let body = CborBody::with_decoder(
+            req,
+            payload,
+            &config,
+            |mime| is_cbor_seq_content_type(mime, &config),
+            decode,
+        )
+        .limit(config.limit_for(req));
+        #[cfg(feature = "rayon")]
+        let body = body.off_thread();
+
+        body.map(move |res| match res {
+            Err(e) => Err(extraction_error(&config, e, &req2)),
+            Ok(items) => Ok(CborSeqVec(items)),
+        })
+        .boxed_local()
+    }
+}
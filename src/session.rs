@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use serde_cbor::Value;
+
+/// Key of the single entry holding the CBOR state in the wrapped store.
+const STATE_KEY: &str = "actix-cbor";
+
+/// Session state as `actix-session` hands it to its stores: each value is the JSON of what was
+/// inserted.
+type SessionState = HashMap<String, String>;
+
+/// `actix-session` store wrapper serializing the session state as CBOR, available with the
+/// `session` feature.
+///
+/// `actix-session` keeps each value of a session as JSON, and stores such as
+/// `CookieSessionStore` or `RedisSessionStore` encode the whole state as a JSON map of those
+/// strings. Wrapped in a `CborSessionStore`, the state reaches the inner store as a single entry
+/// holding the URL-safe base64 of a CBOR map, whose values are the CBOR encoding of the JSON
+/// ones: numbers and nested strings are no longer written as escaped text.
+///
+/// Cookies and redis entries of sessions made of numbers, such as lists of ids, get smaller;
+/// mostly textual sessions grow by up to the third that base64 adds. States saved before the
+/// store was wrapped are still loaded as is.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborSessionStore;
+/// use actix_session::{storage::CookieSessionStore, SessionMiddleware};
+/// use actix_web::{cookie::Key, App};
+///
+/// let store = CborSessionStore::new(CookieSessionStore::default());
+/// let app = App::new().wrap(SessionMiddleware::new(store, Key::generate()));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CborSessionStore<S>(S);
+
+impl<S> CborSessionStore<S> {
+    /// Serialize the state saved in `store` as CBOR.
+    pub fn new(store: S) -> Self {
+        CborSessionStore(store)
+    }
+
+    /// Deconstruct to the wrapped store
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+/// Single entry state holding the CBOR encoding of `state`.
+fn encode_state(state: SessionState) -> Result<SessionState, anyhow::Error> {
+    let mut values = HashMap::with_capacity(state.len());
+    for (key, json) in state {
+        let value = serde_json::from_str::<serde_json::Value>(&json)?;
+        values.insert(key, serde_cbor::value::to_value(value)?);
+    }
+
+    let cbor = serde_cbor::to_vec(&values)?;
+    let encoded = base64::encode_config(cbor, base64::URL_SAFE_NO_PAD);
+    Ok(HashMap::from([(STATE_KEY.to_owned(), encoded)]))
+}
+
+/// State encoded by `encode_state`, or `state` itself when it was not.
+fn decode_state(mut state: SessionState) -> Result<SessionState, anyhow::Error> {
+    let encoded = match state.remove(STATE_KEY) {
+        Some(encoded) if state.is_empty() => encoded,
+        Some(encoded) => {
+            state.insert(STATE_KEY.to_owned(), encoded);
+            return Ok(state);
+        }
+        None => return Ok(state),
+    };
+
+    let cbor = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)?;
+    let values: HashMap<String, Value> = serde_cbor::from_slice(&cbor)?;
+    values
+        .into_iter()
+        .map(|(key, value)| Ok((key, serde_json::to_string(&value)?)))
+        .collect()
+}
+
+impl<S: SessionStore> SessionStore for CborSessionStore<S> {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        match self.0.load(session_key).await? {
+            Some(state) => decode_state(state)
+                .map(Some)
+                .map_err(LoadError::Deserialization),
+            None => Ok(None),
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let state = encode_state(session_state).map_err(SaveError::Serialization)?;
+        self.0.save(state, ttl).await
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let state = encode_state(session_state).map_err(UpdateError::Serialization)?;
+        self.0.update(session_key, state, ttl).await
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        self.0.update_ttl(session_key, ttl).await
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        self.0.delete(session_key).await
+    }
+}
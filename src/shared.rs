@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use actix_web::{
+    body::BoxBody, http::header::EntityTag, web::Bytes, HttpRequest, HttpResponse, Responder,
+};
+use serde::Serialize;
+
+use crate::{cbor_etag, response_with_etag, CborConfig, CborError, CBOR_CONTENT_TYPE_VALUE};
+
+/// Responder for a CBOR payload serialized once and shared between requests.
+///
+/// Cloning only bumps reference counts, so a single instance can be stored in the application
+/// data and answered as is to every request. Its [`cbor_etag`](fn.cbor_etag.html) is computed
+/// along with the payload, for [`CborConfig::etag`](struct.CborConfig.html#method.etag).
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborShared;
+/// use actix_web::{web, App};
+///
+/// #[derive(serde::Serialize)]
+/// struct Metadata {
+///     version: &'static str,
+/// }
+///
+/// async fn metadata(shared: web::Data<CborShared>) -> CborShared {
+///     CborShared::clone(&shared)
+/// }
+///
+/// let shared = CborShared::new(&Metadata { version: "1.0" }).unwrap();
+/// let app = App::new()
+///     .app_data(web::Data::new(shared))
+///     .route("/metadata", web::get().to(metadata));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborShared(Arc<Shared>);
+
+#[derive(Debug, PartialEq, Eq)]
+struct Shared {
+    body: Bytes,
+    etag: EntityTag,
+}
+
+impl CborShared {
+    /// Serialize `value` once.
+    pub fn new<T: Serialize>(value: &T) -> Result<Self, CborError> {
+        Ok(CborShared(Arc::new(Shared {
+            body: serde_cbor::to_vec(value)?.into(),
+            etag: cbor_etag(value)?,
+        })))
+    }
+
+    /// The encoded payload.
+    pub fn bytes(&self) -> &Bytes {
+        &self.0.body
+    }
+
+    /// The strong entity tag of the payload.
+    pub fn etag(&self) -> &EntityTag {
+        &self.0.etag
+    }
+}
+
+impl Responder for CborShared {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let body = Bytes::clone(&self.0.body);
+        if CborConfig::from_req(req).etag == Some(true) {
+            return response_with_etag(req, body, self.0.etag.clone());
+        }
+
+        HttpResponse::Ok()
+            .content_type(CBOR_CONTENT_TYPE_VALUE.clone())
+            .body(body)
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use actix_web::web::Bytes;
+use futures_util::stream::Stream;
+
+/// Signal ending the streaming responders which follow it cleanly when the server stops.
+///
+/// actix-web gives in-flight responses until the shutdown timeout before dropping their
+/// connection, which leaves a streamed body truncated at an arbitrary byte. Once triggered,
+/// [`CborStream`](struct.CborStream.html) and [`CborIter`](struct.CborIter.html) responses set
+/// up with `graceful` stop pulling items, emit their optional last item and the break marker
+/// of indefinite-length arrays, and end the body, so clients can tell a clean end from a cut
+/// connection.
+///
+/// Trigger it when the process starts stopping, before `ServerHandle::stop(true)`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborShutdown, CborStream};
+/// use actix_web::{web, App};
+/// use futures_util::stream;
+///
+/// let shutdown = CborShutdown::new();
+///
+/// let app = App::new()
+///     .app_data(web::Data::new(shutdown.clone()))
+///     .route(
+///         "/events",
+///         web::get().to(|shutdown: web::Data<CborShutdown>| async move {
+///             CborStream::new(stream::iter(0..u64::MAX)).graceful_with(&shutdown, &"shutdown")
+///         }),
+///     );
+///
+/// // on SIGTERM, then stop the server
+/// shutdown.trigger();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CborShutdown {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    triggered: AtomicBool,
+    next_id: AtomicUsize,
+    wakers: Mutex<HashMap<usize, Waker>>,
+}
+
+impl CborShutdown {
+    /// Create a signal which is not triggered yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// End the responses following this signal, waking those waiting on their source.
+    pub fn trigger(&self) {
+        self.inner.triggered.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.inner.wakers.lock().unwrap());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Whether the signal was triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.inner.triggered.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn subscribe(&self) -> Subscription {
+        Subscription {
+            shutdown: self.clone(),
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Registration of a body stream to be woken when a [`CborShutdown`] is triggered.
+pub(crate) struct Subscription {
+    shutdown: CborShutdown,
+    id: usize,
+}
+
+impl Subscription {
+    /// Whether the signal was triggered, registering `cx` to be woken when it is otherwise.
+    fn poll_triggered(&self, cx: &mut Context<'_>) -> bool {
+        if self.shutdown.is_triggered() {
+            return true;
+        }
+        let mut wakers = self.shutdown.inner.wakers.lock().unwrap();
+        match wakers.get_mut(&self.id) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            _ => {
+                wakers.insert(self.id, cx.waker().clone());
+            }
+        }
+        drop(wakers);
+        // triggered while registering
+        self.shutdown.is_triggered()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Ok(mut wakers) = self.shutdown.inner.wakers.lock() {
+            wakers.remove(&self.id);
+        }
+    }
+}
+
+/// Body stream ending with an optional last frame once its shutdown signal is triggered.
+pub(crate) struct Graceful<S> {
+    inner: S,
+    signal: Subscription,
+    last: Option<Bytes>,
+    done: bool,
+}
+
+impl<S> Graceful<S> {
+    pub(crate) fn new(inner: S, shutdown: &CborShutdown, last: Option<Bytes>) -> Self {
+        Self {
+            inner,
+            signal: shutdown.subscribe(),
+            last,
+            done: false,
+        }
+    }
+}
+
+impl<S, E> Stream for Graceful<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if self.signal.poll_triggered(cx) {
+            self.done = true;
+            return Poll::Ready(self.last.take().map(Ok));
+        }
+
+        let item = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(None) = item {
+            self.done = true;
+        }
+        item
+    }
+}
@@ -0,0 +1,104 @@
+use std::convert::Infallible;
+use std::fmt;
+
+use actix_web::{dev::Payload, web::Bytes, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+
+use crate::body::body_decoder;
+use crate::{is_cbor_content_type, CborBody, CborConfig, CborPayloadError, HttpRequestExt};
+
+/// Extractor which never fails, handing the outcome of decoding a CBOR body to the handler
+/// along with the raw body.
+///
+/// Payloads are checked against the request [`CborConfig`](struct.CborConfig.html) as with
+/// [`Cbor`](struct.Cbor.html), but the configured error handler is not called: the handler
+/// decides how to recover, e.g. by storing the rejected payload. The raw body is empty when
+/// the payload could not be read, e.g. on a wrong content type or a payload too large.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborSoft;
+/// use actix_web::HttpResponse;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     item: String,
+/// }
+///
+/// async fn order(order: CborSoft<Order>) -> HttpResponse {
+///     match order.result() {
+///         Ok(order) => HttpResponse::Ok().body(order.item.clone()),
+///         Err(e) => {
+///             log::warn!("rejected {} bytes: {}", order.bytes().len(), e);
+///             HttpResponse::Accepted().finish()
+///         }
+///     }
+/// }
+/// ```
+pub struct CborSoft<T> {
+    result: Result<T, CborPayloadError>,
+    body: Bytes,
+}
+
+impl<T> CborSoft<T> {
+    /// Outcome of decoding the body.
+    pub fn result(&self) -> &Result<T, CborPayloadError> {
+        &self.result
+    }
+
+    /// The raw body.
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Deconstruct to the outcome of decoding and the raw body
+    pub fn into_parts(self) -> (Result<T, CborPayloadError>, Bytes) {
+        (self.result, self.body)
+    }
+
+    /// Deconstruct to the outcome of decoding
+    pub fn into_inner(self) -> Result<T, CborPayloadError> {
+        self.result
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CborSoft<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborSoft: {:?}", self.result)
+    }
+}
+
+impl<T> FromRequest for CborSoft<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    type Error = Infallible;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req);
+        let ctype = config.content_type.clone();
+        let decode = body_decoder::<T>(
+            config.deny_unknown_fields == Some(true),
+            config.lossy_utf8 == Some(true),
+        );
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |_| Ok(()),
+        )
+        .tee(&req)
+        .limit(config.limit_for(&req));
+
+        Box::pin(async move {
+            let read = body.await;
+            let body = req.cbor_raw_body().unwrap_or_default();
+            let result = read.and_then(|()| decode(&body));
+            Ok(CborSoft { result, body })
+        })
+    }
+}
@@ -0,0 +1,220 @@
+//! Buffering of oversized payloads in temporary files.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use actix_web::{error::PayloadError, rt::task::spawn_blocking, web::Bytes};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+
+use crate::schema_registry::{validated_reader_decoder, Validated};
+use crate::untagged;
+use crate::{CborError, CborPayloadError};
+
+/// Body read back from its temporary file, which decoders may rewind to read it again.
+pub(crate) type SpoolReader = BufReader<File>;
+
+/// Decoder of a body read back from its temporary file.
+pub(crate) type ReaderDecoder<U> = fn(&mut SpoolReader) -> Result<U, SpooledError>;
+
+/// Decoding of a spooled body with its decoder, see [`Spool::new`].
+type SpooledDecode<U> =
+    fn(SpoolFile, ReaderDecoder<U>) -> LocalBoxFuture<'static, Result<U, CborPayloadError>>;
+
+/// Failure to decode a spooled body, sent back from the blocking thread pool.
+pub(crate) enum SpooledError {
+    Io(io::Error),
+    Decode(CborError),
+    UnknownField(String),
+}
+
+impl From<serde_cbor::Error> for SpooledError {
+    fn from(e: serde_cbor::Error) -> Self {
+        SpooledError::Decode(e.into())
+    }
+}
+
+impl From<SpooledError> for CborPayloadError {
+    fn from(e: SpooledError) -> Self {
+        match e {
+            SpooledError::Io(e) => io_error(e),
+            SpooledError::Decode(e) => CborPayloadError::Deserialize(e),
+            SpooledError::UnknownField(path) => CborPayloadError::UnknownField(path),
+        }
+    }
+}
+
+/// Number of temporary files created by this process, making their names unique.
+static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+
+/// Settings of the payloads spooled to disk by a `CborBody`.
+pub(crate) struct Spool<U> {
+    /// Max size of spooled payloads
+    pub(crate) limit: usize,
+    decode: ReaderDecoder<U>,
+    run: SpooledDecode<U>,
+}
+
+impl<U: Send + 'static> Spool<U> {
+    /// Spool payloads of up to `limit` bytes, decoded with `decode` on the blocking thread pool,
+    /// which is why `U` must be `Send`.
+    pub(crate) fn new(limit: usize, decode: ReaderDecoder<U>) -> Self {
+        Spool {
+            limit,
+            decode,
+            run: |file, decode| file.decode(decode).boxed_local(),
+        }
+    }
+}
+
+impl<U> Spool<U> {
+    /// Decode the payload spooled to `file`.
+    pub(crate) fn decode(
+        self,
+        file: SpoolFile,
+    ) -> LocalBoxFuture<'static, Result<U, CborPayloadError>> {
+        (self.run)(file, self.decode)
+    }
+}
+
+/// Spools of a `Cbor` payload, decoded as is or checked against a schema first.
+pub(crate) struct Spools<T> {
+    pub(crate) plain: Spool<T>,
+    pub(crate) validated: Spool<Validated<T>>,
+}
+
+impl<T: DeserializeOwned + Send + 'static> Spools<T> {
+    /// Spool payloads of up to `limit` bytes, rejecting map keys `T` does not consume when
+    /// `strict`.
+    pub(crate) fn new(limit: usize, strict: bool) -> Self {
+        let decode = match strict {
+            true => decode_reader_strict::<T>,
+            false => decode_reader::<T>,
+        };
+        Spools {
+            plain: Spool::new(limit, decode),
+            validated: Spool::new(limit, validated_reader_decoder(strict)),
+        }
+    }
+}
+
+/// Decode a single CBOR item spanning the whole reader.
+pub(crate) fn decode_reader<U: DeserializeOwned>(
+    reader: &mut SpoolReader,
+) -> Result<U, SpooledError> {
+    Ok(untagged::from_reader(reader)?)
+}
+
+/// Decode a single CBOR item spanning the whole reader, rejecting map keys `U` does not consume.
+pub(crate) fn decode_reader_strict<U: DeserializeOwned>(
+    reader: &mut SpoolReader,
+) -> Result<U, SpooledError> {
+    let mut unknown = None;
+    let mut de = serde_cbor::Deserializer::from_reader(reader);
+    let value = serde_ignored::deserialize(untagged::untagged(&mut de), |path| {
+        unknown.get_or_insert_with(|| path.to_string());
+    })?;
+    de.end()?;
+
+    match unknown {
+        Some(path) => Err(SpooledError::UnknownField(path)),
+        None => Ok(value),
+    }
+}
+
+/// Move `reader` back to the start of the body.
+pub(crate) fn rewind(reader: &mut SpoolReader) -> Result<(), SpooledError> {
+    reader.seek(SeekFrom::Start(0)).map_err(SpooledError::Io)?;
+    Ok(())
+}
+
+fn io_error(e: io::Error) -> CborPayloadError {
+    CborPayloadError::Payload(PayloadError::Io(e))
+}
+
+/// Run a blocking file operation off the async runtime.
+async fn blocking<R, F>(f: F) -> Result<R, CborPayloadError>
+where
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+    R: Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(res) => res.map_err(io_error),
+        Err(_) => Err(io_error(io::Error::other("spool task failed"))),
+    }
+}
+
+/// Temporary file holding a payload, removed once dropped.
+pub(crate) struct SpoolFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl SpoolFile {
+    /// Create an empty file in the temporary directory.
+    pub(crate) async fn create() -> Result<Self, CborPayloadError> {
+        let name = format!(
+            "actix-cbor-{}-{}.spool",
+            process::id(),
+            SPOOLED.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = env::temp_dir().join(name);
+        let open = path.clone();
+        let file = blocking(move || {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(open)
+        })
+        .await;
+
+        Ok(SpoolFile {
+            path,
+            file: Some(file?),
+        })
+    }
+
+    /// Append `chunk` to the file.
+    pub(crate) async fn write(&mut self, chunk: Bytes) -> Result<(), CborPayloadError> {
+        let mut file = self.file.take().ok_or_else(closed)?;
+        let file = blocking(move || file.write_all(&chunk).map(|_| file)).await?;
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Decode the file from its start with `decode`, on the blocking thread pool as reading it
+    /// blocks. The file is removed once decoded.
+    async fn decode<U>(mut self, decode: ReaderDecoder<U>) -> Result<U, CborPayloadError>
+    where
+        U: Send + 'static,
+    {
+        let mut file = self.file.take().ok_or_else(closed)?;
+        let decoded = spawn_blocking(move || {
+            file.seek(SeekFrom::Start(0)).map_err(SpooledError::Io)?;
+            decode(&mut BufReader::new(file))
+        })
+        .await;
+
+        match decoded {
+            Ok(res) => Ok(res?),
+            Err(_) => Err(io_error(io::Error::other("spool task failed"))),
+        }
+    }
+}
+
+fn closed() -> CborPayloadError {
+    io_error(io::Error::other("spool file closed after a failed write"))
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        drop(self.file.take());
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+
+use crate::spool::Spools;
+use crate::{extract_cbor, CborConfig};
+
+/// Extractor decoding a CBOR body as [`Cbor`](struct.Cbor.html) does, but spooling payloads over
+/// the limit to a temporary file, as set by
+/// [`CborConfig::spool_to_disk`](struct.CborConfig.html#method.spool_to_disk).
+///
+/// Reading the file blocks, so spooled payloads are decoded on the blocking thread pool, which is
+/// why `T` must be `Send`. Without `spool_to_disk`, payloads over the limit are rejected.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborConfig, CborSpooled};
+/// use actix_web::{web, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Upload {
+///     samples: Vec<f64>,
+/// }
+///
+/// async fn upload(upload: CborSpooled<Upload>) -> String {
+///     format!("{} samples", upload.samples.len())
+/// }
+///
+/// let app = App::new()
+///     .app_data(CborConfig::default().spool_to_disk(64 << 20))
+///     .route("/upload", web::post().to(upload));
+/// ```
+pub struct CborSpooled<T>(pub T);
+
+impl<T> CborSpooled<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborSpooled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborSpooled<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for CborSpooled<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborSpooled: {:?}", self.0)
+    }
+}
+
+impl<T> FromRequest for CborSpooled<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = CborConfig::from_req(req);
+        let strict = config.deny_unknown_fields == Some(true);
+        let spools = config.spool_limit.map(|limit| Spools::new(limit, strict));
+        extract_cbor(req, payload, &config, config.limit_for(req), strict, spools)
+            .map(|res| res.map(CborSpooled))
+            .boxed_local()
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use actix_web::{
+    body::BoxBody,
+    http::header::CACHE_CONTROL,
+    web::{Bytes, BytesMut},
+    HttpRequest, HttpResponse, Responder,
+};
+use futures_util::stream::{LocalBoxStream, Stream, StreamExt};
+use serde::Serialize;
+
+use crate::keepalive::KeepAlive;
+use crate::CborError;
+
+/// Responder streaming items as Server-Sent Events whose `data` field is base64-encoded CBOR.
+///
+/// Browsers receive the events through `EventSource` and decode the payload with any CBOR
+/// library, e.g.:
+///
+/// ```js
+/// source.addEventListener("update", (e) => {
+///     const bytes = Uint8Array.from(atob(e.data), (c) => c.charCodeAt(0));
+///     const value = CBOR.decode(bytes.buffer);
+/// });
+/// ```
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborSse;
+/// use futures_util::stream;
+/// use std::time::Duration;
+///
+/// async fn updates() -> CborSse<stream::Iter<std::vec::IntoIter<u32>>> {
+///     CborSse::new(stream::iter(vec![1, 2, 3]))
+///         .event("update")
+///         .keepalive(Duration::from_secs(15))
+/// }
+/// ```
+pub struct CborSse<S> {
+    stream: S,
+    event: Option<String>,
+    retry: Option<Duration>,
+    keepalive: Option<Duration>,
+}
+
+impl<S> CborSse<S> {
+    /// Send every item of `stream` as an unnamed (`message`) event.
+    pub fn new(stream: S) -> Self {
+        CborSse {
+            stream,
+            event: None,
+            retry: None,
+            keepalive: None,
+        }
+    }
+
+    /// Name of the events, dispatched to the matching `EventSource` listener.
+    ///
+    /// Line breaks would end the `event` field early and let the rest of the name inject other
+    /// fields, so `\r` and `\n` are stripped from it.
+    pub fn event(mut self, name: impl Into<String>) -> Self {
+        let mut name = name.into();
+        name.retain(|c| c != '\r' && c != '\n');
+        self.event = Some(name);
+        self
+    }
+
+    /// Reconnection delay advertised to the client.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Send a comment line whenever no event was sent for `interval`.
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
+}
+
+/// Encode one value as an SSE event.
+fn encode_event<T: Serialize>(event: Option<&str>, item: &T) -> Result<Bytes, CborError> {
+    let encoded = base64::encode_config(serde_cbor::to_vec(item)?, base64::STANDARD);
+
+    let mut frame = BytesMut::with_capacity(encoded.len() + 32);
+    if let Some(event) = event {
+        frame.extend_from_slice(b"event: ");
+        frame.extend_from_slice(event.as_bytes());
+        frame.extend_from_slice(b"\n");
+    }
+    frame.extend_from_slice(b"data: ");
+    frame.extend_from_slice(encoded.as_bytes());
+    frame.extend_from_slice(b"\n\n");
+    Ok(frame.freeze())
+}
+
+impl<S> Responder for CborSse<S>
+where
+    S: Stream + 'static,
+    S::Item: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        let event = self.event;
+        let events = self
+            .stream
+            .map(move |item| encode_event(event.as_deref(), &item));
+
+        let mut body: LocalBoxStream<'static, Result<Bytes, CborError>> = match self.retry {
+            Some(retry) => {
+                let retry = Bytes::from(format!("retry: {}\n\n", retry.as_millis()));
+                futures_util::stream::once(async { Ok(retry) })
+                    .chain(events)
+                    .boxed_local()
+            }
+            None => events.boxed_local(),
+        };
+        if let Some(interval) = self.keepalive {
+            body = KeepAlive::new(body, interval, Bytes::from_static(b": keepalive\n\n"))
+                .boxed_local();
+        }
+
+        HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((CACHE_CONTROL, "no-cache"))
+            .streaming(body)
+    }
+}
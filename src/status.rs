@@ -0,0 +1,132 @@
+use actix_web::{
+    body::BoxBody,
+    http::{
+        header::{HeaderValue, LOCATION},
+        StatusCode,
+    },
+    HttpRequest, HttpResponse, Responder,
+};
+use serde::Serialize;
+
+use crate::Cbor;
+
+/// Respond with `value` as `Cbor` would, replacing the `200 OK` status by `status`.
+fn respond_with_status<T: Serialize + 'static>(
+    value: T,
+    status: StatusCode,
+    req: &HttpRequest,
+) -> HttpResponse {
+    let mut res = Cbor(value).respond_to(req);
+    if res.status() == StatusCode::OK {
+        *res.status_mut() = status;
+    }
+    res
+}
+
+/// Responder serializing the created resource as CBOR with `201 Created`, and optionally its
+/// `Location`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborCreated;
+///
+/// #[derive(serde::Serialize)]
+/// struct User {
+///     id: u64,
+/// }
+///
+/// async fn create() -> CborCreated<User> {
+///     CborCreated::new(User { id: 7 }).location("/users/7")
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CborCreated<T> {
+    value: T,
+    location: Option<String>,
+}
+
+impl<T> CborCreated<T> {
+    /// Respond with the created `value`.
+    pub fn new(value: T) -> Self {
+        CborCreated {
+            value,
+            location: None,
+        }
+    }
+
+    /// Set the URL of the created resource.
+    pub fn location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize + 'static> Responder for CborCreated<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let mut res = respond_with_status(self.value, StatusCode::CREATED, req);
+
+        if res.status().is_success() {
+            let location = self.location.as_deref().map(HeaderValue::from_str);
+            if let Some(Ok(location)) = location {
+                res.headers_mut().insert(LOCATION, location);
+            }
+        }
+        res
+    }
+}
+
+/// Responder serializing `T` as CBOR with `202 Accepted`, e.g. to describe a queued job.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborAccepted;
+///
+/// async fn enqueue() -> CborAccepted<u64> {
+///     CborAccepted(42)
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CborAccepted<T>(pub T);
+
+impl<T> CborAccepted<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Serialize + 'static> Responder for CborAccepted<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        respond_with_status(self.0, StatusCode::ACCEPTED, req)
+    }
+}
+
+/// Responder answering `204 No Content`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborNoContent;
+///
+/// async fn delete() -> CborNoContent {
+///     CborNoContent
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CborNoContent;
+
+impl Responder for CborNoContent {
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        HttpResponse::NoContent().finish()
+    }
+}
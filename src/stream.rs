@@ -0,0 +1,313 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::{
+    body::BoxBody,
+    http::header::CONTENT_TYPE,
+    web::{Bytes, BytesMut},
+    HttpRequest, HttpResponse, Responder,
+};
+use bytes::BufMut;
+use futures_util::stream::{LocalBoxStream, Stream, StreamExt};
+use log::error;
+use serde::Serialize;
+
+use crate::backpressure::Paced;
+use crate::keepalive::KeepAlive;
+use crate::seq::CBOR_SEQ_CONTENT_TYPE_VALUE;
+use crate::shutdown::Graceful;
+use crate::{CborError, CborShutdown, Framing, SeqFraming, Watermarks, CBOR_CONTENT_TYPE_VALUE};
+
+/// Responder streaming items as a framed `application/cbor-seq` body.
+///
+/// Each item is serialized as soon as the underlying stream yields it. Serialization failures
+/// abort the response.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborStream, VarintFraming, Watermarks};
+/// use futures_util::stream;
+/// use std::time::Duration;
+///
+/// async fn export() -> CborStream<stream::Iter<std::ops::Range<u32>>, VarintFraming> {
+///     CborStream::new(stream::iter(0..1000))
+///         .framing(VarintFraming)
+///         .keepalive(Duration::from_secs(15), &())
+///         .watermarks(Watermarks::bytes(64 * 1024))
+/// }
+/// ```
+pub struct CborStream<S, F = SeqFraming> {
+    stream: S,
+    framing: F,
+    keepalive: Option<(Duration, Vec<u8>)>,
+    watermarks: Option<Watermarks>,
+    graceful: Option<(CborShutdown, Option<Vec<u8>>)>,
+}
+
+impl<S> CborStream<S> {
+    /// Stream items as a plain CBOR sequence.
+    pub fn new(stream: S) -> Self {
+        CborStream {
+            stream,
+            framing: SeqFraming,
+            keepalive: None,
+            watermarks: None,
+            graceful: None,
+        }
+    }
+}
+
+impl<S, F> CborStream<S, F> {
+    /// Delimit items with another framing strategy.
+    pub fn framing<G: Framing>(self, framing: G) -> CborStream<S, G> {
+        CborStream {
+            stream: self.stream,
+            framing,
+            keepalive: self.keepalive,
+            watermarks: self.watermarks,
+            graceful: self.graceful,
+        }
+    }
+
+    /// Emit `value` as an extra item whenever no item was sent for `interval`, keeping idle
+    /// connections open through proxies.
+    pub fn keepalive<K: Serialize>(mut self, interval: Duration, value: &K) -> Self {
+        match serde_cbor::to_vec(value) {
+            Ok(encoded) => self.keepalive = Some((interval, encoded)),
+            Err(e) => error!("cbor keepalive serialization error: {}", e),
+        }
+        self
+    }
+
+    /// Coalesce and split frames into chunks bounded by `watermarks`.
+    ///
+    /// Items are always pulled from the source as the client consumes the body; this bounds
+    /// what a single pull hands to the connection, however big the items.
+    pub fn watermarks(mut self, watermarks: Watermarks) -> Self {
+        self.watermarks = Some(watermarks);
+        self
+    }
+
+    /// End the stream cleanly once `shutdown` is triggered, after the items already buffered.
+    pub fn graceful(mut self, shutdown: &CborShutdown) -> Self {
+        self.graceful = Some((shutdown.clone(), None));
+        self
+    }
+
+    /// End the stream cleanly once `shutdown` is triggered, with `last` as the final item.
+    pub fn graceful_with<L: Serialize>(mut self, shutdown: &CborShutdown, last: &L) -> Self {
+        match serde_cbor::to_vec(last) {
+            Ok(encoded) => self.graceful = Some((shutdown.clone(), Some(encoded))),
+            Err(e) => error!("cbor shutdown item serialization error: {}", e),
+        }
+        self
+    }
+}
+
+impl<S, F> Responder for CborStream<S, F>
+where
+    S: Stream + Unpin + 'static,
+    S::Item: Serialize,
+    F: Framing,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        let framing = self.framing;
+        let content_type = framing.content_type();
+
+        // extra frames which cannot be framed are left out, like those failing to serialize
+        let frame_of = |encoded: &[u8]| {
+            let mut frame = BytesMut::with_capacity(encoded.len() + 4);
+            match framing.write_frame(encoded, &mut frame) {
+                Ok(()) => Some(frame.freeze()),
+                Err(e) => {
+                    error!("cbor frame error: {}", e);
+                    None
+                }
+            }
+        };
+        let keepalive = self
+            .keepalive
+            .and_then(|(interval, encoded)| frame_of(&encoded).map(|frame| (interval, frame)));
+        let graceful = self
+            .graceful
+            .map(|(shutdown, last)| (shutdown, last.and_then(|encoded| frame_of(&encoded))));
+
+        let mut body: LocalBoxStream<'static, Result<Bytes, CborError>> = self
+            .stream
+            .map(move |item| {
+                let encoded = serde_cbor::to_vec(&item)?;
+                let mut frame = BytesMut::with_capacity(encoded.len() + 4);
+                framing.write_frame(&encoded, &mut frame)?;
+                Ok(frame.freeze())
+            })
+            .boxed_local();
+
+        if let Some((shutdown, last)) = graceful {
+            body = Graceful::new(body, &shutdown, last).boxed_local();
+        }
+        if let Some(watermarks) = self.watermarks {
+            body = Paced::new(body, watermarks).boxed_local();
+        }
+        if let Some((interval, frame)) = keepalive {
+            body = KeepAlive::new(body, interval, frame).boxed_local();
+        }
+
+        HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, content_type))
+            .streaming(body)
+    }
+}
+
+/// Size above which serialized items are flushed as a body chunk.
+const CHUNK_SIZE: usize = 8192;
+
+/// Responder serializing the items of an iterator lazily, as the client consumes the body.
+///
+/// Items are written as an indefinite-length `application/cbor` array by default, or as an
+/// `application/cbor-seq` sequence. Unlike `Cbor<Vec<T>>`, the items never need to be collected.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborIter;
+///
+/// async fn export() -> CborIter<std::ops::Range<u64>> {
+///     CborIter::new(0..1_000_000)
+/// }
+/// ```
+pub struct CborIter<I> {
+    iter: I,
+    sequence: bool,
+    graceful: Option<(CborShutdown, Option<Vec<u8>>)>,
+}
+
+impl<I: Iterator> CborIter<I> {
+    /// Serialize the items of `iter` as an indefinite-length array.
+    pub fn new<T>(iter: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        CborIter {
+            iter: iter.into_iter(),
+            sequence: false,
+            graceful: None,
+        }
+    }
+
+    /// Serialize the items as an `application/cbor-seq` sequence instead.
+    pub fn sequence(mut self) -> Self {
+        self.sequence = true;
+        self
+    }
+
+    /// Stop serializing items once `shutdown` is triggered, ending the array or sequence
+    /// cleanly.
+    pub fn graceful(mut self, shutdown: &CborShutdown) -> Self {
+        self.graceful = Some((shutdown.clone(), None));
+        self
+    }
+
+    /// Stop serializing items once `shutdown` is triggered, with `last` as the final item.
+    pub fn graceful_with<L: Serialize>(mut self, shutdown: &CborShutdown, last: &L) -> Self {
+        match serde_cbor::to_vec(last) {
+            Ok(encoded) => self.graceful = Some((shutdown.clone(), Some(encoded))),
+            Err(e) => error!("cbor shutdown item serialization error: {}", e),
+        }
+        self
+    }
+}
+
+/// Body stream serializing iterator items into chunks of about `CHUNK_SIZE` bytes.
+struct IterBody<I> {
+    iter: I,
+    array: bool,
+    graceful: Option<(CborShutdown, Option<Vec<u8>>)>,
+    started: bool,
+    done: bool,
+}
+
+impl<I> Stream for IterBody<I>
+where
+    I: Iterator + Unpin,
+    I::Item: Serialize,
+{
+    type Item = Result<Bytes, CborError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let mut chunk = BytesMut::new().writer();
+        if this.array && !this.started {
+            chunk.get_mut().put_u8(0x9f);
+        }
+        this.started = true;
+
+        while chunk.get_ref().len() < CHUNK_SIZE {
+            let shutdown = match &mut this.graceful {
+                Some((shutdown, last)) if shutdown.is_triggered() => Some(last.take()),
+                _ => None,
+            };
+            let next = match shutdown {
+                Some(last) => {
+                    chunk.get_mut().put_slice(&last.unwrap_or_default());
+                    None
+                }
+                None => this.iter.next(),
+            };
+
+            match next {
+                Some(item) => {
+                    if let Err(e) = serde_cbor::to_writer(&mut chunk, &item) {
+                        this.done = true;
+                        return Poll::Ready(Some(Err(e.into())));
+                    }
+                }
+                None => {
+                    if this.array {
+                        chunk.get_mut().put_u8(0xff);
+                    }
+                    this.done = true;
+                    break;
+                }
+            }
+        }
+
+        let chunk = chunk.into_inner();
+        if chunk.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(Ok(chunk.freeze())))
+        }
+    }
+}
+
+impl<I> Responder for CborIter<I>
+where
+    I: Iterator + Unpin + 'static,
+    I::Item: Serialize,
+{
+    type Body = BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        let content_type = if self.sequence {
+            &CBOR_SEQ_CONTENT_TYPE_VALUE
+        } else {
+            &CBOR_CONTENT_TYPE_VALUE
+        };
+
+        HttpResponse::Ok()
+            .content_type(content_type.clone())
+            .streaming(IterBody {
+                iter: self.iter,
+                array: !self.sequence,
+                graceful: self.graceful,
+                started: false,
+                done: false,
+            })
+    }
+}
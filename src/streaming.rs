@@ -0,0 +1,164 @@
+use std::io::{self, Read};
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{
+    dev::Payload,
+    error::ErrorInternalServerError,
+    rt::task::{spawn_blocking, JoinError},
+    web::Bytes,
+    FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::LocalBoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::{channel, Receiver};
+
+use crate::untagged;
+use crate::{
+    extraction_error, is_cbor_content_type, take_payload, BodyStream, CborConfig, CborPayloadError,
+};
+
+/// Number of payload chunks queued for the deserializer before reading the payload pauses.
+const QUEUED_CHUNKS: usize = 4;
+
+/// Blocking reader over the payload chunks sent by the request task.
+struct ChunkReader {
+    rx: Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                // sender gone: end of payload
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.current.len());
+        buf[..len].copy_from_slice(&self.current.split_to(len));
+        Ok(len)
+    }
+}
+
+/// Feed the payload to a deserializer running on the blocking thread pool.
+///
+/// The outer error is a panic of the deserializer.
+async fn deserialize_streaming<T>(
+    mut stream: BodyStream,
+    limit: usize,
+) -> Result<Result<T, CborPayloadError>, JoinError>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (tx, rx) = channel::<Bytes>(QUEUED_CHUNKS);
+    let reader = ChunkReader {
+        rx,
+        current: Bytes::new(),
+    };
+    let decoded = spawn_blocking(move || untagged::from_reader::<T, _>(reader));
+
+    let mut received = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return Ok(Err(e.into())),
+        };
+        received += chunk.len();
+        if received > limit {
+            return Ok(Err(CborPayloadError::Overflow));
+        }
+
+        // waits while the queue is full, fails once the deserializer finished, most likely on
+        // an error
+        if tx.send(chunk).await.is_err() {
+            break;
+        }
+    }
+    drop(tx);
+
+    Ok(decoded.await?.map_err(Into::into))
+}
+
+/// Extractor deserializing the body while it is being received.
+///
+/// Unlike [`Cbor`](struct.Cbor.html), the body is never buffered as a whole: chunks are handed
+/// to a deserializer running on the blocking thread pool as soon as they arrive. Peak memory is
+/// bounded by the decoded value plus a few chunks, and invalid payloads are rejected as soon as
+/// the offending bytes are read. The [`CborConfig`](struct.CborConfig.html) limit still applies
+/// to the whole body.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborStreaming;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Document {
+///     pages: Vec<String>,
+/// }
+///
+/// async fn upload(doc: CborStreaming<Document>) -> String {
+///     format!("{} pages", doc.pages.len())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CborStreaming<T>(pub T);
+
+impl<T> CborStreaming<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborStreaming<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborStreaming<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> FromRequest for CborStreaming<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+
+        let res = if is_cbor_content_type(req.content_type(), config.content_type.as_ref()) {
+            Ok(take_payload(req, payload))
+        } else {
+            Err(CborPayloadError::ContentType)
+        };
+        let limit = config.limit_for(req);
+
+        async move {
+            let res = match res {
+                Ok(stream) => match deserialize_streaming(stream, limit).await {
+                    Ok(res) => res,
+                    Err(e) => {
+                        log::error!("CBOR streaming deserializer panicked: {}", e);
+                        return Err(ErrorInternalServerError("CBOR deserializer failed"));
+                    }
+                },
+                Err(e) => Err(e),
+            };
+            res.map(CborStreaming)
+                .map_err(|e| extraction_error(&config, e, &req2))
+        }
+        .boxed_local()
+    }
+}
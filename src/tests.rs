@@ -1,9 +1,10 @@
 use super::*;
-use actix_web::body::Body;
+use actix_web::body::{to_bytes, MessageBody};
 use actix_web::error::InternalError;
 use actix_web::http::header::{self, ContentType, HeaderValue};
-use actix_web::test::{load_body, TestRequest};
+use actix_web::test::TestRequest;
 use actix_web::{web, HttpResponse};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 struct MyObject {
@@ -20,6 +21,15 @@ impl Default for MyObject {
     }
 }
 
+/// Value whose serialization always fails.
+struct Failing;
+
+impl Serialize for Failing {
+    fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("unsupported"))
+    }
+}
+
 fn get_test_bytes() -> Vec<u8> {
     serde_cbor::to_vec(&MyObject::default()).unwrap()
 }
@@ -43,21 +53,18 @@ async fn test_responder() {
     let encoded = get_test_bytes();
 
     let j = Cbor(obj.clone());
-    let resp = j.respond_to(&req).await.unwrap();
+    let resp = j.respond_to(&req);
     assert_eq!(resp.status(), StatusCode::OK);
     assert_eq!(
         resp.headers().get(header::CONTENT_TYPE).unwrap(),
         header::HeaderValue::from_static("application/cbor")
     );
 
-    let body = resp.body();
+    let body = resp.into_body().try_into_bytes().unwrap();
+    assert_eq!(&encoded, &body);
 
-    if let Body::Bytes(b) = body {
-        assert_eq!(&encoded, b);
-
-        let decoded: MyObject = serde_cbor::from_slice(&b).unwrap();
-        assert_eq!(obj, decoded);
-    }
+    let decoded: MyObject = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(obj, decoded);
 }
 
 #[actix_rt::test]
@@ -69,7 +76,7 @@ async fn test_custom_error_responder() {
         .app_data(CborConfig::default().limit(10).error_handler(|err, _| {
             let msg = MyObject::default();
             let resp = HttpResponse::BadRequest().body(serde_cbor::to_vec(&msg).unwrap());
-            InternalError::from_response(err, resp.into()).into()
+            InternalError::from_response(err, resp).into()
         }))
         .to_http_parts();
 
@@ -77,7 +84,7 @@ async fn test_custom_error_responder() {
     let resp = HttpResponse::from_error(s.err().unwrap());
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-    let body = load_body(resp.into_body()).await.unwrap();
+    let body = to_bytes(resp.into_body()).await.unwrap();
     let msg: MyObject = serde_cbor::from_slice(&body).unwrap();
     assert_eq!(msg.name, "test");
 }
@@ -204,3 +211,3828 @@ async fn test_with_config_in_data_wrapper() {
     let err_str = s.err().unwrap().to_string();
     assert!(err_str.contains("Cbor payload size is bigger than allowed"));
 }
+
+impl CddlSchema for MyObject {
+    fn cddl_type() -> String {
+        "my-object".to_owned()
+    }
+
+    fn cddl_define(registry: &mut CddlRegistry) {
+        registry.define(
+            "my-object",
+            CddlMap::new()
+                .field::<String>("name")
+                .field::<i32>("number"),
+        );
+    }
+}
+
+#[test]
+fn test_cddl_registry() {
+    let registry = CddlRegistry::new().add::<Vec<MyObject>>().add::<MyObject>();
+    assert_eq!(
+        registry.to_cddl(),
+        "my-object = {\n  name: tstr,\n  number: int,\n}\n"
+    );
+    assert_eq!(
+        <BTreeMap<String, Option<MyObject>>>::cddl_type(),
+        "{ * tstr => my-object / nil }"
+    );
+}
+
+#[actix_rt::test]
+async fn test_schema_registry() {
+    let registry = InMemorySchemaRegistry::new()
+        .register_type::<MyObject>("my-object.v1")
+        .register("never", |_| Err("rejected".to_owned()));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .insert_header((SCHEMA_ID_HEADER, "my-object.v1"))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().schema_registry(registry.clone()))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+
+    // validated bodies still spool past the limit
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .insert_header((SCHEMA_ID_HEADER, "my-object.v1"))
+        .set_payload(get_test_bytes())
+        .app_data(
+            CborConfig::default()
+                .schema_registry(registry.clone())
+                .limit(4)
+                .spool_to_disk(1024),
+        )
+        .to_http_parts();
+    let s = CborSpooled::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .insert_header((SCHEMA_ID_HEADER, "never"))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().schema_registry(registry.clone()))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    let resp = HttpResponse::from_error(s.err().unwrap());
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (req2, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(
+            CborConfig::default()
+                .schema_registry(registry)
+                .require_schema_id(true),
+        )
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req2, &mut pl).await;
+    let resp = HttpResponse::from_error(s.err().unwrap());
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let resp = WithSchemaId::new(Cbor(MyObject::default()), "my-object.v1").respond_to(&req);
+    assert_eq!(
+        resp.headers().get(SCHEMA_ID_HEADER).unwrap(),
+        HeaderValue::from_static("my-object.v1")
+    );
+    let resp = WithSchemaId::new(Cbor(MyObject::default()), "bad\nid").respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[actix_rt::test]
+async fn test_cbor_seq_vec() {
+    let mut body = get_test_bytes();
+    body.extend(get_test_bytes());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body.clone())
+        .to_http_parts();
+    let s = CborSeqVec::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(
+        s.into_inner(),
+        vec![MyObject::default(), MyObject::default()]
+    );
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body)
+        .app_data(CborConfig::default().limit(20))
+        .to_http_parts();
+    let s = CborSeqVec::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+}
+
+#[actix_rt::test]
+async fn test_cbor_batch() {
+    let body = serde_cbor::to_vec(&vec![
+        serde_cbor::value::to_value(MyObject::default()).unwrap(),
+        serde_cbor::Value::Text("garbage".to_owned()),
+        serde_cbor::value::to_value(MyObject::default()).unwrap(),
+    ])
+    .unwrap();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body)
+        .to_http_parts();
+    let batch = CborBatch::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(batch.len(), 3);
+    assert!(!batch.is_complete());
+
+    let (items, failures) = batch.into_parts();
+    assert_eq!(items, vec![MyObject::default(), MyObject::default()]);
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, 1);
+}
+
+#[actix_rt::test]
+async fn test_large_batch() {
+    let mut values = vec![serde_cbor::value::to_value(MyObject::default()).unwrap(); 2000];
+    values[1500] = serde_cbor::Value::Text("garbage".to_owned());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(serde_cbor::to_vec(&values).unwrap())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    let batch = CborBatch::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let (items, failures) = batch.into_parts();
+    assert_eq!(items.len(), 1999);
+    assert_eq!(failures[0].0, 1500);
+
+    let body = get_test_bytes().repeat(2000);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body.clone())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    let s = CborSeqVec::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.len(), 2000);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body[..body.len() - 1].to_vec())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    assert!(CborSeqVec::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[actix_rt::test]
+async fn test_par_batch() {
+    let mut values = vec![serde_cbor::value::to_value(MyObject::default()).unwrap(); 2000];
+    values[1500] = serde_cbor::Value::Text("garbage".to_owned());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(serde_cbor::to_vec(&values).unwrap())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    let batch = CborBatch::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let (items, failures) = batch.into_parts();
+    assert_eq!(items.len(), 1999);
+    assert_eq!(failures[0].0, 1500);
+
+    let body = get_test_bytes().repeat(2000);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body.clone())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    let s = CborSeqVec::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.len(), 2000);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body[..body.len() - 1].to_vec())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    assert!(CborSeqVec::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[cfg(not(feature = "rayon"))]
+#[actix_rt::test]
+async fn test_batch_not_send() {
+    // without rayon, items are decoded on the current thread
+    #[derive(Deserialize)]
+    struct Local {
+        name: String,
+        #[serde(skip)]
+        _rc: std::marker::PhantomData<std::rc::Rc<()>>,
+    }
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(serde_cbor::to_vec(&vec![MyObject::default(); 2000]).unwrap())
+        .app_data(CborConfig::default().limit(1 << 20))
+        .to_http_parts();
+    let batch = CborBatch::<Local>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert!(batch.is_complete());
+    assert!(batch.items().all(|item| item.name == "test"));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(get_test_bytes().repeat(3))
+        .to_http_parts();
+    let s = CborSeqVec::<Local>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.len(), 3);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(vec![0x82, 0x01])
+        .to_http_parts();
+    assert!(CborBatch::<Local>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[actix_rt::test]
+async fn test_multi_status_responder() {
+    let req = TestRequest::default().to_http_request();
+
+    let mut report = CborMultiStatus::<MyObject, String>::new();
+    report.push_ok(StatusCode::CREATED, MyObject::default());
+    report.push_err(StatusCode::CONFLICT, "duplicate".to_owned());
+
+    let resp = report.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::MULTI_STATUS);
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let decoded: Vec<BTreeMap<String, serde_cbor::Value>> = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(decoded[0]["status"], serde_cbor::Value::Integer(201));
+    assert!(decoded[0].contains_key("body"));
+    assert_eq!(
+        decoded[1]["error"],
+        serde_cbor::Value::Text("duplicate".to_owned())
+    );
+}
+
+#[test]
+fn test_scan_item() {
+    use crate::scan::{scan_item, Malformed, Scan};
+
+    let bytes = serde_cbor::to_vec(&vec![("a", 1), ("b", 2)]).unwrap();
+    assert_eq!(scan_item(&bytes, 8), Ok(Scan::Complete(bytes.len())));
+    assert_eq!(
+        scan_item(&bytes[..bytes.len() - 1], 8),
+        Ok(Scan::Incomplete)
+    );
+    assert_eq!(scan_item(&bytes, 1), Err(Malformed { offset: 1 }));
+
+    // indefinite array [1, "ab"] followed by another item
+    let bytes = [0x9f, 0x01, 0x62, b'a', b'b', 0xff, 0x00];
+    assert_eq!(scan_item(&bytes, 8), Ok(Scan::Complete(6)));
+
+    // stray break and reserved additional information
+    assert_eq!(scan_item(&[0xff], 8), Err(Malformed { offset: 0 }));
+    assert_eq!(scan_item(&[0x1c], 8), Err(Malformed { offset: 0 }));
+}
+
+#[test]
+fn test_sniffer() {
+    use crate::scan::Sniffer;
+
+    // accepts a valid item fed byte by byte
+    let bytes = get_test_bytes();
+    let mut sniffer = Sniffer::new(1024, 8);
+    for b in &bytes {
+        sniffer.feed(&[*b]).unwrap();
+    }
+    // trailing bytes
+    assert!(matches!(
+        sniffer.feed(&[0x00]),
+        Err(CborPayloadError::Malformed(o)) if o == bytes.len()
+    ));
+
+    // reserved additional information, after a header split across chunks
+    let mut sniffer = Sniffer::new(1024, 8);
+    sniffer.feed(&[0x82, 0x19]).unwrap();
+    sniffer.feed(&[0x01]).unwrap();
+    assert!(matches!(
+        sniffer.feed(&[0x00, 0x1c]),
+        Err(CborPayloadError::Malformed(4))
+    ));
+
+    // byte string of 1MiB and array of 2000 items cannot fit in the limit
+    let mut sniffer = Sniffer::new(1024, 8);
+    assert!(matches!(
+        sniffer.feed(&[0x5a, 0x00, 0x10, 0x00, 0x00]),
+        Err(CborPayloadError::Overflow)
+    ));
+    let mut sniffer = Sniffer::new(1024, 8);
+    assert!(matches!(
+        sniffer.feed(&[0x99, 0x07, 0xd0]),
+        Err(CborPayloadError::Overflow)
+    ));
+}
+
+#[actix_rt::test]
+async fn test_sniff_fails_fast() {
+    use actix_web::error::PayloadError;
+    use futures_util::stream::{self, StreamExt};
+
+    // JSON followed by a body which never ends, its brace reading as a huge text string
+    let json = web::Bytes::from_static(br#"{"name": "test""#);
+    let body = stream::iter(vec![Ok::<_, PayloadError>(json)]).chain(stream::pending());
+    let (req, _) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::default().sniff(true))
+        .to_http_parts();
+    let mut pl = Payload::Stream {
+        payload: Box::pin(body) as _,
+    };
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    let err = err.as_error::<CborPayloadError>().unwrap();
+    assert!(matches!(err, CborPayloadError::Overflow));
+}
+
+#[actix_rt::test]
+async fn test_cbor_for_each() {
+    // indefinite-length array of two objects
+    let mut body = vec![0x9f];
+    body.extend(get_test_bytes());
+    body.extend(get_test_bytes());
+    body.push(0xff);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body)
+        .app_data(CborConfig::default().limit(20))
+        .to_http_parts();
+    let items = CborForEach::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let mut seen = Vec::new();
+    let count = items
+        .for_each(|item| {
+            seen.push(item);
+            async {}
+        })
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(seen, vec![MyObject::default(), MyObject::default()]);
+
+    let mut body = get_test_bytes();
+    body.extend(&get_test_bytes()[..4]);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .set_payload(body)
+        .to_http_parts();
+    let items = CborForEach::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let res = items.for_each(|_| async {}).await;
+    assert!(matches!(res, Err(CborPayloadError::Malformed(_))));
+}
+
+#[actix_rt::test]
+async fn test_cbor_streaming() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = CborStreaming::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().limit(10))
+        .to_http_parts();
+    let s = CborStreaming::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+
+    struct Panics;
+
+    impl<'de> serde::Deserialize<'de> for Panics {
+        fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+            panic!("deserializer bug")
+        }
+    }
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = CborStreaming::<Panics>::from_request(&req, &mut pl).await;
+    let resp = HttpResponse::from_error(s.err().unwrap());
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[actix_rt::test]
+async fn test_cbor_framed_round_trip() {
+    use futures_util::stream;
+    use futures_util::StreamExt;
+
+    let req = TestRequest::default().to_http_request();
+    let objects = vec![MyObject::default(), MyObject::default()];
+    let resp = CborStream::new(stream::iter(objects.clone()))
+        .framing(VarintFraming)
+        .respond_to(&req);
+    let content_type = resp.headers().get(header::CONTENT_TYPE).unwrap().clone();
+    assert_eq!(content_type, "application/cbor-seq; framing=varint");
+    let body = to_bytes(resp.into_body()).await.unwrap();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, content_type))
+        .set_payload(body)
+        .to_http_parts();
+    let items = CborFramed::<MyObject, VarintFraming>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let decoded: Vec<MyObject> = items.map(Result::unwrap).collect().await;
+    assert_eq!(decoded, objects);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, CBOR_SEQ_CONTENT_TYPE))
+        .to_http_parts();
+    let res = CborFramed::<MyObject, U32Framing>::from_request(&req, &mut pl).await;
+    assert!(res.is_err());
+}
+
+#[actix_rt::test]
+async fn test_cbor_stream_keepalive() {
+    use futures_util::future::poll_fn;
+    use futures_util::stream;
+    use std::pin::Pin;
+    use std::time::Duration;
+
+    let req = TestRequest::default().to_http_request();
+    let resp = CborStream::new(stream::pending::<MyObject>())
+        .keepalive(Duration::from_millis(10), &())
+        .respond_to(&req);
+
+    let mut body = resp.into_body();
+    let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&chunk[..], &[0xf6]);
+}
+
+#[actix_rt::test]
+async fn test_watermarks_pause_source() {
+    use crate::backpressure::Paced;
+    use actix_web::web::Bytes;
+    use futures_util::stream::{self, StreamExt};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let pulled = Rc::new(Cell::new(0));
+    let counter = pulled.clone();
+    let source = stream::iter(0..5u8).map(move |n| {
+        counter.set(counter.get() + 1);
+        let len = if n == 2 { 20 } else { 4 };
+        Ok::<_, ()>(Bytes::from(vec![n; len]))
+    });
+
+    // frames ready at once are coalesced, without pulling ahead of the connection
+    let mut body = Paced::new(source, Watermarks::bytes(8).items(3));
+    assert_eq!(
+        body.next().await,
+        Some(Ok(Bytes::from(vec![0, 0, 0, 0, 1, 1, 1, 1])))
+    );
+    assert_eq!(pulled.get(), 2);
+
+    // bigger frames are split, and the source waits until they are sent
+    assert_eq!(body.next().await, Some(Ok(Bytes::from(vec![2; 8]))));
+    assert_eq!(body.next().await, Some(Ok(Bytes::from(vec![2; 8]))));
+    assert_eq!(pulled.get(), 3);
+
+    let rest: Vec<_> = body.map(Result::unwrap).collect().await;
+    assert_eq!(
+        rest,
+        vec![
+            Bytes::from(vec![2, 2, 2, 2, 3, 3, 3, 3]),
+            Bytes::from(vec![4; 4])
+        ]
+    );
+    assert_eq!(pulled.get(), 5);
+}
+
+#[actix_rt::test]
+async fn test_cbor_iter_responder() {
+    let req = TestRequest::default().to_http_request();
+
+    let resp = CborIter::new(vec![MyObject::default(); 1000]).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let decoded: Vec<MyObject> = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(decoded, vec![MyObject::default(); 1000]);
+
+    let resp = CborIter::new(0..3u8).sequence().respond_to(&req);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &[0, 1, 2]);
+}
+
+#[actix_rt::test]
+async fn test_graceful_shutdown() {
+    use futures_util::future::poll_fn;
+    use futures_util::stream::{self, StreamExt};
+    use std::pin::Pin;
+
+    let req = TestRequest::default().to_http_request();
+
+    let shutdown = CborShutdown::new();
+    let trigger = shutdown.clone();
+    let items = (0..10u8).inspect(move |&n| {
+        if n == 2 {
+            trigger.trigger();
+        }
+    });
+    let resp = CborIter::new(items)
+        .graceful_with(&shutdown, &"bye")
+        .respond_to(&req);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"\x9f\x00\x01\x02\x63bye\xff");
+
+    // a pending source is woken by the signal
+    let shutdown = CborShutdown::new();
+    let resp = CborStream::new(stream::iter(0..2u8).chain(stream::pending()))
+        .graceful_with(&shutdown, &"bye")
+        .respond_to(&req);
+    let mut body = resp.into_body();
+    for expected in [[0x00], [0x01]] {
+        let chunk = poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await;
+        assert_eq!(&chunk.unwrap().unwrap()[..], &expected);
+    }
+    actix_rt::spawn({
+        let shutdown = shutdown.clone();
+        async move { shutdown.trigger() }
+    });
+    let rest = to_bytes(body).await.unwrap();
+    assert_eq!(&rest[..], b"\x63bye");
+    assert!(shutdown.is_triggered());
+}
+
+#[actix_rt::test]
+async fn test_cbor_body_writer() {
+    use actix_web::body::BodySize;
+
+    let objects = vec![MyObject::default(); 1000];
+    let writer = CborBodyWriter::from_value(&objects).unwrap();
+    let expected = serde_cbor::to_vec(&objects).unwrap();
+    assert_eq!(writer.size(), BodySize::Sized(expected.len() as u64));
+
+    let resp = HttpResponse::Ok().body(writer);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &expected[..]);
+}
+
+#[actix_rt::test]
+async fn test_cbor_sse() {
+    use futures_util::stream;
+
+    let req = TestRequest::default().to_http_request();
+    let resp = CborSse::new(stream::iter(vec![1u8]))
+        .event("tick")
+        .respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/event-stream"
+    );
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"event: tick\ndata: AQ==\n\n");
+
+    let resp = CborSse::new(stream::iter(vec![1u8]))
+        .event("tick\r\ndata: forged")
+        .respond_to(&req);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"event: tickdata: forged\ndata: AQ==\n\n");
+}
+
+#[cfg(feature = "multipart")]
+async fn multipart_field(
+    part_headers: &str,
+) -> (actix_multipart::Multipart, actix_multipart::Field) {
+    use actix_multipart::Multipart;
+    use actix_web::http::header::HeaderMap;
+    use futures_util::{stream, StreamExt};
+
+    let mut body = format!("--abc\r\n{}\r\n", part_headers).into_bytes();
+    body.extend_from_slice(&get_test_bytes());
+    body.extend_from_slice(b"\r\n--abc--\r\n");
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("multipart/form-data; boundary=abc"),
+    );
+    let payload = stream::iter(vec![Ok(web::Bytes::from(body))]);
+    // fields can only be read while their `Multipart` is alive
+    let mut parts = Multipart::new(&headers, payload);
+    let field = parts.next().await.unwrap().unwrap();
+    (parts, field)
+}
+
+#[cfg(feature = "multipart")]
+#[actix_rt::test]
+async fn test_cbor_field() {
+    let (_parts, mut field) = multipart_field(
+        "Content-Disposition: form-data; name=\"meta\"\r\nContent-Type: application/cbor\r\n",
+    )
+    .await;
+    let obj = CborField::<MyObject>::from_field(&mut field).await.unwrap();
+    assert_eq!(obj.into_inner(), MyObject::default());
+
+    let (_parts, mut field) =
+        multipart_field("Content-Disposition: form-data; name=\"meta\"\r\n").await;
+    let err = CborField::<MyObject>::from_field_with_limit(&mut field, 2)
+        .await
+        .unwrap_err();
+    assert!(cbor_eq(err, CborPayloadError::Overflow));
+
+    let (_parts, mut field) = multipart_field(
+        "Content-Disposition: form-data; name=\"meta\"\r\nContent-Type: text/plain\r\n",
+    )
+    .await;
+    let err = CborField::<MyObject>::from_field(&mut field)
+        .await
+        .unwrap_err();
+    assert!(cbor_eq(err, CborPayloadError::ContentType));
+}
+
+#[cfg(feature = "multipart")]
+#[actix_rt::test]
+async fn test_cbor_related() {
+    use futures_util::StreamExt;
+
+    let mut body = b"--abc\r\nContent-Type: application/cbor\r\n\r\n".to_vec();
+    body.extend_from_slice(&get_test_bytes());
+    body.extend_from_slice(
+        b"\r\n--abc\r\nContent-Type: application/octet-stream\r\n\r\nblob\r\n--abc--\r\n",
+    );
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((
+            header::CONTENT_TYPE,
+            "multipart/related; type=\"application/cbor\"; boundary=abc",
+        ))
+        .set_payload(body)
+        .to_http_parts();
+
+    let mut doc = CborRelated::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(doc.root(), &MyObject::default());
+
+    let mut part = doc.parts().next().await.unwrap().unwrap();
+    assert_eq!(&part.next().await.unwrap().unwrap()[..], b"blob");
+    assert!(part.next().await.is_none());
+    drop(part);
+    assert!(doc.parts().next().await.is_none());
+    assert!(doc.preceding().is_empty());
+
+    // root designated by the start parameter, after an attachment
+    let mut body =
+        b"--abc\r\nContent-ID: <blob>\r\nContent-Type: application/octet-stream\r\n\r\nblob\r\n"
+            .to_vec();
+    body.extend_from_slice(
+        b"--abc\r\nContent-ID: <root>\r\nContent-Type: application/cbor\r\n\r\n",
+    );
+    body.extend_from_slice(&get_test_bytes());
+    body.extend_from_slice(b"\r\n--abc--\r\n");
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((
+            header::CONTENT_TYPE,
+            "multipart/related; type=\"application/cbor\"; start=\"<root>\"; boundary=abc",
+        ))
+        .set_payload(body)
+        .to_http_parts();
+
+    let mut doc = CborRelated::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(doc.root(), &MyObject::default());
+    assert_eq!(doc.preceding().len(), 1);
+    assert_eq!(doc.preceding()[0].content_id.as_deref(), Some("<blob>"));
+    assert_eq!(&doc.preceding()[0].body[..], b"blob");
+    assert!(doc.parts().next().await.is_none());
+}
+
+#[cfg(feature = "minicbor")]
+#[actix_rt::test]
+async fn test_mini_cbor() {
+    #[derive(minicbor::Decode, minicbor::Encode, PartialEq, Debug)]
+    struct Point {
+        #[n(0)]
+        x: i32,
+        #[n(1)]
+        y: i32,
+    }
+
+    let bytes = minicbor::to_vec(Point { x: 1, y: -2 }).unwrap();
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(bytes.clone())
+        .to_http_parts();
+    let point = MiniCbor::<Point>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(point.into_inner(), Point { x: 1, y: -2 });
+
+    let resp = MiniCbor(Point { x: 1, y: -2 }).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, bytes);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(bytes)
+        .app_data(CborConfig::default().limit(2))
+        .to_http_parts();
+    let err = MiniCbor::<Point>::from_request(&req, &mut pl)
+        .await
+        .unwrap_err();
+    let resp = err.error_response();
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(&[0x82, 0x01][..])
+        .to_http_parts();
+    let err = MiniCbor::<Point>::from_request(&req, &mut pl)
+        .await
+        .unwrap_err();
+    assert_eq!(err.error_response().status(), StatusCode::BAD_REQUEST);
+}
+
+#[cfg(feature = "msgpack")]
+#[actix_rt::test]
+async fn test_cbor_or_msgpack() {
+    let bytes = rmp_serde::to_vec_named(&MyObject::default()).unwrap();
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/msgpack"))
+        .insert_header((
+            header::ACCEPT,
+            "application/cbor;q=0.5, application/msgpack",
+        ))
+        .set_payload(bytes.clone())
+        .to_http_parts();
+    let obj = CborOrMsgpack::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(obj.0, MyObject::default());
+
+    let resp = obj.respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/msgpack"))
+    );
+    assert_eq!(
+        resp.headers().get(header::VARY),
+        Some(&HeaderValue::from_static("accept"))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, bytes);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let obj = CborOrMsgpack::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let resp = obj.respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/cbor"))
+    );
+    assert_eq!(
+        resp.headers().get(header::VARY),
+        Some(&HeaderValue::from_static("accept"))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, get_test_bytes());
+}
+
+#[cfg(feature = "session")]
+#[actix_rt::test]
+async fn test_cbor_session_store() {
+    use actix_session::storage::{CookieSessionStore, SessionStore};
+    use actix_session::{Session, SessionMiddleware};
+    use actix_web::cookie::{time::Duration, Key};
+    use actix_web::{test, App};
+    use std::collections::HashMap;
+
+    let ids: Vec<u32> = (100_000..100_100).collect();
+    let state = HashMap::from([
+        ("ids".to_owned(), serde_json::to_string(&ids).unwrap()),
+        (
+            "user".to_owned(),
+            serde_json::to_string(&MyObject::default()).unwrap(),
+        ),
+    ]);
+
+    // the state round-trips, and takes less room than its JSON
+    let ttl = Duration::minutes(5);
+    let store = CborSessionStore::new(CookieSessionStore::default());
+    let key = store.save(state.clone(), &ttl).await.unwrap();
+    let json_key = CookieSessionStore::default()
+        .save(state.clone(), &ttl)
+        .await
+        .unwrap();
+    assert!(key.as_ref().len() < json_key.as_ref().len());
+    assert_eq!(store.load(&key).await.unwrap().unwrap(), state);
+
+    // states saved before the store was wrapped are loaded as is
+    assert_eq!(store.load(&json_key).await.unwrap().unwrap(), state);
+
+    let app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(store, Key::from(&[0; 64])))
+            .route(
+                "/set",
+                web::post().to(|session: Session| async move {
+                    session.insert("user", MyObject::default()).unwrap();
+                    HttpResponse::Ok().finish()
+                }),
+            )
+            .route(
+                "/get",
+                web::get().to(|session: Session| async move {
+                    Cbor(session.get::<MyObject>("user").unwrap())
+                }),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post().uri("/set").to_request();
+    let resp = test::call_service(&app, req).await;
+    let cookie = resp.response().cookies().next().unwrap().into_owned();
+    let req = test::TestRequest::get()
+        .uri("/get")
+        .cookie(cookie)
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let user: Option<MyObject> = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(user, Some(MyObject::default()));
+}
+
+#[actix_rt::test]
+async fn test_response_cbor() {
+    use actix_web::dev::Service;
+    use actix_web::{test, App};
+
+    let app = test::init_service(
+        App::new()
+            .wrap_fn(|req, srv| {
+                let fut = srv.call(req);
+                async move {
+                    let res = fut.await?;
+                    if !is_cbor_response(&res) {
+                        return Ok(res);
+                    }
+                    let (res, mut obj) = response_cbor::<MyObject, _>(res, 1024).await?;
+                    obj.number += 1;
+                    Ok(with_cbor_body(res, &obj)?)
+                }
+            })
+            .route(
+                "/cbor",
+                web::get().to(|| async { Cbor(MyObject::default()).with_etag() }),
+            )
+            .route("/text", web::get().to(|| async { "hello" })),
+    )
+    .await;
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/cbor").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get(header::ETAG).is_none());
+    let body = test::read_body(resp).await;
+    let obj: MyObject = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(obj.number, MyObject::default().number + 1);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/text").to_request()).await;
+    assert_eq!(test::read_body(resp).await, "hello");
+
+    let req = TestRequest::default().to_http_request();
+    let res = Cbor(MyObject::default()).respond_to(&req);
+    let res = actix_web::dev::ServiceResponse::new(req, res);
+    let err = response_cbor::<MyObject, _>(res, 2).await.unwrap_err();
+    assert!(matches!(err, CborPayloadError::ResponseOverflow));
+}
+
+#[actix_rt::test]
+async fn test_cbor_error_handlers() {
+    use actix_web::{test, App};
+
+    let app = test::init_service(
+        App::new()
+            .wrap(cbor_error_handlers())
+            .route(
+                "/",
+                web::post().to(|obj: Cbor<MyObject>| async move { obj }),
+            )
+            .route(
+                "/{id}",
+                web::put().to(|id: web::Path<u32>| async move { id.to_string() }),
+            ),
+    )
+    .await;
+
+    let resp =
+        test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(body, CborErrorBody::new(StatusCode::NOT_FOUND, "Not Found"));
+
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(ContentType::json())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(
+        body,
+        CborErrorBody::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type")
+    );
+
+    let req = test::TestRequest::put().uri("/abc").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert!(body.error.contains("abc"), "{}", body.error);
+}
+
+#[actix_rt::test]
+async fn test_cbor_rejection() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType::json())
+        .app_data(CborConfig::default().error_handler(|err, _| CborRejection::from(err).into()))
+        .to_http_parts();
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap_err();
+    let resp = err.error_response();
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let body: CborErrorBody = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(body.code, Some(CborErrorCode::UnsupportedMediaType));
+    assert_eq!(body.error, "Content type error");
+
+    let value = serde_cbor::value::to_value(CborErrorCode::DecodeError).unwrap();
+    assert_eq!(value, serde_cbor::Value::Text("decode_error".to_owned()));
+    assert_eq!(
+        CborPayloadError::UnknownField("extra".to_owned()).code(),
+        CborErrorCode::UnknownField
+    );
+}
+
+#[actix_rt::test]
+async fn test_localized_messages() {
+    let config = CborConfig::default()
+        .limit(2)
+        .messages(|code, lang| match (code, lang) {
+            (CborErrorCode::Overflow, "fr") => Some("Contenu trop volumineux".to_owned()),
+            _ => None,
+        });
+
+    for (languages, expected) in [
+        ("de, fr-CA;q=0.8", "Contenu trop volumineux"),
+        ("de", "Cbor payload size is bigger than allowed"),
+    ] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .insert_header((header::ACCEPT_LANGUAGE, languages))
+            .set_payload(get_test_bytes())
+            .app_data(config.clone())
+            .to_http_parts();
+        let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+            .await
+            .unwrap_err();
+        let resp = err.error_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        let body: CborErrorBody = serde_cbor::from_slice(&body).unwrap();
+        assert_eq!(body.code, Some(CborErrorCode::Overflow));
+        assert_eq!(body.error, expected);
+    }
+}
+
+#[actix_rt::test]
+async fn test_idempotency() {
+    use actix_web::{test, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = test::init_service(
+        App::new()
+            .wrap(Idempotency::new(InMemoryIdempotencyStore::new(
+                std::time::Duration::from_secs(60),
+            )))
+            .route(
+                "/",
+                web::post().to(move |map: Cbor<BTreeMap<String, u32>>| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async move { Cbor(map.values().sum::<u32>()) }
+                }),
+            ),
+    )
+    .await;
+
+    let post = |key: &str, body: &'static [u8]| {
+        test::TestRequest::post()
+            .uri("/")
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .insert_header((IDEMPOTENCY_KEY, key.to_owned()))
+            .set_payload(body)
+            .to_request()
+    };
+    // {"a": 1, "b": 2}, with the keys in both orders
+    let ab: &[u8] = &[0xa2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x02];
+    let ba: &[u8] = &[0xa2, 0x61, 0x62, 0x02, 0x61, 0x61, 0x01];
+
+    let resp = test::call_service(&app, post("k1", ab)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    assert_eq!(test::read_body(resp).await, serde_cbor::to_vec(&3).unwrap());
+
+    let resp = test::call_service(&app, post("k1", ba)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+    assert_eq!(test::read_body(resp).await, serde_cbor::to_vec(&3).unwrap());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let resp = test::call_service(&app, post("k1", &[0xa1, 0x61, 0x61, 0x05])).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let resp = test::call_service(&app, post("k2", ba)).await;
+    assert!(resp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[actix_rt::test]
+async fn test_idempotency_limits() {
+    use actix_web::{test, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let store = InMemoryIdempotencyStore::new(std::time::Duration::from_secs(60)).max_entries(1);
+    let app = test::init_service(
+        App::new()
+            .wrap(Idempotency::new(store).max_body_size(4))
+            .route(
+                "/",
+                web::post().to(move |n: Cbor<u32>| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async move { Cbor(vec![*n; *n as usize]) }
+                }),
+            ),
+    )
+    .await;
+
+    let post = |key: &str, n: u32| {
+        test::TestRequest::post()
+            .uri("/")
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .insert_header((IDEMPOTENCY_KEY, key.to_owned()))
+            .set_payload(serde_cbor::to_vec(&n).unwrap())
+            .to_request()
+    };
+
+    // 6 bytes, over the limit: sent whole but not stored
+    for _ in 0..2 {
+        let resp = test::call_service(&app, post("big", 5)).await;
+        assert!(resp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(
+            test::read_body(resp).await,
+            serde_cbor::to_vec(&[5; 5]).unwrap()
+        );
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let resp = test::call_service(&app, post("k1", 3)).await;
+    assert!(resp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    let resp = test::call_service(&app, post("k1", 3)).await;
+    assert_eq!(resp.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // the store holds a single entry, so k2 evicts k1
+    test::call_service(&app, post("k2", 3)).await;
+    let resp = test::call_service(&app, post("k1", 3)).await;
+    assert!(resp.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+}
+
+#[actix_rt::test]
+async fn test_response_cache() {
+    use actix_web::{test, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let app = test::init_service(
+        App::new()
+            .wrap(CborResponseCache::new(Duration::from_millis(200)).max_body_size(16))
+            .route(
+                "/",
+                web::post().to(move |map: Cbor<BTreeMap<String, u32>>| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let sum = map.values().sum::<u32>();
+                    async move { Cbor(vec![sum; sum as usize]) }
+                }),
+            ),
+    )
+    .await;
+
+    let post = |body: &'static [u8]| {
+        test::TestRequest::post()
+            .uri("/")
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .set_payload(body)
+            .to_request()
+    };
+    // {"a": 1, "b": 2}, with the keys in both orders
+    let ab: &[u8] = &[0xa2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x02];
+    let ba: &[u8] = &[0xa2, 0x61, 0x62, 0x02, 0x61, 0x61, 0x01];
+    let expected = serde_cbor::to_vec(&vec![3; 3]).unwrap();
+
+    let resp = test::call_service(&app, post(ab)).await;
+    assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+    assert_eq!(test::read_body(resp).await, expected);
+
+    let resp = test::call_service(&app, post(ba)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(X_CACHE).unwrap(), "HIT");
+    assert_eq!(test::read_body(resp).await, expected);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // requests differing by their Accept header or body get their own entry
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .insert_header((header::ACCEPT, "application/cbor"))
+        .set_payload(ab)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+    let resp = test::call_service(&app, post(&[0xa1, 0x61, 0x61, 0x03])).await;
+    assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // {"a": 20} answers 21 bytes, over the size limit
+    let big: &[u8] = &[0xa1, 0x61, 0x61, 0x14];
+    for _ in 0..2 {
+        let resp = test::call_service(&app, post(big)).await;
+        assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+    actix_rt::time::sleep(Duration::from_millis(250)).await;
+    let resp = test::call_service(&app, post(ab)).await;
+    assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+    assert_eq!(calls.load(Ordering::SeqCst), 6);
+}
+
+#[actix_rt::test]
+async fn test_response_cache_credentials() {
+    use actix_web::{test, App};
+    use std::time::Duration;
+
+    let app = test::init_service(
+        App::new()
+            .wrap(CborResponseCache::new(Duration::from_secs(60)))
+            .route(
+                "/user",
+                web::get().to(|req: HttpRequest| async move {
+                    let user = req
+                        .headers()
+                        .get(header::AUTHORIZATION)
+                        .map(|v| v.to_str().unwrap().to_owned());
+                    Cbor(user)
+                }),
+            )
+            .route(
+                "/login",
+                web::get().to(|| async {
+                    HttpResponse::Ok()
+                        .insert_header((header::SET_COOKIE, "session=1"))
+                        .finish()
+                }),
+            ),
+    )
+    .await;
+
+    for user in ["Bearer alice", "Bearer bob", "Bearer alice"] {
+        let req = test::TestRequest::get()
+            .uri("/user")
+            .insert_header((header::AUTHORIZATION, user))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(X_CACHE).is_none());
+        assert_eq!(
+            test::read_body(resp).await,
+            serde_cbor::to_vec(&Some(user)).unwrap()
+        );
+    }
+
+    for _ in 0..2 {
+        let req = test::TestRequest::get().uri("/login").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(X_CACHE).unwrap(), "MISS");
+        assert_eq!(resp.headers().get(header::SET_COOKIE).unwrap(), "session=1");
+    }
+}
+
+#[actix_rt::test]
+async fn test_cached_cbor() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+
+    let value = CachedCbor::<serde_cbor::Value>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert!(matches!(value.into_inner(), serde_cbor::Value::Map(_)));
+
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.name, "test");
+    let s = CachedCbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.number, 7);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert!(Cbor::<MyObject>::from_request(&req, &mut pl).await.is_err());
+}
+
+#[actix_rt::test]
+async fn test_tee() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::default().tee(true))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    assert!(req.cbor_raw_body().is_none());
+
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.name, "test");
+    assert_eq!(req.cbor_raw_body().unwrap(), get_test_bytes());
+
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.number, 7);
+}
+
+#[cfg(feature = "macros")]
+#[actix_rt::test]
+async fn test_cbor_handler() {
+    use actix_web::error::ErrorNotFound;
+    use actix_web::{test, App};
+
+    #[cbor_handler]
+    async fn rename(id: web::Path<i32>, obj: MyObject) -> actix_web::Result<MyObject> {
+        match id.into_inner() {
+            0 => Err(ErrorNotFound("no such object")),
+            number => Ok(MyObject { number, ..obj }),
+        }
+    }
+
+    let app = test::init_service(App::new().route("/{id}", web::put().to(rename))).await;
+
+    let req = test::TestRequest::put()
+        .uri("/3")
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let obj: MyObject = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(obj.name, "test");
+    assert_eq!(obj.number, 3);
+
+    let req = test::TestRequest::put()
+        .uri("/0")
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(
+        body,
+        CborErrorBody::new(StatusCode::NOT_FOUND, "no such object")
+    );
+}
+
+#[cfg(feature = "macros")]
+#[actix_rt::test]
+async fn test_derive_cbor_responder() {
+    #[derive(Serialize, CborResponder)]
+    #[cbor_responder(status = 201, header("X-Api-Version", "2"))]
+    struct Created<T> {
+        value: T,
+    }
+
+    #[derive(Serialize, CborResponder)]
+    struct Plain(u32);
+
+    let req = TestRequest::default().to_http_request();
+    let resp = Created { value: 7 }.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.headers().get("x-api-version").unwrap(), "2");
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, serde_cbor::to_vec(&Created { value: 7 }).unwrap());
+
+    let resp = Plain(3).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, serde_cbor::to_vec(&3).unwrap());
+
+    let resp = Plain(4).respond_cbor(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn test_cbor_responder_macro() {
+    #[derive(Serialize)]
+    struct Order {
+        id: u64,
+    }
+
+    #[derive(Serialize)]
+    struct Empty;
+
+    cbor_responder!(Order, Empty);
+
+    let req = TestRequest::default().to_http_request();
+    let resp = Order { id: 7 }.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, serde_cbor::to_vec(&Order { id: 7 }).unwrap());
+
+    let resp = Empty.respond_cbor(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_transcoding() {
+    let cbor: Cbor<MyObject> = web::Json(MyObject {
+        name: "test".to_owned(),
+        number: 7,
+    })
+    .into();
+    let json: web::Json<MyObject> = cbor.into();
+    assert_eq!(json.name, "test");
+
+    let json = br#"{"name":"test","number":7}"#;
+    let cbor = transcode_json_to_cbor(json).unwrap();
+    assert_eq!(
+        serde_cbor::from_slice::<MyObject>(&cbor).unwrap(),
+        MyObject {
+            name: "test".to_owned(),
+            number: 7,
+        }
+    );
+    assert_eq!(transcode_cbor_to_json(&cbor).unwrap(), json);
+
+    assert!(matches!(
+        transcode_json_to_cbor(b"{"),
+        Err(CborTranscodeError::Json(_))
+    ));
+    assert!(matches!(
+        transcode_cbor_to_json(&[0xa1, 0x80, 0x02]),
+        Err(CborTranscodeError::Json(_))
+    ));
+    assert!(matches!(
+        transcode_cbor_to_json(&[0x82, 0x01]),
+        Err(CborTranscodeError::Cbor(_))
+    ));
+}
+
+#[test]
+fn test_cbor_bytes() {
+    use std::convert::TryFrom;
+
+    let s = Cbor::<MyObject>::from_slice(&get_test_bytes()).unwrap();
+    assert_eq!(s.name, "test");
+    assert_eq!(s.to_vec().unwrap(), get_test_bytes());
+
+    let s = Cbor::<MyObject>::try_from(Bytes::from(get_test_bytes())).unwrap();
+    assert_eq!(s.number, 7);
+
+    assert!(matches!(
+        Cbor::<MyObject>::from_slice(&get_test_bytes()[1..]),
+        Err(CborPayloadError::Deserialize(_))
+    ));
+}
+
+#[actix_rt::test]
+async fn test_manual_extract() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let config = CborConfig::default().limit(4);
+    assert!(Cbor::<MyObject>::extract(&req, &mut pl, &config)
+        .await
+        .is_err());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = Cbor::<MyObject>::extract(&req, &mut pl, &CborConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(s.name, "test");
+}
+
+#[actix_rt::test]
+async fn test_manual_extract_overrides_app_data() {
+    let explicit = CborConfig::default()
+        .tee(true)
+        .messages(|code, lang| match (code, lang) {
+            (CborErrorCode::Overflow, "fr") => Some("Contenu trop volumineux".to_owned()),
+            _ => None,
+        });
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().tee(false))
+        .to_http_parts();
+    Cbor::<MyObject>::extract(&req, &mut pl, &explicit)
+        .await
+        .unwrap();
+    assert_eq!(req.cbor_raw_body().unwrap(), get_test_bytes());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .insert_header((header::ACCEPT_LANGUAGE, "fr"))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().error_content_type(mime::TEXT_PLAIN_UTF_8))
+        .to_http_parts();
+    let err = Cbor::<MyObject>::extract(&req, &mut pl, &explicit.clone().limit(2))
+        .await
+        .unwrap_err();
+    let resp = err.error_response();
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/cbor"))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let body: CborErrorBody = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(body.error, "Contenu trop volumineux");
+}
+
+#[actix_rt::test]
+async fn test_cbor_body_from_bytes() {
+    let body = Bytes::from(get_test_bytes());
+    let s: MyObject = CborBody::from_bytes(body.clone()).await.unwrap();
+    assert_eq!(s.name, "test");
+
+    let err = CborBody::<MyObject>::from_bytes(body.clone())
+        .limit(4)
+        .await
+        .err()
+        .unwrap();
+    assert!(cbor_eq(err, CborPayloadError::Overflow));
+
+    let err = CborBody::<MyObject>::from_bytes(body.slice(1..))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(err, CborPayloadError::Deserialize(_)));
+}
+
+#[actix_rt::test]
+async fn test_cbor_lazy() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let lazy = CborLazy::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(lazy.bytes(), &get_test_bytes());
+    assert_eq!(lazy.parse().unwrap().name, "test");
+
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.number, 7);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes()[1..].to_vec())
+        .to_http_parts();
+    let lazy = CborLazy::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert!(lazy.parse().is_err());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType::json())
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    assert!(CborLazy::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[actix_rt::test]
+async fn test_spool_to_disk() {
+    let config = CborConfig::default().limit(4).spool_to_disk(1024);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(config.clone())
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = CborSpooled::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.name, "test");
+
+    // other extractors keep rejecting payloads over the limit
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(config.clone())
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.error_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(config.clone().deny_unknown_fields(true))
+        .set_payload(serde_cbor::to_vec(&(1, 2)).unwrap())
+        .to_http_parts();
+    assert!(CborSpooled::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(config)
+        .set_payload(serde_cbor::to_vec(&vec![0u8; 2048]).unwrap())
+        .to_http_parts();
+    let err = CborSpooled::<Vec<u8>>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.error_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[actix_rt::test]
+async fn test_cbor_not_send() {
+    #[derive(Deserialize)]
+    struct Local {
+        name: String,
+        #[serde(skip)]
+        _rc: std::rc::Rc<()>,
+    }
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = Cbor::<Local>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.name, "test");
+}
+
+#[actix_rt::test]
+async fn test_cbor_arc() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = CborArc::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let shared = s.clone().into_inner();
+    assert_eq!(s.name, "test");
+    assert_eq!(shared.number, 7);
+    assert_eq!(std::sync::Arc::strong_count(&shared), 2);
+}
+
+#[actix_rt::test]
+async fn test_progress_hook() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let received = Arc::new(AtomicUsize::new(0));
+    let hook = Arc::clone(&received);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::default().on_progress(move |len, _| {
+            hook.store(len, Ordering::SeqCst);
+        }))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(received.load(Ordering::SeqCst), get_test_bytes().len());
+}
+
+#[actix_rt::test]
+async fn test_no_body() {
+    use actix_web::{test, App};
+
+    let req = TestRequest::get().to_http_request();
+    let err = Cbor::<MyObject>::from_request(&req, &mut Payload::None)
+        .await
+        .err()
+        .unwrap();
+    let err = err.as_error::<CborPayloadError>().unwrap();
+    assert!(matches!(err, CborPayloadError::NoBody));
+    assert_eq!(err.code(), CborErrorCode::NoBody);
+
+    let (req, mut pl) = TestRequest::get()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .to_http_parts();
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    let err = err.as_error::<CborPayloadError>().unwrap();
+    assert!(matches!(err, CborPayloadError::NoBody));
+
+    let app = test::init_service(
+        App::new().route("/", web::get().to(|obj: Cbor<MyObject>| async move { obj })),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/")
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn test_sample_log() {
+    use actix_web::{test, App};
+
+    let log = CborSampleLog::every(1).redact_field("name");
+    let value = serde_cbor::Value::Array(vec![
+        serde_cbor::value::to_value(MyObject::default()).unwrap(),
+        serde_cbor::Value::Bytes(vec![1, 255]),
+        serde_cbor::Value::Float(-1.5),
+    ]);
+    assert_eq!(
+        log.render(&serde_cbor::to_vec(&value).unwrap()),
+        r#"[{"name": "<redacted>", "number": 7}, h'01ff', -1.5]"#
+    );
+    assert_eq!(
+        log.clone().max_len(8).render(&get_test_bytes()),
+        r#"{"name":..."#
+    );
+    assert_eq!(
+        log.clone().max_body_size(4).render(&get_test_bytes()),
+        "<19 bytes>"
+    );
+    assert_eq!(log.render(&[0x82, 0x01]), "<2 bytes, not well-formed>");
+
+    let app = test::init_service(App::new().wrap(CborSampleLog::every(2)).route(
+        "/",
+        web::post().to(|obj: Cbor<MyObject>| async move { obj }),
+    ))
+    .await;
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .set_payload(get_test_bytes())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(test::read_body(resp).await, get_test_bytes());
+    }
+}
+
+#[actix_rt::test]
+async fn test_error_content_type() {
+    use actix_web::{test, App};
+
+    const PROBLEM: &str = "application/concise-problem-details+cbor";
+    let app = test::init_service(
+        App::new()
+            .app_data(
+                CborConfig::default()
+                    .error_content_type(PROBLEM.parse().unwrap())
+                    .messages(|_, _| None),
+            )
+            .wrap(cbor_error_handlers())
+            .route(
+                "/",
+                web::post().to(|obj: Cbor<MyObject>| async move { obj }),
+            ),
+    )
+    .await;
+
+    let resp =
+        test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), PROBLEM);
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(body, CborErrorBody::new(StatusCode::NOT_FOUND, "Not Found"));
+
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(ContentType::json())
+        .set_payload(get_test_bytes())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), PROBLEM);
+    let body: CborErrorBody = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(body.code, Some(CborErrorCode::UnsupportedMediaType));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(CborConfig::default().error_content_type(mime::TEXT_PLAIN_UTF_8))
+            .wrap(cbor_error_handlers()),
+    )
+    .await;
+    let resp =
+        test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(test::read_body(resp).await, "Not Found");
+}
+
+#[actix_rt::test]
+async fn test_unit_responder() {
+    let req = TestRequest::default().to_http_request();
+    let resp = Cbor(()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert!(resp.headers().get(header::CONTENT_TYPE).is_none());
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+
+    let resp = Cbor(None::<u32>).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(to_bytes(resp.into_body()).await.unwrap(), [0xf6].as_ref());
+
+    let resp = Cbor(serde_cbor::Value::Null).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(to_bytes(resp.into_body()).await.unwrap(), [0xf6].as_ref());
+}
+
+#[test]
+fn test_accept_cbor() {
+    use actix_web::guard::Guard;
+    use actix_web::http::header::Header;
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/json, application/cbor;q=0.5"))
+        .to_http_request();
+    let accept = AcceptCbor::parse(&req).unwrap();
+    assert_eq!(accept.quality(), header::q(0.5));
+    assert!(accept.accepts_cbor());
+    assert!(!accept.prefers_cbor_over("application", "json"));
+    assert!(accept.prefers_cbor_over("text", "html"));
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/json"))
+        .to_srv_request();
+    assert!(!AcceptCbor::parse(&req).unwrap().accepts_cbor());
+    assert!(!guard::accepts_cbor().check(&req.guard_ctx()));
+
+    let req = TestRequest::default().to_srv_request();
+    assert_eq!(
+        AcceptCbor::parse(&req).unwrap().quality(),
+        header::Quality::MAX
+    );
+    assert!(guard::accepts_cbor().check(&req.guard_ctx()));
+}
+
+#[actix_rt::test]
+async fn test_health_handler() {
+    use actix_web::{test, App};
+
+    let health = health_handler(HealthInfo::new("service", "1.2.3").build("abc"));
+    let app = test::init_service(App::new().route("/health", web::get().to(health))).await;
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/health").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    let report: HealthReport = serde_cbor::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(report.status, "ok");
+    assert_eq!(report.name, "service");
+    assert_eq!(report.version, "1.2.3");
+    assert_eq!(report.build.as_deref(), Some("abc"));
+    assert_eq!(report.uptime, 0);
+}
+
+#[actix_rt::test]
+async fn test_config_presets() {
+    let payload = serde_cbor::to_vec(&BTreeMap::from([
+        ("name", serde_cbor::Value::Text("test".to_owned())),
+        ("number", serde_cbor::Value::Integer(7)),
+        ("extra", serde_cbor::Value::Bool(true)),
+    ]))
+    .unwrap();
+
+    for (config, content_type, ok) in [
+        (CborConfig::strict(), "application/cbor", false),
+        (CborConfig::constrained_device(), "application/cbor", false),
+        (CborConfig::lenient(), "application/cbor", true),
+        (CborConfig::lenient(), "application/octet-stream", true),
+        (CborConfig::lenient(), "application/senml+cbor", true),
+        (CborConfig::strict(), "application/octet-stream", false),
+    ] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .app_data(config)
+            .set_payload(payload.clone())
+            .to_http_parts();
+        let res = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+        assert_eq!(res.is_ok(), ok, "{}", content_type);
+    }
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::constrained_device())
+        .set_payload(serde_cbor::to_vec(&vec![0u8; 2048]).unwrap())
+        .to_http_parts();
+    let err = Cbor::<Vec<u8>>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.error_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // {"name": "te\xff", "number": 7}
+    let mut invalid_utf8 = vec![0xa2, 0x64, b'n', b'a', b'm', b'e', 0x63, b't', b'e', 0xff];
+    invalid_utf8.extend_from_slice(&[0x66, b'n', b'u', b'm', b'b', b'e', b'r', 0x07]);
+    for (config, ok) in [
+        (CborConfig::strict(), false),
+        (CborConfig::constrained_device(), false),
+        (CborConfig::lenient(), true),
+    ] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .app_data(config)
+            .set_payload(invalid_utf8.clone())
+            .to_http_parts();
+        let res = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+        assert_eq!(res.is_ok(), ok);
+    }
+
+    // a malformed body is rejected while received
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::strict())
+        .set_payload(vec![0xff])
+        .to_http_parts();
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    let err = err.as_error::<CborPayloadError>().unwrap();
+    assert!(matches!(err, CborPayloadError::Malformed(0)));
+}
+
+#[actix_rt::test]
+async fn test_metric_label() {
+    use std::sync::{Arc, Mutex};
+
+    let parent = CborConfig::default().metric_label("orders");
+    let labels = Arc::new(Mutex::new(Vec::new()));
+    let hook = Arc::clone(&labels);
+    let config = CborConfig::default()
+        .error_handler(move |err, req| {
+            hook.lock().unwrap().push(req.cbor_metric_label());
+            err.into()
+        })
+        .inherit(&parent);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(config)
+        .set_payload(vec![0xff])
+        .to_http_parts();
+    assert!(Cbor::<MyObject>::from_request(&req, &mut pl).await.is_err());
+    assert_eq!(*labels.lock().unwrap(), vec![Some("orders")]);
+
+    let req = TestRequest::default().to_http_request();
+    assert_eq!(req.cbor_metric_label(), None);
+}
+
+#[actix_rt::test]
+async fn test_cbor_soft() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let soft = CborSoft::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(soft.bytes(), &get_test_bytes());
+    assert_eq!(soft.into_inner().unwrap().name, "test");
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .app_data(CborConfig::default().error_handler(|_, _| unreachable!()))
+        .set_payload(vec![0xa1, 0x61])
+        .to_http_parts();
+    let soft = CborSoft::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let (result, body) = soft.into_parts();
+    assert!(matches!(result, Err(CborPayloadError::Deserialize(_))));
+    assert_eq!(body, web::Bytes::from_static(&[0xa1, 0x61]));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "text/plain"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let soft = CborSoft::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert!(matches!(soft.result(), Err(CborPayloadError::ContentType)));
+    assert!(soft.bytes().is_empty());
+}
+
+#[actix_rt::test]
+async fn test_cbor_meta() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let meta = CborMeta::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(meta.name, "test");
+    assert_eq!(
+        meta.content_type().unwrap().essence_str(),
+        "application/cbor"
+    );
+    assert_eq!(meta.len(), get_test_bytes().len());
+    assert_eq!(meta.hash(), crate::etag::hash_bytes(&get_test_bytes()));
+    assert_eq!(meta.into_inner().number, 7);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(vec![0xa1, 0x61])
+        .to_http_parts();
+    assert!(CborMeta::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[actix_rt::test]
+async fn test_lossy_utf8() {
+    // {"name": "te\xffst", "number": 7}, then with "name" as an indefinite string
+    let definite = b"\xa2\x64name\x65te\xffst\x66number\x07".to_vec();
+    let indefinite = b"\xa2\x64name\x7f\x62te\x63\xffst\xff\x66number\x07".to_vec();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(definite.clone())
+        .to_http_parts();
+    assert!(Cbor::<MyObject>::from_request(&req, &mut pl).await.is_err());
+
+    for (payload, strict) in [(definite, false), (indefinite, true)] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .app_data(
+                CborConfig::default()
+                    .lossy_utf8(true)
+                    .deny_unknown_fields(strict),
+            )
+            .set_payload(payload)
+            .to_http_parts();
+        let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.name, "te\u{fffd}st");
+        assert_eq!(s.number, 7);
+    }
+}
+
+#[cfg(feature = "compress")]
+#[actix_rt::test]
+async fn test_compress_above() {
+    let config = CborConfig::default().compress_above(64);
+    let small = MyObject {
+        name: "test".to_owned(),
+        number: 7,
+    };
+    let large = MyObject {
+        name: "test".repeat(100),
+        number: 7,
+    };
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT_ENCODING, "gzip"))
+        .app_data(config.clone())
+        .to_http_request();
+    let res = Cbor(small).respond_to(&req);
+    assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-encoding");
+    let res = Cbor(large.clone()).respond_to(&req);
+    assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+    assert_eq!(res.headers().get_all(header::VARY).count(), 1);
+    let body = to_bytes(res.into_body()).await.unwrap();
+    assert!(body.len() < 400);
+    assert_eq!(&body[..2], &[0x1f, 0x8b]);
+
+    let req = TestRequest::default()
+        .app_data(config.clone())
+        .to_http_request();
+    let res = Cbor(large.clone()).respond_to(&req);
+    assert!(!res.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(res.headers().get(header::VARY).unwrap(), "accept-encoding");
+
+    // compressed bytes differ, and so does their tag
+    let config = config.etag(true);
+    let req = TestRequest::default()
+        .app_data(config.clone())
+        .to_http_request();
+    let res = Cbor(large.clone()).respond_to(&req);
+    let identity = res
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT_ENCODING, "gzip"))
+        .app_data(config.clone())
+        .to_http_request();
+    let res = Cbor(large.clone()).respond_to(&req);
+    let gzip = res
+        .headers()
+        .get(header::ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(gzip, format!("{}-gzip\"", identity.trim_end_matches('"')));
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT_ENCODING, "gzip"))
+        .insert_header((header::IF_NONE_MATCH, gzip.as_str()))
+        .app_data(config)
+        .to_http_request();
+    let res = Cbor(large.clone()).respond_to(&req);
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MATCH, gzip.as_str()))
+        .to_http_request();
+    let precondition = CborIfMatch::extract(&req).await.unwrap();
+    assert!(precondition.check(&large).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_cbor_multi_doc() {
+    let mut envelope = BTreeMap::new();
+    envelope.insert(
+        serde_cbor::Value::Text("object".to_owned()),
+        serde_cbor::Value::Bytes(get_test_bytes().to_vec()),
+    );
+    envelope.insert(
+        serde_cbor::Value::Text("blob".to_owned()),
+        serde_cbor::Value::Bytes(vec![1, 2, 3]),
+    );
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(serde_cbor::to_vec(&envelope).unwrap())
+        .to_http_parts();
+    let docs = CborMultiDoc::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs.get("blob").unwrap().as_ref(), &[1, 2, 3]);
+    let object: MyObject = docs.decode("object").unwrap().unwrap();
+    assert_eq!(object.name, "test");
+    assert!(docs.decode::<MyObject>("blob").unwrap().is_err());
+    assert!(docs.decode::<MyObject>("missing").is_none());
+    assert_eq!(
+        docs.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        vec!["blob", "object"]
+    );
+
+    // {"blob": (_ h'0102' h'03')}
+    let mut chunked = vec![0xa1, 0x64];
+    chunked.extend_from_slice(b"blob");
+    chunked.extend_from_slice(&[0x5f, 0x42, 1, 2, 0x41, 3, 0xff]);
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(chunked)
+        .to_http_parts();
+    let docs = CborMultiDoc::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(docs.get("blob").unwrap().as_ref(), &[1, 2, 3]);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    assert!(CborMultiDoc::from_request(&req, &mut pl).await.is_err());
+}
+
+#[actix_rt::test]
+async fn test_cbor_well_formed() {
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let body = CborWellFormed::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(body.into_inner(), get_test_bytes());
+
+    for (payload, offset) in [
+        (vec![0xa1, 0x61, 0x61], 3),
+        (vec![0x01, 0x02], 1),
+        (vec![0x1c], 0),
+    ] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/cbor"))
+            .set_payload(payload)
+            .to_http_parts();
+        let err = CborWellFormed::from_request(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        let err = err.as_error::<CborPayloadError>().unwrap();
+        assert!(matches!(err, CborPayloadError::Malformed(o) if *o == offset));
+    }
+}
+
+#[cfg(feature = "json")]
+#[actix_rt::test]
+async fn test_transcode_streams() {
+    use actix_web::error::PayloadError;
+    use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+
+    fn bytewise(body: &[u8]) -> impl Stream<Item = Result<web::Bytes, PayloadError>> {
+        let chunks = body.iter().map(|b| Ok(web::Bytes::copy_from_slice(&[*b])));
+        stream::iter(chunks.collect::<Vec<_>>())
+    }
+
+    async fn concat(body: impl Stream<Item = Result<web::Bytes, CborTranscodeError>>) -> Vec<u8> {
+        body.try_fold(Vec::new(), |mut all, chunk| async move {
+            all.extend_from_slice(&chunk);
+            Ok(all)
+        })
+        .await
+        .unwrap()
+    }
+
+    let json = concat(transcode_cbor_stream_to_json(bytewise(&get_test_bytes()))).await;
+    assert_eq!(json, br#"{"name":"test","number":7}"#);
+
+    let document =
+        r#" {"name": "te\"st\u00e9", "list": [1, -2, 1.5e3, true, null, [], {}], "n": 42} "#;
+    let cbor = concat(transcode_json_stream_to_cbor(bytewise(document.as_bytes()))).await;
+    let value: serde_json::Value = serde_cbor::from_slice(&cbor).unwrap();
+    assert_eq!(
+        value,
+        serde_json::from_str::<serde_json::Value>(document).unwrap()
+    );
+
+    // back to JSON, from the indefinite-length containers
+    let json = concat(transcode_cbor_stream_to_json(bytewise(&cbor))).await;
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+        value
+    );
+
+    for json in [
+        &br#"{"name": "te"#[..],
+        br#"[1, 2"#,
+        br#"[1,]"#,
+        br#"{} {}"#,
+    ] {
+        let last = transcode_json_stream_to_cbor(bytewise(json))
+            .collect::<Vec<_>>()
+            .await
+            .pop()
+            .unwrap();
+        assert!(matches!(last, Err(CborTranscodeError::Json(_))));
+    }
+    let last = transcode_cbor_stream_to_json(bytewise(&get_test_bytes()[..10]))
+        .collect::<Vec<_>>()
+        .await
+        .pop()
+        .unwrap();
+    assert!(matches!(last, Err(CborTranscodeError::Cbor(_))));
+
+    let failing = stream::iter(vec![Err(PayloadError::Incomplete(None))]);
+    let last = transcode_cbor_stream_to_json(failing)
+        .collect::<Vec<_>>()
+        .await
+        .pop()
+        .unwrap();
+    assert!(matches!(last, Err(CborTranscodeError::Payload(_))));
+}
+
+#[cfg(feature = "tags")]
+#[actix_rt::test]
+async fn test_preserve_tags() {
+    use serde_cbor::Value;
+
+    // {"at": 1(1600000000), "id": 37(h'00')}
+    let payload = b"\xa2\x62at\xc1\x1a\x5f\x5e\x10\x00\x62id\xd8\x25\x41\x00".to_vec();
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(payload.clone())
+        .to_http_parts();
+    let value = Cbor::<Value>::from_request(&req, &mut pl).await.unwrap();
+    match &*value {
+        Value::Map(map) => assert_eq!(
+            map.get(&Value::Text("id".to_owned())),
+            Some(&Value::Tag(37, Box::new(Value::Bytes(vec![0]))))
+        ),
+        value => panic!("unexpected {:?}", value),
+    }
+
+    let res = value.respond_to(&req);
+    let body = to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(body, payload);
+}
+
+#[cfg(feature = "tags")]
+#[actix_rt::test]
+async fn test_typed_extraction_ignores_tags() {
+    #[derive(Deserialize, Debug)]
+    struct Event {
+        at: u64,
+        note: Option<String>,
+    }
+
+    // 55799({"at": 1(1600000000), "note": 32("x")})
+    let payload = b"\xd9\xd9\xf7\xa2\x62at\xc1\x1a\x5f\x5e\x10\x00\x64note\xd8\x20\x61x".to_vec();
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(payload)
+        .to_http_parts();
+    let event = Cbor::<Event>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(event.at, 1_600_000_000);
+    assert_eq!(event.note.as_deref(), Some("x"));
+}
+
+#[cfg(feature = "tags")]
+#[actix_rt::test]
+async fn test_bignum() {
+    use serde_cbor::Value;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Transfer {
+        #[serde(with = "bignum")]
+        amount: i128,
+        #[serde(with = "bignum")]
+        balance: u128,
+    }
+
+    // plain integers within the 64-bit range
+    let small = Transfer {
+        amount: -5,
+        balance: 7,
+    };
+    let value: BTreeMap<String, Value> =
+        serde_cbor::from_slice(&serde_cbor::to_vec(&small).unwrap()).unwrap();
+    assert_eq!(value["amount"], Value::Integer(-5));
+    assert_eq!(value["balance"], Value::Integer(7));
+
+    let large = Transfer {
+        amount: i128::MIN,
+        balance: u128::MAX,
+    };
+    let bytes = serde_cbor::to_vec(&large).unwrap();
+    let value: BTreeMap<String, Value> = serde_cbor::from_slice(&bytes).unwrap();
+    let mut magnitude = vec![0x7f];
+    magnitude.extend([0xff; 15]);
+    assert_eq!(
+        value["amount"],
+        Value::Tag(3, Box::new(Value::Bytes(magnitude)))
+    );
+    assert_eq!(
+        value["balance"],
+        Value::Tag(2, Box::new(Value::Bytes(vec![0xff; 16])))
+    );
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(bytes)
+        .to_http_parts();
+    let s = Cbor::<Transfer>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), large);
+
+    // fields without the helper still ignore tags
+    #[derive(Deserialize)]
+    struct Entry {
+        #[serde(with = "bignum")]
+        amount: i128,
+        at: u64,
+    }
+    let mut entry = BTreeMap::new();
+    entry.insert("amount", Value::Tag(2, Box::new(Value::Bytes(vec![1; 9]))));
+    entry.insert("at", Value::Tag(1, Box::new(Value::Integer(1_600_000_000))));
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(serde_cbor::to_vec(&entry).unwrap())
+        .to_http_parts();
+    let entry = Cbor::<Entry>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(entry.amount, 0x010101010101010101);
+    assert_eq!(entry.at, 1_600_000_000);
+
+    // negative bignums do not fit in u128
+    let negative = serde_cbor::to_vec(&Value::Tag(3, Box::new(Value::Bytes(vec![0x01])))).unwrap();
+    let err = bignum::deserialize::<u128, _>(&mut serde_cbor::Deserializer::from_slice(&negative));
+    assert!(err.is_err());
+}
+
+#[cfg(feature = "tags")]
+#[test]
+fn test_time_tags() {
+    use crate::time_tags::{self, Period};
+    use serde_cbor::Value;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Job {
+        #[serde(with = "time_tags::system_time")]
+        next_run: SystemTime,
+        #[serde(with = "time_tags::duration")]
+        timeout: Duration,
+        window: Period,
+    }
+
+    let job = Job {
+        next_run: UNIX_EPOCH - Duration::from_millis(1500),
+        timeout: Duration::from_millis(1500),
+        window: Period {
+            start: Some(UNIX_EPOCH + Duration::from_secs(60)),
+            end: None,
+            duration: Some(Duration::from_secs(3600)),
+        },
+    };
+    let bytes = serde_cbor::to_vec(&job).unwrap();
+    assert_eq!(serde_cbor::from_slice::<Job>(&bytes).unwrap(), job);
+
+    let value: BTreeMap<String, Value> = serde_cbor::from_slice(&bytes).unwrap();
+    let seconds = |secs: i128, nanos: Option<i128>| {
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(1), Value::Integer(secs));
+        if let Some(nanos) = nanos {
+            map.insert(Value::Integer(-9), Value::Integer(nanos));
+        }
+        Value::Map(map)
+    };
+    assert_eq!(
+        value["next_run"],
+        Value::Tag(1001, Box::new(seconds(-2, Some(500_000_000))))
+    );
+    assert_eq!(
+        value["timeout"],
+        Value::Tag(1002, Box::new(seconds(1, Some(500_000_000))))
+    );
+    assert_eq!(
+        value["window"],
+        Value::Tag(
+            1003,
+            Box::new(Value::Array(vec![
+                seconds(60, None),
+                Value::Null,
+                seconds(3600, None),
+            ]))
+        )
+    );
+
+    // milliseconds, and unknown critical keys
+    let mut map = BTreeMap::new();
+    map.insert(Value::Integer(1), Value::Integer(2));
+    map.insert(Value::Integer(-3), Value::Integer(250));
+    let duration =
+        serde_cbor::to_vec(&Value::Tag(1002, Box::new(Value::Map(map.clone())))).unwrap();
+    let mut de = serde_cbor::Deserializer::from_slice(&duration);
+    assert_eq!(
+        time_tags::duration::deserialize(&mut de).unwrap(),
+        Duration::from_millis(2250)
+    );
+
+    map.insert(Value::Integer(-1), Value::Integer(0));
+    let duration = serde_cbor::to_vec(&Value::Tag(1002, Box::new(Value::Map(map)))).unwrap();
+    let mut de = serde_cbor::Deserializer::from_slice(&duration);
+    assert!(time_tags::duration::deserialize(&mut de).is_err());
+
+    // periods need exactly two of their bounds
+    let open = Period {
+        start: Some(UNIX_EPOCH),
+        end: None,
+        duration: None,
+    };
+    assert!(serde_cbor::to_vec(&open).is_err());
+    let overdetermined = Period {
+        end: Some(UNIX_EPOCH + Duration::from_secs(60)),
+        duration: Some(Duration::from_secs(60)),
+        ..open
+    };
+    assert!(serde_cbor::to_vec(&overdetermined).is_err());
+
+    let periods = [
+        vec![seconds(60, None), Value::Null],
+        vec![Value::Null, Value::Null, seconds(60, None)],
+        vec![seconds(0, None), seconds(60, None), seconds(60, None)],
+    ];
+    for items in periods.iter() {
+        let period = Value::Tag(1003, Box::new(Value::Array(items.clone())));
+        let bytes = serde_cbor::to_vec(&period).unwrap();
+        assert!(serde_cbor::from_slice::<Period>(&bytes).is_err());
+    }
+}
+
+#[cfg(feature = "tags")]
+#[actix_rt::test]
+async fn test_typed_array() {
+    use serde_cbor::Value;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Samples {
+        #[serde(with = "typed_array")]
+        values: Vec<f32>,
+        #[serde(with = "typed_array")]
+        counts: Vec<u16>,
+    }
+
+    let samples = Samples {
+        values: vec![1.5, -2.0],
+        counts: vec![1, 0x0203],
+    };
+    let bytes = serde_cbor::to_vec(&samples).unwrap();
+    let value: BTreeMap<String, Value> = serde_cbor::from_slice(&bytes).unwrap();
+    let mut packed = 1.5f32.to_le_bytes().to_vec();
+    packed.extend((-2.0f32).to_le_bytes());
+    assert_eq!(
+        value["values"],
+        Value::Tag(85, Box::new(Value::Bytes(packed)))
+    );
+    assert_eq!(
+        value["counts"],
+        Value::Tag(69, Box::new(Value::Bytes(vec![1, 0, 3, 2])))
+    );
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(bytes)
+        .to_http_parts();
+    let s = Cbor::<Samples>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), samples);
+
+    // big-endian typed arrays and plain arrays
+    let mut map = BTreeMap::new();
+    map.insert(
+        Value::Text("values".to_owned()),
+        Value::Array(vec![Value::Float(1.5), Value::Float(-2.0)]),
+    );
+    map.insert(
+        Value::Text("counts".to_owned()),
+        Value::Tag(65, Box::new(Value::Bytes(vec![0, 1, 2, 3]))),
+    );
+    let bytes = serde_cbor::to_vec(&Value::Map(map.clone())).unwrap();
+    assert_eq!(serde_cbor::from_slice::<Samples>(&bytes).unwrap(), samples);
+
+    // truncated element, and typed array of another element type
+    for counts in [
+        Value::Tag(69, Box::new(Value::Bytes(vec![1, 0, 3]))),
+        Value::Tag(70, Box::new(Value::Bytes(vec![1, 0, 0, 0]))),
+    ] {
+        map.insert(Value::Text("counts".to_owned()), counts);
+        let bytes = serde_cbor::to_vec(&Value::Map(map.clone())).unwrap();
+        assert!(serde_cbor::from_slice::<Samples>(&bytes).is_err());
+    }
+}
+
+#[actix_rt::test]
+async fn test_cbor_input() {
+    let (req, mut pl) = TestRequest::default()
+        .param("id", "7")
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let input = CborInput::<(web::Path<u64>, Cbor<MyObject>)>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    let (id, obj) = input.into_inner();
+    assert_eq!(id.into_inner(), 7);
+    assert_eq!(obj.into_inner(), MyObject::default());
+
+    let (req, mut pl) = TestRequest::default()
+        .param("id", "seven")
+        .insert_header((header::CONTENT_TYPE, "text/plain"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let err = CborInput::<(web::Path<u64>, Cbor<MyObject>)>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    let failures = err.failures();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].index, 0);
+    assert_eq!(failures[0].component, "Path<u64>");
+    assert_eq!(failures[1].component, "Cbor<MyObject>");
+    assert_eq!(failures[1].code, Some(CborErrorCode::UnsupportedMediaType));
+
+    let resp = actix_web::ResponseError::error_response(&err);
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let body: CborInputErrorBody = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(body.status, 400);
+    assert_eq!(body.errors, failures);
+}
+
+#[actix_rt::test]
+async fn test_cbor_patch() {
+    let mut patch = BTreeMap::new();
+    patch.insert("number", 9);
+    let body = serde_cbor::to_vec(&patch).unwrap();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, MERGE_PATCH_CBOR_CONTENT_TYPE))
+        .set_payload(body.clone())
+        .to_http_parts();
+    let patch = CborPatch::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+
+    let mut obj = MyObject::default();
+    patch.apply_to(&mut obj).unwrap();
+    assert_eq!(obj.name, "test");
+    assert_eq!(obj.number, 9);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body)
+        .to_http_parts();
+    assert!(CborPatch::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_merge_patch_diff() {
+    use serde_cbor::Value;
+
+    let text = |s: &str| Value::Text(s.to_owned());
+    let map = |entries: Vec<(&str, Value)>| {
+        Value::Map(entries.into_iter().map(|(k, v)| (text(k), v)).collect())
+    };
+
+    let original = map(vec![
+        ("name", text("test")),
+        (
+            "tags",
+            map(vec![("a", Value::Integer(1)), ("b", Value::Integer(2))]),
+        ),
+        ("old", Value::Bool(true)),
+    ]);
+    let updated = map(vec![
+        ("name", text("test")),
+        (
+            "tags",
+            map(vec![("a", Value::Integer(1)), ("c", Value::Integer(3))]),
+        ),
+        ("new", Value::Bool(false)),
+    ]);
+
+    let patch = diff_merge_patch(&original, &updated);
+    assert_eq!(
+        patch,
+        map(vec![
+            (
+                "tags",
+                map(vec![("b", Value::Null), ("c", Value::Integer(3))])
+            ),
+            ("old", Value::Null),
+            ("new", Value::Bool(false)),
+        ])
+    );
+
+    let mut patched = original;
+    apply_merge_patch(&mut patched, &patch);
+    assert_eq!(patched, updated);
+}
+
+#[actix_rt::test]
+async fn test_accept_patch() {
+    use actix_web::{test, App};
+
+    async fn update(patch: CborPatch<MyObject>) -> HttpResponse {
+        match patch.apply(&MyObject::default()) {
+            Ok(obj) => HttpResponse::Ok().body(obj.name),
+            Err(e) => HttpResponse::BadRequest().body(e.to_string()),
+        }
+    }
+
+    let app = test::init_service(
+        App::new().service(
+            web::resource("/")
+                .wrap(AcceptPatch)
+                .route(web::patch().to(update))
+                .route(web::get().to(HttpResponse::NotFound)),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::patch()
+        .insert_header(ContentType::json())
+        .set_payload("{}")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        resp.headers().get(ACCEPT_PATCH).unwrap(),
+        MERGE_PATCH_CBOR_CONTENT_TYPE
+    );
+
+    let req = test::TestRequest::patch()
+        .insert_header((header::CONTENT_TYPE, MERGE_PATCH_CBOR_CONTENT_TYPE))
+        .set_payload(serde_cbor::to_vec(&BTreeMap::<String, i32>::new()).unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().contains_key(ACCEPT_PATCH));
+
+    let resp = test::call_service(&app, test::TestRequest::get().to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert!(!resp.headers().contains_key(ACCEPT_PATCH));
+}
+
+#[actix_rt::test]
+async fn test_cbor_if_match() {
+    use actix_web::http::header::EntityTag;
+
+    let etag = cbor_etag(&MyObject::default()).unwrap();
+
+    let req = TestRequest::default().to_http_request();
+    let precondition = CborIfMatch::extract(&req).await.unwrap();
+    assert_eq!(precondition.check(&MyObject::default()).unwrap(), etag);
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MATCH, etag.to_string()))
+        .to_http_request();
+    let precondition = CborIfMatch::extract(&req).await.unwrap();
+    assert!(precondition.check(&MyObject::default()).is_ok());
+
+    let stale = MyObject {
+        number: 8,
+        ..MyObject::default()
+    };
+    let resp = precondition.check(&stale).unwrap_err().error_response();
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+        cbor_etag(&stale).unwrap().to_string()
+    );
+    assert!(!precondition.matches(&EntityTag::new_weak(etag.tag().to_owned())));
+
+    // the tag of a GET satisfies If-Match, whatever the order of the fields
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+        id: u32,
+    }
+    let user = || User {
+        name: "John".to_owned(),
+        id: 1,
+    };
+
+    let req = TestRequest::default().to_http_request();
+    let resp = Cbor(user()).with_etag().respond_to(&req);
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MATCH, etag))
+        .to_http_request();
+    let precondition = CborIfMatch::extract(&req).await.unwrap();
+    assert!(precondition.check(&user()).is_ok());
+
+    // so does the tag of a negotiated profile
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().etag(true))
+        .insert_header((header::ACCEPT, "application/cbor;profile=packed"))
+        .to_http_request();
+    let resp = Cbor(user()).respond_to(&req);
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+    assert_eq!(
+        etag.to_str().unwrap(),
+        format!("\"{}-packed\"", cbor_etag(&user()).unwrap().tag())
+    );
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MATCH, etag))
+        .to_http_request();
+    let precondition = CborIfMatch::extract(&req).await.unwrap();
+    assert!(precondition.check(&user()).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_cbor_etag() {
+    let req = TestRequest::default().to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert!(resp.headers().get(header::ETAG).is_none());
+
+    let tagged = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    let etag = tagged.headers().get(header::ETAG).unwrap().clone();
+    assert!(etag.to_str().unwrap().starts_with('"'));
+
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().etag(true))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+
+    let resp = HttpResponse::Ok().cbor_with_etag(&MyObject::default());
+    assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+
+    // the tag does not depend on the iteration order of maps
+    let map = |keys: &mut dyn Iterator<Item = u32>| {
+        keys.map(|k| (k.to_string(), k))
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+    let forward = map(&mut (0..64));
+    let backward = map(&mut (0..64).rev());
+    assert_eq!(cbor_etag(&forward).unwrap(), cbor_etag(&backward).unwrap());
+    let resp = Cbor(backward).with_etag().respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+        cbor_etag(&forward).unwrap().to_string()
+    );
+}
+
+#[cfg(feature = "xxhash")]
+#[test]
+fn test_cbor_etag_xxh3() {
+    let etag = cbor_etag(&MyObject::default()).unwrap();
+    assert_eq!(
+        etag.tag(),
+        format!("{:016x}", xxhash_rust::xxh3::xxh3_64(&get_test_bytes()))
+    );
+    assert_eq!(etag.tag(), "ab5b1f3a01a6f020");
+}
+
+#[actix_rt::test]
+async fn test_cbor_not_modified() {
+    let req = TestRequest::default().to_http_request();
+    let resp = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_NONE_MATCH, etag.clone()))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(resp.headers().get(header::ETAG), Some(&etag));
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_NONE_MATCH, "\"other\""))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = TestRequest::post()
+        .insert_header((header::IF_NONE_MATCH, etag))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn test_cbor_last_modified() {
+    use actix_web::http::header::HttpDate;
+    use std::time::{Duration, SystemTime};
+
+    let modified = SystemTime::now();
+    let since = HttpDate::from(modified).to_string();
+    let earlier = HttpDate::from(modified - Duration::from_secs(60)).to_string();
+
+    let req = TestRequest::default().to_http_request();
+    let resp = CborLastModified::new(MyObject::default(), modified).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::LAST_MODIFIED).unwrap(),
+        &since[..]
+    );
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MODIFIED_SINCE, since))
+        .to_http_request();
+    let resp = CborLastModified::new(MyObject::default(), modified).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_MODIFIED_SINCE, earlier))
+        .to_http_request();
+    let resp = CborLastModified::new(MyObject::default(), modified).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn test_cbor_cached() {
+    use std::time::Duration;
+
+    let req = TestRequest::default().to_http_request();
+    let resp = CborCached::new(MyObject::default())
+        .public()
+        .max_age(Duration::from_secs(60))
+        .expires(std::time::UNIX_EPOCH)
+        .respond_to(&req);
+
+    assert_eq!(
+        resp.headers().get(header::CACHE_CONTROL).unwrap(),
+        "public, max-age=60"
+    );
+    assert_eq!(
+        resp.headers().get(header::EXPIRES).unwrap(),
+        "Thu, 01 Jan 1970 00:00:00 GMT"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+}
+
+#[actix_rt::test]
+async fn test_cbor_shared() {
+    let shared = CborShared::new(&MyObject::default()).unwrap();
+    assert_eq!(&shared.bytes()[..], &get_test_bytes()[..]);
+
+    let req = TestRequest::default().to_http_request();
+    for _ in 0..2 {
+        let resp = shared.clone().respond_to(&req);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/cbor"
+        );
+        let body = to_bytes(resp.into_body()).await.unwrap();
+        assert_eq!(&body[..], &get_test_bytes()[..]);
+    }
+
+    let etag = cbor_etag(&MyObject::default()).unwrap();
+    assert_eq!(shared.etag(), &etag);
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().etag(true))
+        .to_http_request();
+    let resp = shared.clone().respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::ETAG).unwrap().to_str().unwrap(),
+        etag.to_string()
+    );
+}
+
+#[actix_rt::test]
+async fn test_cbor_preencoded() {
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().etag(true))
+        .to_http_request();
+    let resp = CborPreencoded(get_test_bytes().into()).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    assert!(resp.headers().contains_key(header::ETAG));
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+}
+
+#[actix_rt::test]
+async fn test_cbor_memo() {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    let memo = CborMemo::new(Duration::from_secs(60));
+    let calls = Cell::new(0);
+    let produce = || async {
+        calls.set(calls.get() + 1);
+        MyObject::default()
+    };
+
+    let first = memo.get_or_insert_with("obj", produce).await.unwrap();
+    let second = memo.get_or_insert_with("obj", produce).await.unwrap();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(first.etag(), second.etag());
+    assert_eq!(&second.bytes()[..], &get_test_bytes()[..]);
+
+    // entries are keyed by type as well
+    assert!(memo.get::<String>("obj").is_none());
+
+    memo.invalidate::<MyObject>("obj");
+    memo.get_or_insert_with("obj", produce).await.unwrap();
+    assert_eq!(calls.get(), 2);
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_NONE_MATCH, first.etag().to_string()))
+        .to_http_request();
+    let resp = second.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    let expired = CborMemo::new(Duration::from_secs(0));
+    expired.insert("obj", &MyObject::default()).unwrap();
+    assert!(expired.get::<MyObject>("obj").is_none());
+
+    // a full cache evicts the entry closest to expiring
+    let bounded = CborMemo::new(Duration::from_secs(60)).max_entries(2);
+    for key in ["a", "b", "c"] {
+        bounded.insert(key, &MyObject::default()).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(bounded.get::<MyObject>("a").is_none());
+    assert!(bounded.get::<MyObject>("b").is_some());
+    assert!(bounded.get::<MyObject>("c").is_some());
+
+    let disabled = CborMemo::new(Duration::from_secs(60)).max_entries(0);
+    assert!(disabled.insert("obj", &MyObject::default()).is_ok());
+    assert!(disabled.get::<MyObject>("obj").is_none());
+}
+
+#[actix_rt::test]
+async fn test_named_cbor_file() {
+    let dir = std::env::temp_dir().join(format!("actix-cbor-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("object.cbor");
+    std::fs::write(&path, get_test_bytes()).unwrap();
+
+    let req = TestRequest::default().to_http_request();
+    let resp = NamedCborFile::open(&path).await.unwrap().respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    assert!(resp.headers().contains_key(header::LAST_MODIFIED));
+    let etag = resp.headers().get(header::ETAG).unwrap().clone();
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+
+    let req = TestRequest::default()
+        .insert_header((header::IF_NONE_MATCH, etag))
+        .to_http_request();
+    let resp = NamedCborFile::open_validated(&path)
+        .await
+        .unwrap()
+        .respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+    // validated files are streamed from the start once scanned
+    let req = TestRequest::default().to_http_request();
+    let resp = NamedCborFile::open_validated(&path)
+        .await
+        .unwrap()
+        .respond_to(&req);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+
+    // an item spanning several chunks
+    let large = dir.join("large.cbor");
+    let bytes = serde_cbor::to_vec(&vec![7u8; 200_000]).unwrap();
+    std::fs::write(&large, &bytes).unwrap();
+    let resp = NamedCborFile::open_validated(&large)
+        .await
+        .unwrap()
+        .respond_to(&req);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &bytes[..]);
+
+    let trailing = dir.join("trailing.cbor");
+    std::fs::write(&trailing, [&get_test_bytes()[..], &[0]].concat()).unwrap();
+    let err = NamedCborFile::open_validated(&trailing)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err
+        .to_string()
+        .ends_with(&format!("at byte {}", get_test_bytes().len())));
+
+    let truncated = dir.join("truncated.cbor");
+    let bytes = get_test_bytes();
+    std::fs::write(&truncated, &bytes[..bytes.len() - 1]).unwrap();
+    assert!(NamedCborFile::open(&truncated).await.is_ok());
+    let err = NamedCborFile::open_validated(&truncated)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_cbor_page() {
+    let req = TestRequest::default().to_http_request();
+    let resp = CborPage::new(vec![MyObject::default()])
+        .total(3)
+        .next("/objects?page=2")
+        .respond_to(&req);
+
+    assert_eq!(
+        resp.headers().get(header::LINK).unwrap(),
+        "</objects?page=2>; rel=\"next\""
+    );
+    assert_eq!(resp.headers().get(X_TOTAL_COUNT).unwrap(), "3");
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let page: BTreeMap<String, serde_cbor::Value> = serde_cbor::from_slice(&body).unwrap();
+    assert_eq!(page["total"], serde_cbor::Value::Integer(3));
+    assert!(!page.contains_key("prev"));
+    assert_eq!(
+        page["items"],
+        serde_cbor::value::to_value(vec![MyObject::default()]).unwrap()
+    );
+}
+
+#[actix_rt::test]
+async fn test_cbor_head() {
+    let len = get_test_bytes().len().to_string();
+
+    let req = TestRequest::default()
+        .method(actix_web::http::Method::HEAD)
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+    assert_eq!(
+        resp.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &len[..]
+    );
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+
+    let resp = Cbor(MyObject::default()).with_etag().respond_to(&req);
+    assert!(resp.headers().contains_key(header::ETAG));
+    assert_eq!(
+        resp.headers().get(header::CONTENT_LENGTH).unwrap(),
+        &len[..]
+    );
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn test_cbor_return_minimal() {
+    let config = CborConfig::default().return_minimal(true);
+
+    let req = TestRequest::post()
+        .app_data(config.clone())
+        .insert_header((PREFER, "respond-async, return=minimal; foo=bar"))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        resp.headers().get(PREFERENCE_APPLIED).unwrap(),
+        "return=minimal"
+    );
+
+    let req = TestRequest::post()
+        .app_data(config)
+        .insert_header((PREFER, "return=representation"))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = TestRequest::post()
+        .insert_header((PREFER, "return=minimal"))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+async fn test_cbor_status_helpers() {
+    let req = TestRequest::post().to_http_request();
+
+    let resp = CborCreated::new(MyObject::default())
+        .location("/objects/test")
+        .respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(
+        resp.headers().get(header::LOCATION).unwrap(),
+        "/objects/test"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+
+    let resp = CborAccepted(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+    let resp = CborNoContent.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_rt::test]
+async fn test_cbor_with_status() {
+    let req = TestRequest::default().to_http_request();
+    let resp = (Cbor(MyObject::default()), StatusCode::CREATED).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/cbor"
+    );
+
+    let resp = (Cbor(MyObject::default()), StatusCode::CREATED)
+        .customize()
+        .insert_header(("x-version", "1.2.3"))
+        .respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(resp.headers().get("x-version").unwrap(), "1.2.3");
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], &get_test_bytes()[..]);
+}
+
+#[actix_rt::test]
+async fn test_try_cbor() {
+    let req = TestRequest::default().to_http_request();
+    let resp = TryCbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = TryCbor(Failing).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("unsupported"));
+}
+
+#[actix_rt::test]
+async fn test_try_cbor_builder() {
+    let resp = HttpResponse::Created()
+        .try_cbor(&MyObject::default())
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/cbor"))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, get_test_bytes());
+
+    let err = HttpResponse::Ok().try_cbor(&Failing).unwrap_err();
+    assert!(matches!(err, CborPayloadError::Serialize(_)));
+    assert_eq!(
+        actix_web::ResponseError::error_response(&err).status(),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+}
+
+#[actix_rt::test]
+async fn test_cbor_stream_builder() {
+    let items = futures_util::stream::iter(vec![MyObject::default(), MyObject::default()]);
+    let resp = HttpResponse::Ok()
+        .insert_header(("x-export", "catalog"))
+        .cbor_stream(items);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static(CBOR_SEQ_CONTENT_TYPE))
+    );
+    assert!(resp.headers().contains_key("x-export"));
+
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body, [get_test_bytes(), get_test_bytes()].concat());
+}
+
+#[actix_rt::test]
+async fn test_cbor_canonical_and_packed_builder() {
+    let mut map = BTreeMap::new();
+    map.insert("bb".to_owned(), 1);
+    map.insert("c".to_owned(), 2);
+
+    let resp = HttpResponse::Ok().cbor_canonical(&map);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE).unwrap(),
+        CborProfile::Canonical.content_type()
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    // length-first ordering puts the shorter key first
+    assert_eq!(
+        body,
+        [&[0xa2, 0x61, b'c', 0x02, 0x62][..], b"bb", &[0x01]].concat()
+    );
+
+    let resp = HttpResponse::Ok().cbor_packed(&MyObject::default());
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static(
+            "application/cbor; profile=packed"
+        ))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(
+        body,
+        serde_cbor::ser::to_vec_packed(&MyObject::default()).unwrap()
+    );
+    assert!(body.len() < get_test_bytes().len());
+}
+
+#[actix_rt::test]
+async fn test_http_request_ext() {
+    let req = TestRequest::default().to_http_request();
+    assert!(req.accepts_cbor());
+    assert!(!req.prefers_cbor_over_json());
+    assert!(!req.content_type_is_cbor());
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/json, application/cbor;q=0.5"))
+        .insert_header(ContentType(mime::APPLICATION_JSON))
+        .to_http_request();
+    assert!(req.accepts_cbor());
+    assert!(!req.prefers_cbor_over_json());
+    assert!(!req.content_type_is_cbor());
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/*, application/cbor;q=0"))
+        .to_http_request();
+    assert!(!req.accepts_cbor());
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/cbor, application/json"))
+        .insert_header((header::CONTENT_TYPE, "application/cbor"))
+        .to_http_request();
+    assert!(req.prefers_cbor_over_json());
+    assert!(req.content_type_is_cbor());
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/*"))
+        .to_http_request();
+    assert!(req.accepts_cbor());
+    assert!(!req.prefers_cbor_over_json());
+}
+
+#[actix_rt::test]
+async fn test_cbor_guard() {
+    use actix_web::guard::Guard;
+
+    let req = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/cbor; charset=binary"))
+        .to_srv_request();
+    assert!(guard::cbor().check(&req.guard_ctx()));
+
+    let req = TestRequest::default()
+        .insert_header(ContentType(mime::APPLICATION_JSON))
+        .to_srv_request();
+    assert!(!guard::cbor().check(&req.guard_ctx()));
+
+    let req = TestRequest::default().to_srv_request();
+    assert!(!guard::cbor().check(&req.guard_ctx()));
+
+    let req = TestRequest::default()
+        .insert_header(ContentType(mime::TEXT_PLAIN))
+        .app_data(CborConfig::default().content_type_raw(|mime: &str| mime == "text/plain"))
+        .to_srv_request();
+    assert!(guard::cbor().check(&req.guard_ctx()));
+}
+
+#[actix_rt::test]
+async fn test_response_limit() {
+    let len = get_test_bytes().len();
+
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().response_limit(len))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = TestRequest::default()
+        .app_data(CborConfig::default().response_limit(len - 1))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let resp = TryCbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let req = TestRequest::default()
+        .method(actix_web::http::Method::HEAD)
+        .app_data(CborConfig::default().response_limit(len - 1))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let req = TestRequest::default()
+        .app_data(
+            CborConfig::default()
+                .response_limit(len - 1)
+                .error_handler(|err, _| {
+                    assert!(matches!(err, CborPayloadError::ResponseOverflow));
+                    InternalError::from_response(err, HttpResponse::InsufficientStorage().finish())
+                        .into()
+                }),
+        )
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INSUFFICIENT_STORAGE);
+    let resp = TryCbor(MyObject::default()).respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::INSUFFICIENT_STORAGE);
+}
+
+#[actix_rt::test]
+async fn test_method_limit() {
+    use actix_web::http::Method;
+
+    let config = CborConfig::default()
+        .limit(10)
+        .method_limit(Method::PUT, 1024);
+
+    let (req, mut pl) = TestRequest::default()
+        .method(Method::POST)
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(config.clone())
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+
+    let (req, mut pl) = TestRequest::default()
+        .method(Method::PUT)
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(config.method_limit(Method::PUT, 1024))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_config_merge() {
+    let app_config = CborConfig::default().limit(10).error_handler(|err, _| {
+        InternalError::from_response(err, HttpResponse::Conflict().finish()).into()
+    });
+
+    // the route config only changes the limit and keeps the app error handler
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(web::Data::new(app_config.clone()))
+        .app_data(CborConfig::default().limit(4))
+        .to_http_parts();
+    let err = Cbor::<MyObject>::from_request(&req, &mut pl)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.error_response().status(), StatusCode::CONFLICT);
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().limit(1024).inherit(&app_config))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_config_layer() {
+    use actix_web::{test, App};
+
+    let limit = get_test_bytes().len();
+    let app = test::init_service(
+        App::new()
+            .app_data(CborConfig::default().limit(limit))
+            .service(
+                web::scope("/scope")
+                    .wrap(CborConfigLayer::new(
+                        CborConfig::default().content_type_raw(|ct| ct == "text/plain"),
+                    ))
+                    .route(
+                        "/item",
+                        web::post().to(|item: Cbor<MyObject>| async move { item }),
+                    ),
+            )
+            .route(
+                "/item",
+                web::post().to(|item: Cbor<MyObject>| async move { item }),
+            ),
+    )
+    .await;
+    let post = |uri: &'static str, content_type: &'static str, body: Vec<u8>| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .to_request()
+    };
+
+    // the content type of the scope applies...
+    let resp = test::call_service(&app, post("/scope/item", "text/plain", get_test_bytes())).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, post("/item", "text/plain", get_test_bytes())).await;
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    // ...along with the limit of the app
+    let large = MyObject {
+        name: "a longer name".to_owned(),
+        ..MyObject::default()
+    };
+    let body = serde_cbor::to_vec(&large).unwrap();
+    let resp = test::call_service(&app, post("/scope/item", "text/plain", body)).await;
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[test]
+fn test_config_from_env() {
+    let vars = |vars: &'static [(&'static str, &'static str)]| {
+        move |var: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+
+    let config = CborConfig::from_lookup(vars(&[
+        ("ACTIX_CBOR_LIMIT", "2MiB"),
+        ("ACTIX_CBOR_ETAG", "On"),
+    ]))
+    .unwrap();
+    assert_eq!(config.limit, Some(2 * 1024 * 1024));
+    assert_eq!(config.etag, Some(true));
+    assert_eq!(config.return_minimal, None);
+    assert_eq!(config.response_limit, None);
+
+    let err = CborConfig::from_lookup(vars(&[("ACTIX_CBOR_RESPONSE_LIMIT", "lots")]))
+        .err()
+        .unwrap();
+    assert_eq!(err.var, "ACTIX_CBOR_RESPONSE_LIMIT");
+    assert_eq!(
+        err.to_string(),
+        "invalid value \"lots\" for ACTIX_CBOR_RESPONSE_LIMIT: expected a size such as 32768, 512kB or 2MiB"
+    );
+}
+
+#[test]
+fn test_limit_str() {
+    let limit = |value: &str| CborConfig::default().limit_str(value).unwrap().limit;
+    assert_eq!(limit("32_768"), Some(32_768));
+    assert_eq!(limit("512kB"), Some(512_000));
+    assert_eq!(limit("4 KiB"), Some(4096));
+    assert_eq!(limit("1GB"), Some(1_000_000_000));
+
+    assert!(CborConfig::default().limit_str("2 megs").is_err());
+}
+
+#[actix_rt::test]
+async fn test_config_handle() {
+    let handle = CborConfigHandle::new(CborConfig::default().limit(10));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(web::Data::new(handle.clone()))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+
+    handle.update(|config| config.limit(1024));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(web::Data::new(handle.clone()))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_config_resolver() {
+    use std::borrow::Cow;
+
+    let resolver = CborConfigResolver::new(|req| match req.headers().get("x-tenant") {
+        Some(tenant) if tenant == "small" => Cow::Owned(CborConfig::default().limit(10)),
+        _ => Cow::Owned(CborConfig::default()),
+    });
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .insert_header(("x-tenant", "small"))
+        .set_payload(get_test_bytes())
+        .app_data(resolver.clone())
+        .app_data(CborConfig::default().limit(1024))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .insert_header(("x-tenant", "large"))
+        .set_payload(get_test_bytes())
+        .app_data(resolver)
+        .app_data(CborConfig::default().limit(1024))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_cbor_with() {
+    struct Tiny;
+
+    impl CborSettings for Tiny {
+        const LIMIT: usize = 10;
+    }
+
+    struct Roomy;
+
+    impl CborSettings for Roomy {
+        const LIMIT: usize = 1024;
+    }
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().limit(1024))
+        .to_http_parts();
+    let s = CborWith::<MyObject, Tiny>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().limit(10))
+        .to_http_parts();
+    let s = CborWith::<MyObject, Roomy>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_deny_unknown_fields() {
+    let mut map = BTreeMap::new();
+    map.insert("name", serde_cbor::Value::Text("test".to_owned()));
+    map.insert("number", serde_cbor::Value::Integer(7));
+    map.insert("extra", serde_cbor::Value::Bool(true));
+    let body = serde_cbor::to_vec(&map).unwrap();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body.clone())
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body)
+        .app_data(CborConfig::default().deny_unknown_fields(true))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Unknown field in CBOR payload: extra"));
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().deny_unknown_fields(true))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+}
+
+#[actix_rt::test]
+async fn test_cbor_lenient() {
+    #[derive(Serialize, Deserialize, Default, PartialEq, Debug)]
+    struct Reading {
+        value: i32,
+        battery: u8,
+        nested: MyObject,
+    }
+
+    let mut nested = BTreeMap::new();
+    nested.insert("number", serde_cbor::Value::Integer(3));
+    let mut map = BTreeMap::new();
+    map.insert("value", serde_cbor::Value::Integer(12));
+    map.insert("nested", serde_cbor::value::to_value(nested).unwrap());
+    let body = serde_cbor::to_vec(&map).unwrap();
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body.clone())
+        .to_http_parts();
+    assert!(Cbor::<Reading>::from_request(&req, &mut pl).await.is_err());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header(ContentType("application/cbor".parse().unwrap()))
+        .set_payload(body)
+        .to_http_parts();
+    let s = CborLenient::<Reading>::from_request(&req, &mut pl)
+        .await
+        .unwrap();
+    assert_eq!(
+        s.into_inner(),
+        Reading {
+            value: 12,
+            battery: 0,
+            nested: MyObject {
+                name: "test".to_owned(),
+                number: 3,
+            },
+        }
+    );
+}
+
+#[actix_rt::test]
+async fn test_cbor_versioned() {
+    #[derive(Debug, PartialEq)]
+    enum Versions {
+        V1(MyObject),
+        V2(Vec<MyObject>),
+    }
+
+    impl CborVersions for Versions {
+        fn decode(version: u64, payload: serde_cbor::Value) -> Result<Self, CborPayloadError> {
+            match version {
+                1 => Ok(Versions::V1(serde_cbor::value::from_value(payload)?)),
+                2 => Ok(Versions::V2(serde_cbor::value::from_value(payload)?)),
+                _ => Err(CborPayloadError::UnsupportedVersion(Some(version))),
+            }
+        }
+    }
+
+    let extract = |body: Vec<u8>| async move {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header(ContentType("application/cbor".parse().unwrap()))
+            .set_payload(body)
+            .to_http_parts();
+        CborVersioned::<Versions>::from_request(&req, &mut pl).await
+    };
+
+    let mut map = serde_cbor::value::to_value(MyObject::default()).unwrap();
+    if let serde_cbor::Value::Map(map) = &mut map {
+        map.insert("version".to_owned().into(), 1.into());
+    }
+    let s = extract(serde_cbor::to_vec(&map).unwrap()).await.unwrap();
+    assert_eq!(s.into_inner(), Versions::V1(MyObject::default()));
+
+    // tag 2 followed by the payload
+    let tagged = [
+        &[0xc2][..],
+        &serde_cbor::to_vec(&vec![MyObject::default()]).unwrap(),
+    ]
+    .concat();
+    let s = extract(tagged.clone()).await.unwrap();
+    assert_eq!(s.into_inner(), Versions::V2(vec![MyObject::default()]));
+
+    // the self-described CBOR tag is skipped before reading the version
+    let self_described = [&[0xd9, 0xd9, 0xf7][..], &tagged].concat();
+    let s = extract(self_described).await.unwrap();
+    assert_eq!(s.into_inner(), Versions::V2(vec![MyObject::default()]));
+    let self_described = [&[0xd9, 0xd9, 0xf7][..], &serde_cbor::to_vec(&map).unwrap()].concat();
+    let s = extract(self_described).await.unwrap();
+    assert_eq!(s.into_inner(), Versions::V1(MyObject::default()));
+
+    // tag 3 followed by null
+    let err = extract(vec![0xc3, 0xf6]).await.err();
+    assert!(format!("{}", err.unwrap()).contains("Unsupported CBOR payload version: 3"));
+
+    let err = extract(get_test_bytes()).await.err();
+    assert!(format!("{}", err.unwrap()).contains("Missing CBOR payload version"));
+}
+
+#[test]
+fn test_profile_content_type() {
+    for profile in [CborProfile::Packed, CborProfile::Canonical] {
+        let mime: mime::Mime = profile.content_type().parse().unwrap();
+        assert_eq!(mime.essence_str(), "application/cbor");
+        assert_eq!(mime.get_param("profile").unwrap(), profile.name());
+    }
+}
+
+#[actix_rt::test]
+async fn test_profile_negotiation() {
+    let mut map = BTreeMap::new();
+    map.insert("bb".to_owned(), 1);
+    map.insert("c".to_owned(), 2);
+
+    let req = TestRequest::default()
+        .insert_header((
+            header::ACCEPT,
+            "application/cbor;profile=packed, application/cbor;q=0.5",
+        ))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static(
+            "application/cbor; profile=packed"
+        ))
+    );
+    assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept");
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(
+        body,
+        serde_cbor::ser::to_vec_packed(&MyObject::default()).unwrap()
+    );
+
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/cbor;profile=canonical"))
+        .to_http_request();
+    let resp = Cbor(map).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static(
+            "application/cbor; profile=canonical"
+        ))
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(body[..3], [0xa2, 0x61, b'c']);
+
+    // unknown profiles and lower ranked ones are ignored
+    let req = TestRequest::default()
+        .insert_header((
+            header::ACCEPT,
+            "application/cbor;profile=other, application/cbor, application/cbor;profile=packed;q=0.1",
+        ))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/cbor"))
+    );
+
+    // refused profiles are not used
+    let req = TestRequest::default()
+        .insert_header((header::ACCEPT, "application/cbor;profile=packed;q=0"))
+        .to_http_request();
+    let resp = Cbor(MyObject::default()).respond_to(&req);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("application/cbor"))
+    );
+}
+
+#[actix_rt::test]
+async fn test_packed_or_named_payload() {
+    let named = get_test_bytes();
+    let packed = serde_cbor::ser::to_vec_packed(&MyObject::default()).unwrap();
+    let array = serde_cbor::to_vec(&("test", 7)).unwrap();
+
+    for (body, content_type) in [
+        (named, "application/cbor"),
+        (packed.clone(), "application/cbor; profile=packed"),
+        (packed, "application/cbor"),
+        (array, "application/cbor"),
+    ] {
+        let (req, mut pl) = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload(body)
+            .app_data(CborConfig::default().deny_unknown_fields(true))
+            .to_http_parts();
+        let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+        assert_eq!(s.into_inner(), MyObject::default());
+    }
+}
@@ -0,0 +1,311 @@
+//! Serde helpers encoding times, durations and periods with the extended time tags of RFC 9581,
+//! available with the `tags` feature.
+//!
+//! | Tag | Rust type | Helper |
+//! |---|---|---|
+//! | 1001, extended time | `SystemTime` | [`system_time`](system_time/index.html) |
+//! | 1002, duration | `Duration` | [`duration`](duration/index.html) |
+//! | 1003, period | [`Period`](struct.Period.html) | implements `Serialize` and `Deserialize` |
+//!
+//! Times and durations are maps holding whole seconds under key `1` and, when not zero, the
+//! nanoseconds under key `-9`. Decoding also accepts a floating point key `1`, milliseconds
+//! (`-3`) or microseconds (`-6`), and `SystemTime` accepts classic epoch times (tag 1). Other
+//! negative keys are critical, and rejected; other keys are ignored.
+//!
+//! # Example
+//! ```
+//! use actix_cbor::time_tags::{self, Period};
+//! use std::time::{Duration, SystemTime};
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Job {
+//!     #[serde(with = "time_tags::system_time")]
+//!     next_run: SystemTime,
+//!     #[serde(with = "time_tags::duration")]
+//!     timeout: Duration,
+//!     window: Period,
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_cbor::tags::Tagged;
+use serde_cbor::Value;
+
+/// Tag of classic epoch-based times.
+const EPOCH_TIME: u64 = 1;
+/// Tag of extended times.
+const EXTENDED_TIME: u64 = 1001;
+/// Tag of durations.
+const DURATION: u64 = 1002;
+/// Tag of periods.
+const PERIOD: u64 = 1003;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// Content of an extended time or duration tag: whole seconds and nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Seconds {
+    secs: i128,
+    nanos: u32,
+}
+
+impl Seconds {
+    fn of_time(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => Self::of_duration(elapsed),
+            Err(e) => {
+                // before the epoch, the fraction counts forward from the previous second
+                let before = e.duration();
+                let mut secs = -i128::from(before.as_secs());
+                let mut nanos = before.subsec_nanos();
+                if nanos > 0 {
+                    secs -= 1;
+                    nanos = NANOS_PER_SEC - nanos;
+                }
+                Self { secs, nanos }
+            }
+        }
+    }
+
+    fn of_duration(duration: Duration) -> Self {
+        Self {
+            secs: i128::from(duration.as_secs()),
+            nanos: duration.subsec_nanos(),
+        }
+    }
+
+    fn to_time(self) -> Option<SystemTime> {
+        let nanos = Duration::from_nanos(u64::from(self.nanos));
+        match u64::try_from(self.secs) {
+            Ok(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+            Err(_) => {
+                let secs = u64::try_from(-self.secs).ok()?;
+                UNIX_EPOCH.checked_sub(Duration::from_secs(secs))
+            }
+        }?
+        .checked_add(nanos)
+    }
+
+    fn to_duration(self) -> Option<Duration> {
+        let secs = u64::try_from(self.secs).ok()?;
+        Some(Duration::new(secs, self.nanos))
+    }
+
+    /// Seconds of a floating point number of seconds.
+    fn of_float(secs: f64) -> Option<Self> {
+        if !secs.is_finite() {
+            return None;
+        }
+        let whole = secs.floor();
+        Some(Self {
+            secs: whole as i128,
+            nanos: (((secs - whole) * f64::from(NANOS_PER_SEC)) as u32).min(NANOS_PER_SEC - 1),
+        })
+    }
+
+    /// Seconds of the map of an extended time or duration.
+    fn of_map(map: &BTreeMap<Value, Value>) -> Result<Self, String> {
+        let mut seconds = None;
+        let mut nanos = None;
+        for (key, value) in map {
+            let key = match key {
+                Value::Integer(key) => *key,
+                _ => continue,
+            };
+            match (key, value) {
+                (1, Value::Integer(secs)) => {
+                    seconds = Some(Self {
+                        secs: *secs,
+                        nanos: 0,
+                    })
+                }
+                (1, Value::Float(secs)) => seconds = Self::of_float(*secs),
+                (-3, Value::Integer(n)) if (0..1_000).contains(n) => {
+                    nanos = Some(*n as u32 * 1_000_000)
+                }
+                (-6, Value::Integer(n)) if (0..1_000_000).contains(n) => {
+                    nanos = Some(*n as u32 * 1_000)
+                }
+                (-9, Value::Integer(n)) if (0..1_000_000_000).contains(n) => {
+                    nanos = Some(*n as u32)
+                }
+                (key, _) if key < 0 => return Err(format!("unsupported time key {}", key)),
+                _ => {}
+            }
+        }
+
+        let mut seconds = seconds.ok_or("time without seconds")?;
+        seconds.nanos = nanos.unwrap_or(seconds.nanos);
+        Ok(seconds)
+    }
+
+    /// Seconds of `value`, the content of a time tag.
+    fn of_value(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Map(map) => Self::of_map(map),
+            _ => Err("time tag without a map".to_owned()),
+        }
+    }
+}
+
+impl Serialize for Seconds {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = if self.nanos == 0 { 1 } else { 2 };
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry(&1, &self.secs)?;
+        if self.nanos != 0 {
+            map.serialize_entry(&-9, &self.nanos)?;
+        }
+        map.end()
+    }
+}
+
+/// Read a tagged value, as `tags` allows.
+fn tagged<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(Option<u64>, Value), D::Error> {
+    let tagged = Tagged::<Value>::deserialize(deserializer)?;
+    Ok((tagged.tag, tagged.value))
+}
+
+/// `SystemTime` as an extended time (tag 1001).
+pub mod system_time {
+    use super::*;
+
+    /// Serialize `time` as an extended time.
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged::new(Some(EXTENDED_TIME), Seconds::of_time(*time)).serialize(serializer)
+    }
+
+    /// Deserialize an extended time, or a classic epoch time.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let seconds = match tagged(deserializer)? {
+            (Some(EXTENDED_TIME), value) => Seconds::of_value(&value).map_err(D::Error::custom)?,
+            (Some(EPOCH_TIME), Value::Integer(secs)) => Seconds { secs, nanos: 0 },
+            (Some(EPOCH_TIME), Value::Float(secs)) => {
+                Seconds::of_float(secs).ok_or_else(|| D::Error::custom("invalid epoch time"))?
+            }
+            _ => return Err(D::Error::custom("expected an extended time (tag 1001)")),
+        };
+        seconds
+            .to_time()
+            .ok_or_else(|| D::Error::custom("time out of range"))
+    }
+}
+
+/// `Duration` as a duration (tag 1002).
+pub mod duration {
+    use super::*;
+
+    /// Serialize `duration` as a duration.
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged::new(Some(DURATION), Seconds::of_duration(*duration)).serialize(serializer)
+    }
+
+    /// Deserialize a duration, failing when it is negative.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match tagged(deserializer)? {
+            (Some(DURATION), value) => Seconds::of_value(&value)
+                .map_err(D::Error::custom)?
+                .to_duration()
+                .ok_or_else(|| D::Error::custom("negative or out of range duration")),
+            _ => Err(D::Error::custom("expected a duration (tag 1002)")),
+        }
+    }
+}
+
+/// Period (tag 1003): an array of its start, end and duration, two of them being given.
+///
+/// Start and end are extended times and the duration a duration, all without their tag, or
+/// `null` when not given. The duration is left out of the array when not given. Periods without
+/// exactly two of them fail to serialize and are rejected when decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    /// Start of the period
+    pub start: Option<SystemTime>,
+    /// End of the period
+    pub end: Option<SystemTime>,
+    /// Duration of the period
+    pub duration: Option<Duration>,
+}
+
+impl Period {
+    /// Number of bounds given among start, end and duration.
+    fn given(&self) -> usize {
+        [
+            self.start.is_some(),
+            self.end.is_some(),
+            self.duration.is_some(),
+        ]
+        .iter()
+        .filter(|given| **given)
+        .count()
+    }
+}
+
+/// Error of periods which do not have exactly two of start, end and duration.
+const INVALID_PERIOD: &str = "a period needs exactly two of start, end and duration";
+
+impl Serialize for Period {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.given() != 2 {
+            return Err(serde::ser::Error::custom(INVALID_PERIOD));
+        }
+        let start = self.start.map(Seconds::of_time);
+        let end = self.end.map(Seconds::of_time);
+        match self.duration.map(Seconds::of_duration) {
+            Some(duration) => {
+                Tagged::new(Some(PERIOD), (start, end, duration)).serialize(serializer)
+            }
+            None => Tagged::new(Some(PERIOD), (start, end)).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Period {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let items = match tagged(deserializer)? {
+            (Some(PERIOD), Value::Array(items)) if (2..=3).contains(&items.len()) => items,
+            _ => return Err(D::Error::custom("expected a period (tag 1003)")),
+        };
+
+        // items may keep the tag of their type
+        let seconds = |item: &Value, tag: u64| match item {
+            Value::Null => Ok(None),
+            Value::Tag(t, value) if *t == tag => Seconds::of_value(value).map(Some),
+            value => Seconds::of_value(value).map(Some),
+        };
+        let time = |item: &Value| match seconds(item, EXTENDED_TIME)? {
+            Some(seconds) => seconds
+                .to_time()
+                .map(Some)
+                .ok_or_else(|| "time out of range".to_owned()),
+            None => Ok(None),
+        };
+
+        let start = time(&items[0]).map_err(D::Error::custom)?;
+        let end = time(&items[1]).map_err(D::Error::custom)?;
+        let duration = match items.get(2).map(|item| seconds(item, DURATION)) {
+            Some(Ok(Some(seconds))) => Some(
+                seconds
+                    .to_duration()
+                    .ok_or_else(|| D::Error::custom("negative or out of range duration"))?,
+            ),
+            Some(Err(e)) => return Err(D::Error::custom(e)),
+            _ => None,
+        };
+
+        let period = Period {
+            start,
+            end,
+            duration,
+        };
+        match period.given() {
+            2 => Ok(period),
+            _ => Err(D::Error::custom(INVALID_PERIOD)),
+        }
+    }
+}
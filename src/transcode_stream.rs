@@ -0,0 +1,697 @@
+//! Incremental transcoding of streamed bodies between JSON and CBOR.
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::Write;
+
+use actix_web::web::{Bytes, BytesMut};
+use bytes::Buf;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::de::Error as _;
+
+use crate::scan::{read_head, DEFAULT_MAX_DEPTH};
+use crate::CborTranscodeError;
+
+/// Transcoder fed with the chunks of a body.
+trait Incremental {
+    /// Transcode as much of `input` as possible to `out`, keeping unterminated items for the
+    /// next chunk.
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), CborTranscodeError>;
+
+    /// Transcode what is left once the body ended.
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), CborTranscodeError>;
+}
+
+/// Stream of the chunks transcoded from `body`, ending at the first error.
+fn transcode<S, E, T>(
+    body: S,
+    transcoder: T,
+) -> impl Stream<Item = Result<Bytes, CborTranscodeError>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<CborTranscodeError>,
+    T: Incremental,
+{
+    stream::unfold(Some((Box::pin(body), transcoder)), |state| async move {
+        let (mut body, mut transcoder) = state?;
+        let mut out = Vec::new();
+        loop {
+            let res = match body.next().await {
+                Some(Ok(chunk)) => transcoder.feed(&chunk, &mut out),
+                Some(Err(e)) => Err(e.into()),
+                None => {
+                    return match transcoder.finish(&mut out) {
+                        Ok(()) if out.is_empty() => None,
+                        Ok(()) => Some((Ok(Bytes::from(out)), None)),
+                        Err(e) => Some((Err(e), None)),
+                    };
+                }
+            };
+            match res {
+                Ok(()) if out.is_empty() => {}
+                Ok(()) => return Some((Ok(Bytes::from(out)), Some((body, transcoder)))),
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    })
+}
+
+/// Transcode a streamed JSON document into CBOR, without holding the document in memory,
+/// available with the `json` feature.
+///
+/// Each chunk of `body` is transcoded as soon as it is received, except for the string or
+/// number it ends in the middle of. Arrays and objects become indefinite-length arrays and maps,
+/// strings become text strings and numbers become integers when they have no fraction nor
+/// exponent, and floats otherwise. The stream ends with the first error.
+///
+/// # Example
+/// ```
+/// use actix_cbor::transcode_json_stream_to_cbor;
+/// use actix_web::{web, HttpResponse};
+///
+/// // answers a JSON upload, of any size, in CBOR
+/// async fn bridge(body: web::Payload) -> HttpResponse {
+///     HttpResponse::Ok()
+///         .content_type("application/cbor")
+///         .streaming(transcode_json_stream_to_cbor(body))
+/// }
+/// ```
+pub fn transcode_json_stream_to_cbor<S, E>(
+    body: S,
+) -> impl Stream<Item = Result<Bytes, CborTranscodeError>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<CborTranscodeError>,
+{
+    transcode(body, JsonToCbor::default())
+}
+
+/// Transcode a streamed CBOR data item into JSON, without holding the item in memory, available
+/// with the `json` feature.
+///
+/// Each chunk of `body` is transcoded as soon as it is received, except for the definite-length
+/// string it ends in the middle of. Conversions follow
+/// [`transcode_cbor_to_json`](fn.transcode_cbor_to_json.html), except that map entries keep
+/// their order. The stream ends with the first error.
+///
+/// # Example
+/// ```
+/// use actix_cbor::transcode_cbor_stream_to_json;
+/// use actix_web::{web, HttpResponse};
+///
+/// // answers a CBOR upload, of any size, in JSON
+/// async fn bridge(body: web::Payload) -> HttpResponse {
+///     HttpResponse::Ok()
+///         .content_type("application/json")
+///         .streaming(transcode_cbor_stream_to_json(body))
+/// }
+/// ```
+pub fn transcode_cbor_stream_to_json<S, E>(
+    body: S,
+) -> impl Stream<Item = Result<Bytes, CborTranscodeError>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<CborTranscodeError>,
+{
+    transcode(body, CborToJson::default())
+}
+
+fn json_error(msg: impl Display) -> CborTranscodeError {
+    serde_json::Error::custom(msg).into()
+}
+
+fn cbor_error(msg: impl Display) -> CborTranscodeError {
+    serde_cbor::Error::custom(msg).into()
+}
+
+/// Append `value` to `out`, which cannot fail.
+fn push_display(out: &mut Vec<u8>, value: impl Display) {
+    let _ = write!(out, "{}", value);
+}
+
+/// JSON container being transcoded.
+enum JsonFrame {
+    Array,
+    Object,
+}
+
+/// Next token expected in a JSON document.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ValueOrEnd,
+    Key,
+    KeyOrEnd,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+/// Incremental JSON to CBOR transcoder.
+struct JsonToCbor {
+    buf: BytesMut,
+    stack: Vec<JsonFrame>,
+    expect: Expect,
+    /// Bytes of the unterminated string at the start of `buf` already scanned, and whether the
+    /// last one escapes the next.
+    scanned: usize,
+    escaped: bool,
+}
+
+impl Default for JsonToCbor {
+    fn default() -> Self {
+        JsonToCbor {
+            buf: BytesMut::new(),
+            stack: Vec::new(),
+            expect: Expect::Value,
+            scanned: 0,
+            escaped: false,
+        }
+    }
+}
+
+impl JsonToCbor {
+    fn after_value(&mut self) {
+        self.expect = match self.stack.is_empty() {
+            true => Expect::Done,
+            false => Expect::CommaOrEnd,
+        };
+    }
+
+    fn open(&mut self, frame: JsonFrame, out: &mut Vec<u8>) -> Result<(), CborTranscodeError> {
+        if self.stack.len() >= DEFAULT_MAX_DEPTH {
+            return Err(json_error("recursion limit exceeded"));
+        }
+        let (marker, expect) = match frame {
+            JsonFrame::Array => (0x9f, Expect::ValueOrEnd),
+            JsonFrame::Object => (0xbf, Expect::KeyOrEnd),
+        };
+        out.push(marker);
+        self.stack.push(frame);
+        self.expect = expect;
+        Ok(())
+    }
+
+    fn close(&mut self, out: &mut Vec<u8>) {
+        self.stack.pop();
+        out.push(0xff);
+        self.after_value();
+    }
+
+    /// End of the string starting at `buf[start]`, if received.
+    fn string_end(&mut self, buf: &[u8], start: usize) -> Option<usize> {
+        let from = start + self.scanned.max(1);
+        for (i, byte) in buf[from..].iter().enumerate() {
+            match (self.escaped, byte) {
+                (true, _) => self.escaped = false,
+                (false, b'\\') => self.escaped = true,
+                (false, b'"') => {
+                    self.scanned = 0;
+                    return Some(from + i);
+                }
+                _ => {}
+            }
+        }
+        self.scanned = buf.len() - start;
+        None
+    }
+
+    /// Transcode the tokens of `buf` from `pos`, leaving `pos` at the first unterminated one.
+    fn process(
+        &mut self,
+        buf: &[u8],
+        pos: &mut usize,
+        out: &mut Vec<u8>,
+        eof: bool,
+    ) -> Result<(), CborTranscodeError> {
+        loop {
+            while buf.get(*pos).is_some_and(|c| b" \t\r\n".contains(c)) {
+                *pos += 1;
+            }
+            let c = match buf.get(*pos) {
+                Some(c) => *c,
+                None => return Ok(()),
+            };
+
+            match (self.expect, c) {
+                (Expect::Done, _) => return Err(json_error("trailing characters")),
+                (Expect::Colon, b':') => {
+                    *pos += 1;
+                    self.expect = Expect::Value;
+                }
+                (Expect::CommaOrEnd, b',') => {
+                    *pos += 1;
+                    self.expect = match self.stack.last() {
+                        Some(JsonFrame::Object) => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                }
+                (Expect::CommaOrEnd, b']') | (Expect::ValueOrEnd, b']')
+                    if matches!(self.stack.last(), Some(JsonFrame::Array)) =>
+                {
+                    *pos += 1;
+                    self.close(out);
+                }
+                (Expect::CommaOrEnd, b'}') | (Expect::KeyOrEnd, b'}')
+                    if matches!(self.stack.last(), Some(JsonFrame::Object)) =>
+                {
+                    *pos += 1;
+                    self.close(out);
+                }
+                (Expect::Key, b'"') | (Expect::KeyOrEnd, b'"') => {
+                    if !self.string(buf, pos, out)? {
+                        return Ok(());
+                    }
+                    self.expect = Expect::Colon;
+                }
+                (Expect::Value, _) | (Expect::ValueOrEnd, _) => {
+                    if !self.value(buf, pos, out, eof)? {
+                        return Ok(());
+                    }
+                }
+                (_, c) => {
+                    return Err(json_error(format_args!(
+                        "unexpected character `{}`",
+                        char::from(c)
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Transcode the string starting at `buf[pos]`, unless it is unterminated.
+    fn string(
+        &mut self,
+        buf: &[u8],
+        pos: &mut usize,
+        out: &mut Vec<u8>,
+    ) -> Result<bool, CborTranscodeError> {
+        let end = match self.string_end(buf, *pos) {
+            Some(end) => end,
+            None => return Ok(false),
+        };
+        let text: String = serde_json::from_slice(&buf[*pos..=end])?;
+        serde_cbor::to_writer(&mut *out, &text)?;
+        *pos = end + 1;
+        Ok(true)
+    }
+
+    /// Transcode the value starting at `buf[pos]`, unless it is unterminated.
+    fn value(
+        &mut self,
+        buf: &[u8],
+        pos: &mut usize,
+        out: &mut Vec<u8>,
+        eof: bool,
+    ) -> Result<bool, CborTranscodeError> {
+        match buf[*pos] {
+            b'[' => {
+                *pos += 1;
+                self.open(JsonFrame::Array, out)?;
+                return Ok(true);
+            }
+            b'{' => {
+                *pos += 1;
+                self.open(JsonFrame::Object, out)?;
+                return Ok(true);
+            }
+            b'"' => {
+                if !self.string(buf, pos, out)? {
+                    return Ok(false);
+                }
+            }
+            b't' | b'f' | b'n' => {
+                let (literal, encoded): (&[u8], u8) = match buf[*pos] {
+                    b't' => (b"true", 0xf5),
+                    b'f' => (b"false", 0xf4),
+                    _ => (b"null", 0xf6),
+                };
+                let received = &buf[*pos..buf.len().min(*pos + literal.len())];
+                if received.len() < literal.len() && !eof && literal.starts_with(received) {
+                    return Ok(false);
+                }
+                if received != literal {
+                    return Err(json_error("invalid literal"));
+                }
+                out.push(encoded);
+                *pos += literal.len();
+            }
+            b'-' | b'0'..=b'9' => {
+                let len = buf[*pos..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || b"+-.eE".contains(c))
+                    .count();
+                if *pos + len == buf.len() && !eof {
+                    return Ok(false);
+                }
+                let number: serde_json::Number = serde_json::from_slice(&buf[*pos..*pos + len])?;
+                if let Some(n) = number.as_u64() {
+                    serde_cbor::to_writer(&mut *out, &n)?;
+                } else if let Some(n) = number.as_i64() {
+                    serde_cbor::to_writer(&mut *out, &n)?;
+                } else if let Some(n) = number.as_f64() {
+                    serde_cbor::to_writer(&mut *out, &n)?;
+                }
+                *pos += len;
+            }
+            c => {
+                return Err(json_error(format_args!(
+                    "unexpected character `{}`",
+                    char::from(c)
+                )))
+            }
+        }
+        self.after_value();
+        Ok(true)
+    }
+
+    fn run(&mut self, out: &mut Vec<u8>, eof: bool) -> Result<(), CborTranscodeError> {
+        let buf = std::mem::take(&mut self.buf);
+        let mut pos = 0;
+        let res = self.process(&buf, &mut pos, out, eof);
+        self.buf = buf;
+        self.buf.advance(pos);
+        res
+    }
+}
+
+impl Incremental for JsonToCbor {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), CborTranscodeError> {
+        self.buf.extend_from_slice(input);
+        self.run(out, false)
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), CborTranscodeError> {
+        self.run(out, true)?;
+        match self.expect {
+            Expect::Done => Ok(()),
+            _ => Err(json_error("EOF while parsing a value")),
+        }
+    }
+}
+
+/// CBOR container being transcoded.
+enum CborFrame {
+    /// Array expecting this many more items, or a break code when indefinite.
+    Array { remaining: Option<u64>, first: bool },
+    /// Map expecting this many more keys and values, or a break code when indefinite.
+    Map {
+        remaining: Option<u64>,
+        key: bool,
+        first: bool,
+    },
+    /// Indefinite-length text string, whose chunks are concatenated.
+    Text,
+    /// Indefinite-length byte string, whose chunks are concatenated into an array of numbers.
+    Bytes { first: bool },
+}
+
+/// Position of the next item in its container.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Key,
+    Value,
+}
+
+/// Incremental CBOR to JSON transcoder.
+#[derive(Default)]
+struct CborToJson {
+    buf: BytesMut,
+    stack: Vec<CborFrame>,
+    done: bool,
+}
+
+/// Value of a half-precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exp = i32::from((bits >> 10) & 0x1f);
+    let frac = f64::from(bits & 0x3ff);
+    sign * match exp {
+        0 => frac * 2f64.powi(-24),
+        31 if bits & 0x3ff == 0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + frac / 1024.0) * 2f64.powi(exp - 15),
+    }
+}
+
+/// Append the bytes of `bytes` as a comma-separated list of numbers.
+fn push_byte_list(out: &mut Vec<u8>, bytes: &[u8], first: &mut bool) {
+    for byte in bytes {
+        if !*first {
+            out.push(b',');
+        }
+        *first = false;
+        push_display(out, byte);
+    }
+}
+
+impl CborToJson {
+    /// Write the separator of the next item of the current container, and locate it.
+    fn begin_item(&mut self, out: &mut Vec<u8>) -> Slot {
+        match self.stack.last_mut() {
+            Some(CborFrame::Array { first, .. }) => {
+                if !*first {
+                    out.push(b',');
+                }
+                *first = false;
+                Slot::Value
+            }
+            Some(CborFrame::Map {
+                key: true, first, ..
+            }) => {
+                if !*first {
+                    out.push(b',');
+                }
+                *first = false;
+                Slot::Key
+            }
+            Some(CborFrame::Map { key: false, .. }) => {
+                out.push(b':');
+                Slot::Value
+            }
+            _ => Slot::Value,
+        }
+    }
+
+    /// Account for a complete item, closing the definite containers it completes.
+    fn end_item(&mut self, out: &mut Vec<u8>) {
+        loop {
+            let (remaining, closer) = match self.stack.last_mut() {
+                None => {
+                    self.done = true;
+                    return;
+                }
+                Some(CborFrame::Array { remaining, .. }) => (remaining, b']'),
+                Some(CborFrame::Map { remaining, key, .. }) => {
+                    *key = !*key;
+                    (remaining, b'}')
+                }
+                Some(_) => return,
+            };
+            match remaining {
+                Some(n) if *n <= 1 => {
+                    self.stack.pop();
+                    out.push(closer);
+                }
+                Some(n) => {
+                    *n -= 1;
+                    return;
+                }
+                None => return,
+            }
+        }
+    }
+
+    fn open(&mut self, frame: CborFrame) -> Result<(), CborTranscodeError> {
+        if self.stack.len() >= DEFAULT_MAX_DEPTH {
+            return Err(cbor_error("recursion limit exceeded"));
+        }
+        self.stack.push(frame);
+        Ok(())
+    }
+
+    /// Transcode the items of `buf` from `pos`, leaving `pos` at the first incomplete one.
+    fn process(
+        &mut self,
+        buf: &[u8],
+        pos: &mut usize,
+        out: &mut Vec<u8>,
+    ) -> Result<(), CborTranscodeError> {
+        loop {
+            if self.done {
+                return match *pos < buf.len() {
+                    true => Err(cbor_error("trailing data")),
+                    false => Ok(()),
+                };
+            }
+            let head = match read_head(buf, *pos) {
+                Ok(Some(head)) => head,
+                Ok(None) => return Ok(()),
+                Err(e) => return Err(cbor_error(format_args!("malformed at {}", e.offset))),
+            };
+            let start = *pos + head.len;
+
+            // break code, closing an indefinite container
+            if head.major == 7 && head.arg.is_none() {
+                let closer = match self.stack.pop() {
+                    Some(CborFrame::Array {
+                        remaining: None, ..
+                    }) => b']',
+                    Some(CborFrame::Map {
+                        remaining: None,
+                        key: true,
+                        ..
+                    }) => b'}',
+                    Some(CborFrame::Text) => b'"',
+                    Some(CborFrame::Bytes { .. }) => b']',
+                    _ => return Err(cbor_error("unexpected break code")),
+                };
+                out.push(closer);
+                *pos = start;
+                self.end_item(out);
+                continue;
+            }
+
+            // definite strings are transcoded once received
+            let len = match (head.major, head.arg) {
+                (2, Some(n)) | (3, Some(n)) => match usize::try_from(n) {
+                    Ok(n) if n <= buf.len() - start => n,
+                    Ok(_) => return Ok(()),
+                    Err(_) => return Err(cbor_error("string too long")),
+                },
+                _ => 0,
+            };
+            let content = &buf[start..start + len];
+            *pos = start + len;
+
+            // chunks of an indefinite string
+            match self.stack.last_mut() {
+                Some(CborFrame::Text) => {
+                    if head.major != 3 || head.arg.is_none() {
+                        return Err(cbor_error("invalid text string chunk"));
+                    }
+                    let text = std::str::from_utf8(content)
+                        .map_err(|_| cbor_error("invalid UTF-8 in text string"))?;
+                    let escaped = serde_json::to_string(text)?;
+                    out.extend_from_slice(&escaped.as_bytes()[1..escaped.len() - 1]);
+                    continue;
+                }
+                Some(CborFrame::Bytes { first }) => {
+                    if head.major != 2 || head.arg.is_none() {
+                        return Err(cbor_error("invalid byte string chunk"));
+                    }
+                    push_byte_list(out, content, first);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // tags are dropped
+            if head.major == 6 {
+                match head.arg {
+                    Some(_) => continue,
+                    None => return Err(cbor_error(format_args!("malformed at {}", *pos - 1))),
+                }
+            }
+
+            let slot = self.begin_item(out);
+            let is_key = slot == Slot::Key;
+            match (head.major, head.arg) {
+                (0, Some(n)) if is_key => push_display(out, format_args!("\"{}\"", n)),
+                (0, Some(n)) => push_display(out, n),
+                (1, Some(n)) if is_key => {
+                    push_display(out, format_args!("\"{}\"", -1 - i128::from(n)))
+                }
+                (1, Some(n)) => push_display(out, -1 - i128::from(n)),
+                (3, Some(_)) => {
+                    let text = std::str::from_utf8(content)
+                        .map_err(|_| cbor_error("invalid UTF-8 in text string"))?;
+                    serde_json::to_writer(&mut *out, text)?;
+                }
+                (3, None) => {
+                    out.push(b'"');
+                    self.open(CborFrame::Text)?;
+                    continue;
+                }
+                _ if is_key => {
+                    return Err(cbor_error(
+                        "map keys must be text strings or integers to be transcoded",
+                    ))
+                }
+                (2, Some(_)) => {
+                    out.push(b'[');
+                    push_byte_list(out, content, &mut true);
+                    out.push(b']');
+                }
+                (2, None) => {
+                    out.push(b'[');
+                    self.open(CborFrame::Bytes { first: true })?;
+                    continue;
+                }
+                (4, Some(0)) => out.extend_from_slice(b"[]"),
+                (5, Some(0)) => out.extend_from_slice(b"{}"),
+                (4, remaining) => {
+                    out.push(b'[');
+                    self.open(CborFrame::Array {
+                        remaining,
+                        first: true,
+                    })?;
+                    continue;
+                }
+                (5, remaining) => {
+                    let remaining = match remaining {
+                        Some(n) => {
+                            Some(n.checked_mul(2).ok_or_else(|| cbor_error("map too long"))?)
+                        }
+                        None => None,
+                    };
+                    out.push(b'{');
+                    self.open(CborFrame::Map {
+                        remaining,
+                        key: true,
+                        first: true,
+                    })?;
+                    continue;
+                }
+                (7, Some(arg)) => match head.len {
+                    1 if arg == 20 => out.extend_from_slice(b"false"),
+                    1 if arg == 21 => out.extend_from_slice(b"true"),
+                    1 if arg == 22 || arg == 23 => out.extend_from_slice(b"null"),
+                    3 | 5 | 9 => {
+                        let value = match head.len {
+                            3 => f16_to_f64(arg as u16),
+                            5 => f64::from(f32::from_bits(arg as u32)),
+                            _ => f64::from_bits(arg),
+                        };
+                        match value.is_finite() {
+                            true => serde_json::to_writer(&mut *out, &value)?,
+                            false => out.extend_from_slice(b"null"),
+                        }
+                    }
+                    _ => return Err(cbor_error("unsupported simple value")),
+                },
+                _ => return Err(cbor_error("unexpected CBOR item")),
+            }
+            self.end_item(out);
+        }
+    }
+}
+
+impl Incremental for CborToJson {
+    fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), CborTranscodeError> {
+        self.buf.extend_from_slice(input);
+        let buf = std::mem::take(&mut self.buf);
+        let mut pos = 0;
+        let res = self.process(&buf, &mut pos, out);
+        self.buf = buf;
+        self.buf.advance(pos);
+        res
+    }
+
+    fn finish(&mut self, _: &mut Vec<u8>) -> Result<(), CborTranscodeError> {
+        match self.done {
+            true => Ok(()),
+            false => Err(cbor_error("EOF while parsing a value")),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{body::BoxBody, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::{respond_cbor, response_error, CborPayloadError};
+
+/// Responder behaving like [`Cbor`](struct.Cbor.html), except that serialization failures are
+/// rendered through [`CborError`](struct.CborError.html), with the serializer message, instead
+/// of being logged behind an empty `500 Internal Server Error`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::TryCbor;
+/// use std::collections::HashMap;
+///
+/// async fn index() -> TryCbor<HashMap<String, u32>> {
+///     TryCbor(HashMap::new())
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TryCbor<T>(pub T);
+
+impl<T> TryCbor<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for TryCbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for TryCbor<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Serialize> Responder for TryCbor<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        match respond_cbor(&self.0, req) {
+            Ok(res) => res,
+            // the serializer message is the point of this responder
+            Err(CborPayloadError::Serialize(e)) => HttpResponse::from_error(e),
+            Err(e) => response_error(e, req),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Serde helpers encoding numeric vectors as the typed arrays of RFC 8746, available with the
+//! `tags` feature.
+//!
+//! A `Vec<f32>` is then a single byte string tagged 85, holding the little-endian elements one
+//! after the other, instead of an array of individually encoded floats. Decoding accepts the
+//! little and big-endian tags of the element type, as well as plain arrays from clients which
+//! do not use typed arrays.
+//!
+//! | Element | Tag |
+//! |---|---|
+//! | `u8` | 64 (68 for clamped arrays is also accepted) |
+//! | `u16`, `u32`, `u64` | 69, 70, 71 (big-endian: 65, 66, 67) |
+//! | `i8` | 72 |
+//! | `i16`, `i32`, `i64` | 77, 78, 79 (big-endian: 73, 74, 75) |
+//! | `f32`, `f64` | 85, 86 (big-endian: 81, 82) |
+//!
+//! # Example
+//! ```
+//! use actix_cbor::typed_array;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Samples {
+//!     sensor: String,
+//!     #[serde(with = "typed_array")]
+//!     values: Vec<f32>,
+//! }
+//! ```
+
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeOwned, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use serde_cbor::tags::{current_cbor_tag, Tagged};
+
+/// Element of a typed array.
+pub trait TypedArrayElement: Copy + DeserializeOwned {
+    /// Bytes taken by one element.
+    const SIZE: usize;
+    /// Tag of little-endian arrays of this element.
+    const TAG_LE: u64;
+    /// Tag of big-endian arrays of this element.
+    const TAG_BE: u64;
+
+    /// Append the little-endian bytes of `self` to `out`.
+    fn write_le(self, out: &mut Vec<u8>);
+
+    /// Element of `Self::SIZE` bytes, in little-endian order when `le`, big-endian otherwise.
+    fn read(bytes: &[u8], le: bool) -> Self;
+
+    /// Whether `tag` is another tag accepted for arrays of this element.
+    fn accepts(_tag: u64) -> bool {
+        false
+    }
+}
+
+macro_rules! typed_array_element {
+    ($($ty:ty => $le:expr, $be:expr;)+) => {
+        $(
+            impl TypedArrayElement for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                const TAG_LE: u64 = $le;
+                const TAG_BE: u64 = $be;
+
+                fn write_le(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(bytes: &[u8], le: bool) -> Self {
+                    let bytes = bytes.try_into().unwrap();
+                    match le {
+                        true => <$ty>::from_le_bytes(bytes),
+                        false => <$ty>::from_be_bytes(bytes),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+typed_array_element! {
+    u16 => 69, 65;
+    u32 => 70, 66;
+    u64 => 71, 67;
+    i8 => 72, 72;
+    i16 => 77, 73;
+    i32 => 78, 74;
+    i64 => 79, 75;
+    f32 => 85, 81;
+    f64 => 86, 82;
+}
+
+impl TypedArrayElement for u8 {
+    const SIZE: usize = 1;
+    const TAG_LE: u64 = 64;
+    const TAG_BE: u64 = 64;
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+
+    fn read(bytes: &[u8], _: bool) -> Self {
+        bytes[0]
+    }
+
+    fn accepts(tag: u64) -> bool {
+        // clamped arrays only differ in how values were computed
+        tag == 68
+    }
+}
+
+/// Packed little-endian elements, serialized as a byte string.
+struct Packed<'a, T>(&'a [T]);
+
+impl<T: TypedArrayElement> Serialize for Packed<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = Vec::with_capacity(self.0.len() * T::SIZE);
+        for value in self.0 {
+            value.write_le(&mut bytes);
+        }
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+/// Serialize `values` as a little-endian typed array.
+pub fn serialize<T, S>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: TypedArrayElement,
+    S: Serializer,
+{
+    Tagged::new(Some(T::TAG_LE), Packed(values)).serialize(serializer)
+}
+
+/// Deserialize a typed array of `T`, or a plain array.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    T: TypedArrayElement,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(TypedArrayVisitor(PhantomData))
+}
+
+struct TypedArrayVisitor<T>(PhantomData<T>);
+
+impl<'de, T: TypedArrayElement> Visitor<'de> for TypedArrayVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a typed array or an array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        let le = match current_cbor_tag() {
+            Some(tag) if tag == T::TAG_LE || T::accepts(tag) => true,
+            Some(tag) if tag == T::TAG_BE => false,
+            Some(tag) => {
+                return Err(de::Error::invalid_value(
+                    de::Unexpected::Other(&format!("tag {}", tag)),
+                    &self,
+                ))
+            }
+            None => return Err(de::Error::custom("typed array without a tag")),
+        };
+        deserializer.deserialize_bytes(PackedVisitor(le, PhantomData))
+    }
+}
+
+struct PackedVisitor<T>(bool, PhantomData<T>);
+
+impl<'de, T: TypedArrayElement> Visitor<'de> for PackedVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a byte string of {}-byte elements", T::SIZE)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Vec<T>, E> {
+        let chunks = bytes.chunks_exact(T::SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(E::invalid_length(bytes.len(), &self));
+        }
+        Ok(chunks.map(|chunk| T::read(chunk, self.0)).collect())
+    }
+}
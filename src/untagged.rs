@@ -0,0 +1,334 @@
+//! Decoding of typed values which ignores the tags of their items, as serde_cbor does without
+//! its `tags` feature.
+//!
+//! With the `tags` feature, serde_cbor hands every tagged item to `visit_newtype_struct`, which
+//! most types reject: a `u64` field fails on an epoch time such as `1(1600000000)`, and any
+//! struct fails on a body prefixed with the self-described CBOR tag 55799. Items read with a
+//! type hint (`deserialize_u64`, `deserialize_struct`...) are decoded here as if they were not
+//! tagged, while those read with `deserialize_any` or `deserialize_newtype_struct` keep their
+//! tag, so `serde_cbor::Value`, `Tagged<T>` and the [`bignum`](bignum/index.html),
+//! [`time_tags`](time_tags/index.html) and [`typed_array`](typed_array/index.html) helpers
+//! still see it.
+
+use std::io::Read;
+
+use serde::de::Deserialize;
+
+/// Decode a single CBOR item spanning the whole slice.
+pub(crate) fn from_slice<'a, T: Deserialize<'a>>(slice: &'a [u8]) -> serde_cbor::Result<T> {
+    let mut de = serde_cbor::Deserializer::from_slice(slice);
+    let value = T::deserialize(untagged(&mut de))?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Decode a single CBOR item spanning the whole reader.
+pub(crate) fn from_reader<T, R>(reader: R) -> serde_cbor::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    R: Read,
+{
+    let mut de = serde_cbor::Deserializer::from_reader(reader);
+    let value = T::deserialize(untagged(&mut de))?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Decode every item of a CBOR sequence.
+pub(crate) fn seq_from_slice<'a, T: Deserialize<'a>>(
+    slice: &'a [u8],
+) -> serde_cbor::Result<Vec<T>> {
+    let mut de = serde_cbor::Deserializer::from_slice(slice);
+    let mut items = Vec::new();
+    while de.byte_offset() < slice.len() {
+        items.push(T::deserialize(untagged(&mut de))?);
+    }
+    Ok(items)
+}
+
+#[cfg(not(feature = "tags"))]
+pub(crate) fn untagged<D>(de: D) -> D {
+    de
+}
+
+#[cfg(feature = "tags")]
+pub(crate) use self::tags::untagged;
+
+#[cfg(feature = "tags")]
+mod tags {
+    use std::fmt;
+
+    use serde::de::{
+        DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+    };
+
+    /// Deserializer skipping the tags of items read with a type hint.
+    pub(crate) fn untagged<D>(de: D) -> Untagged<D> {
+        Untagged(de)
+    }
+
+    pub(crate) struct Untagged<D>(D);
+
+    /// How a tagged item is read again once its tag is skipped.
+    #[derive(Clone, Copy)]
+    enum Hint {
+        /// Tags are kept for the visitor.
+        Keep,
+        Any,
+        Option,
+        Enum(&'static str, &'static [&'static str]),
+    }
+
+    struct Wrap<V> {
+        visitor: V,
+        hint: Hint,
+    }
+
+    macro_rules! hinted {
+        ($($method:ident)*) => {
+            $(
+                fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+                    self.0.$method(Wrap { visitor, hint: Hint::Any })
+                }
+            )*
+        };
+    }
+
+    impl<'de, D: Deserializer<'de>> Deserializer<'de> for Untagged<D> {
+        type Error = D::Error;
+
+        hinted! {
+            deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+            deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+            deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+            deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+            deserialize_seq deserialize_map deserialize_identifier deserialize_ignored_any
+        }
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+            let hint = Hint::Keep;
+            self.0.deserialize_any(Wrap { visitor, hint })
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+            let hint = Hint::Option;
+            self.0.deserialize_option(Wrap { visitor, hint })
+        }
+
+        fn deserialize_unit_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Any;
+            self.0.deserialize_unit_struct(name, Wrap { visitor, hint })
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Keep;
+            self.0
+                .deserialize_newtype_struct(name, Wrap { visitor, hint })
+        }
+
+        fn deserialize_tuple<V: Visitor<'de>>(
+            self,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Any;
+            self.0.deserialize_tuple(len, Wrap { visitor, hint })
+        }
+
+        fn deserialize_tuple_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Any;
+            self.0
+                .deserialize_tuple_struct(name, len, Wrap { visitor, hint })
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Any;
+            self.0
+                .deserialize_struct(name, fields, Wrap { visitor, hint })
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let hint = Hint::Enum(name, variants);
+            self.0
+                .deserialize_enum(name, variants, Wrap { visitor, hint })
+        }
+
+        fn is_human_readable(&self) -> bool {
+            self.0.is_human_readable()
+        }
+    }
+
+    macro_rules! forward_visit {
+        ($($method:ident($ty:ty))*) => {
+            $(
+                fn $method<E: serde::de::Error>(self, value: $ty) -> Result<V::Value, E> {
+                    self.visitor.$method(value)
+                }
+            )*
+        };
+    }
+
+    impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<V> {
+        type Value = V::Value;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.visitor.expecting(f)
+        }
+
+        forward_visit! {
+            visit_bool(bool) visit_i8(i8) visit_i16(i16) visit_i32(i32) visit_i64(i64)
+            visit_i128(i128) visit_u8(u8) visit_u16(u16) visit_u32(u32) visit_u64(u64)
+            visit_u128(u128) visit_f32(f32) visit_f64(f64) visit_char(char) visit_str(&str)
+            visit_borrowed_str(&'de str) visit_string(String) visit_bytes(&[u8])
+            visit_borrowed_bytes(&'de [u8]) visit_byte_buf(Vec<u8>)
+        }
+
+        fn visit_none<E: serde::de::Error>(self) -> Result<V::Value, E> {
+            self.visitor.visit_none()
+        }
+
+        fn visit_unit<E: serde::de::Error>(self) -> Result<V::Value, E> {
+            self.visitor.visit_unit()
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, de: D) -> Result<V::Value, D::Error> {
+            self.visitor.visit_some(Untagged(de))
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<V::Value, D::Error> {
+            // past the tag, the item is read again as the visitor asked for
+            match self.hint {
+                Hint::Keep => self.visitor.visit_newtype_struct(Untagged(de)),
+                Hint::Any => de.deserialize_any(self),
+                Hint::Option => de.deserialize_option(self),
+                Hint::Enum(name, variants) => de.deserialize_enum(name, variants, self),
+            }
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+            self.visitor.visit_seq(Untagged(seq))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+            self.visitor.visit_map(Untagged(map))
+        }
+
+        fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+            self.visitor.visit_enum(Untagged(data))
+        }
+    }
+
+    impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for Untagged<T> {
+        type Value = T::Value;
+
+        fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<T::Value, D::Error> {
+            self.0.deserialize(Untagged(de))
+        }
+    }
+
+    impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for Untagged<A> {
+        type Error = A::Error;
+
+        fn next_element_seed<T: DeserializeSeed<'de>>(
+            &mut self,
+            seed: T,
+        ) -> Result<Option<T::Value>, A::Error> {
+            self.0.next_element_seed(Untagged(seed))
+        }
+
+        fn size_hint(&self) -> Option<usize> {
+            self.0.size_hint()
+        }
+    }
+
+    impl<'de, A: MapAccess<'de>> MapAccess<'de> for Untagged<A> {
+        type Error = A::Error;
+
+        fn next_key_seed<K: DeserializeSeed<'de>>(
+            &mut self,
+            seed: K,
+        ) -> Result<Option<K::Value>, A::Error> {
+            self.0.next_key_seed(Untagged(seed))
+        }
+
+        fn next_value_seed<V: DeserializeSeed<'de>>(
+            &mut self,
+            seed: V,
+        ) -> Result<V::Value, A::Error> {
+            self.0.next_value_seed(Untagged(seed))
+        }
+
+        fn size_hint(&self) -> Option<usize> {
+            self.0.size_hint()
+        }
+    }
+
+    impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for Untagged<A> {
+        type Error = A::Error;
+        type Variant = Untagged<A::Variant>;
+
+        fn variant_seed<V: DeserializeSeed<'de>>(
+            self,
+            seed: V,
+        ) -> Result<(V::Value, Self::Variant), A::Error> {
+            let (value, variant) = self.0.variant_seed(Untagged(seed))?;
+            Ok((value, Untagged(variant)))
+        }
+    }
+
+    impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for Untagged<A> {
+        type Error = A::Error;
+
+        fn unit_variant(self) -> Result<(), A::Error> {
+            self.0.unit_variant()
+        }
+
+        fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+            self,
+            seed: T,
+        ) -> Result<T::Value, A::Error> {
+            self.0.newtype_variant_seed(Untagged(seed))
+        }
+
+        fn tuple_variant<V: Visitor<'de>>(
+            self,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, A::Error> {
+            let hint = Hint::Any;
+            self.0.tuple_variant(len, Wrap { visitor, hint })
+        }
+
+        fn struct_variant<V: Visitor<'de>>(
+            self,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, A::Error> {
+            let hint = Hint::Any;
+            self.0.struct_variant(fields, Wrap { visitor, hint })
+        }
+    }
+}
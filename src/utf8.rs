@@ -0,0 +1,68 @@
+//! Repair of CBOR text strings holding invalid UTF-8.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use crate::scan::read_head;
+
+/// Append the header of a definite text string of `len` bytes to `out`.
+fn write_text_head(out: &mut Vec<u8>, len: usize) {
+    let len = len as u64;
+    match len {
+        0..=23 => out.push(0x60 | len as u8),
+        24..=0xff => out.extend_from_slice(&[0x78, len as u8]),
+        0x100..=0xffff => {
+            out.push(0x79);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0x7a);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(0x7b);
+            out.extend_from_slice(&len.to_be_bytes());
+        }
+    }
+}
+
+/// Replace the invalid UTF-8 sequences of the text strings of `body` with U+FFFD.
+///
+/// Chunks of indefinite text strings are repaired one by one. Malformed data is left as is from
+/// the first offending byte, for the decoder to report. The body is only copied when a text
+/// string needs repair.
+pub(crate) fn repair_utf8(body: &[u8]) -> Cow<'_, [u8]> {
+    let mut repaired: Option<Vec<u8>> = None;
+    let mut copied = 0;
+    let mut pos = 0;
+
+    while let Ok(Some(head)) = read_head(body, pos) {
+        let start = pos + head.len;
+        let len = match (head.major, head.arg) {
+            (2, Some(n)) | (3, Some(n)) => match usize::try_from(n) {
+                Ok(n) if n <= body.len() - start => n,
+                _ => break,
+            },
+            _ => 0,
+        };
+
+        let text = &body[start..start + len];
+        if head.major == 3 && std::str::from_utf8(text).is_err() {
+            let out = repaired.get_or_insert_with(|| Vec::with_capacity(body.len() + 8));
+            out.extend_from_slice(&body[copied..pos]);
+            let text = String::from_utf8_lossy(text);
+            write_text_head(out, text.len());
+            out.extend_from_slice(text.as_bytes());
+            copied = start + len;
+        }
+        pos = start + len;
+    }
+
+    match repaired {
+        Some(mut out) => {
+            out.extend_from_slice(&body[copied..]);
+            Cow::Owned(out)
+        }
+        None => Cow::Borrowed(body),
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::{Deref, DerefMut};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde_cbor::Value;
+
+use crate::scan::{read_head, Head};
+use crate::untagged;
+use crate::{extraction_error, is_cbor_content_type, CborBody, CborConfig, CborPayloadError};
+
+/// Set of wire versions of a payload, decoded by [`CborVersioned`](struct.CborVersioned.html).
+///
+/// The version is the number of a tag wrapping the payload, or else the integer stored under
+/// [`VERSION_KEY`](#associatedconstant.VERSION_KEY) in the payload map.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborPayloadError, CborVersions};
+/// use serde_cbor::{value::from_value, Value};
+///
+/// #[derive(serde::Deserialize)]
+/// struct OrderV1 {
+///     item: String,
+/// }
+///
+/// #[derive(serde::Deserialize)]
+/// struct OrderV2 {
+///     items: Vec<String>,
+/// }
+///
+/// enum Order {
+///     V1(OrderV1),
+///     V2(OrderV2),
+/// }
+///
+/// impl CborVersions for Order {
+///     fn decode(version: u64, payload: Value) -> Result<Self, CborPayloadError> {
+///         match version {
+///             1 => Ok(Order::V1(from_value(payload)?)),
+///             2 => Ok(Order::V2(from_value(payload)?)),
+///             _ => Err(CborPayloadError::UnsupportedVersion(Some(version))),
+///         }
+///     }
+/// }
+/// ```
+pub trait CborVersions: Sized {
+    /// Key of the payload map holding the version, when the payload is not tagged
+    const VERSION_KEY: &'static str = "version";
+
+    /// Decode the `payload` of the given `version`
+    ///
+    /// Tagged payloads are passed without their tag. The self-described CBOR tag 55799 which may
+    /// prefix a payload is skipped, and never taken for a version.
+    fn decode(version: u64, payload: Value) -> Result<Self, CborPayloadError>;
+}
+
+/// Tag marking a payload as self-described CBOR, which does not stand for a version.
+const SELF_DESCRIBE_TAG: u64 = 55799;
+
+/// Read the version of `body` and decode it with `T`.
+fn decode_versioned<T: CborVersions>(mut body: &[u8]) -> Result<T, CborPayloadError> {
+    while let Ok(Some(Head {
+        major: 6,
+        arg: Some(SELF_DESCRIBE_TAG),
+        len,
+    })) = read_head(body, 0)
+    {
+        body = &body[len..];
+    }
+
+    // tags are read from the encoding, `Value` does not keep them
+    if let Ok(Some(Head {
+        major: 6,
+        arg: Some(tag),
+        len,
+    })) = read_head(body, 0)
+    {
+        return T::decode(tag, untagged::from_slice(&body[len..])?);
+    }
+
+    let payload: Value = serde_cbor::from_slice(body)?;
+    let version = match &payload {
+        Value::Map(map) => map.get(&Value::Text(T::VERSION_KEY.to_owned())),
+        _ => None,
+    };
+    match version {
+        Some(&Value::Integer(version)) if version >= 0 && version <= u64::MAX as i128 => {
+            T::decode(version as u64, payload)
+        }
+        _ => Err(CborPayloadError::UnsupportedVersion(None)),
+    }
+}
+
+/// Extractor reading the version of the payload and decoding it with the matching
+/// [`CborVersions`](trait.CborVersions.html) implementation.
+///
+/// # Example
+/// ```
+/// use actix_cbor::{CborPayloadError, CborVersioned, CborVersions};
+/// use serde_cbor::{value::from_value, Value};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Reading {
+///     value: f64,
+/// }
+///
+/// enum Readings {
+///     V1(Reading),
+///     V2(Vec<Reading>),
+/// }
+///
+/// impl CborVersions for Readings {
+///     fn decode(version: u64, payload: Value) -> Result<Self, CborPayloadError> {
+///         match version {
+///             1 => Ok(Readings::V1(from_value(payload)?)),
+///             2 => Ok(Readings::V2(from_value(payload)?)),
+///             _ => Err(CborPayloadError::UnsupportedVersion(Some(version))),
+///         }
+///     }
+/// }
+///
+/// async fn ingest(readings: CborVersioned<Readings>) -> String {
+///     match readings.into_inner() {
+///         Readings::V1(_) => "1 reading".to_owned(),
+///         Readings::V2(readings) => format!("{} readings", readings.len()),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CborVersioned<T>(pub T);
+
+impl<T> CborVersioned<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for CborVersioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for CborVersioned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> FromRequest for CborVersioned<T>
+where
+    T: CborVersions + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req2 = req.clone();
+        let config = CborConfig::from_req(req).into_owned();
+
+        let ctype = config.content_type.clone();
+
+        CborBody::with_decoder(
+            req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            decode_versioned::<T>,
+        )
+        .limit(config.limit_for(req))
+        .map(move |res| match res {
+            Err(e) => Err(extraction_error(&config, e, &req2)),
+            Ok(data) => Ok(CborVersioned(data)),
+        })
+        .boxed_local()
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::ops::Deref;
+
+use actix_web::{dev::Payload, web::Bytes, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+
+use crate::scan::{scan_item, Scan, DEFAULT_MAX_DEPTH};
+use crate::{
+    extraction_error, is_cbor_content_type, CborBody, CborConfig, CborPayloadError, HttpRequestExt,
+};
+
+/// Check that `body` is exactly one well-formed CBOR item, nesting at most `max_depth`
+/// containers deep.
+fn check_well_formed(body: &[u8], max_depth: usize) -> Result<(), CborPayloadError> {
+    if body.is_empty() {
+        return Err(CborPayloadError::NoBody);
+    }
+    match scan_item(body, max_depth) {
+        Ok(Scan::Complete(len)) if len == body.len() => Ok(()),
+        // trailing bytes after the item
+        Ok(Scan::Complete(len)) => Err(CborPayloadError::Malformed(len)),
+        Ok(Scan::Incomplete) => Err(CborPayloadError::Malformed(body.len())),
+        Err(e) => Err(CborPayloadError::Malformed(e.offset)),
+    }
+}
+
+/// Extractor checking that a body is a single well-formed CBOR item, without decoding it, and
+/// keeping its raw bytes.
+///
+/// Items may nest 128 containers deep. Their content is not checked, e.g. text strings may hold
+/// invalid UTF-8, so pass-through routes can reject garbage early and forward the payload
+/// untouched. Other failures are those of [`Cbor`](struct.Cbor.html), and bodies which are not
+/// well-formed fail with `CborPayloadError::Malformed`.
+///
+/// # Example
+/// ```
+/// use actix_cbor::CborWellFormed;
+/// use actix_web::HttpResponse;
+///
+/// async fn forward(body: CborWellFormed) -> HttpResponse {
+///     // send `body.into_inner()` upstream
+///     HttpResponse::Accepted().finish()
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct CborWellFormed(pub Bytes);
+
+impl CborWellFormed {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for CborWellFormed {
+    type Target = Bytes;
+
+    fn deref(&self) -> &Bytes {
+        &self.0
+    }
+}
+
+impl fmt::Debug for CborWellFormed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CborWellFormed: {:?}", self.0)
+    }
+}
+
+impl FromRequest for CborWellFormed {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = CborConfig::from_req(&req).into_owned();
+        let ctype = config.content_type.clone();
+        let body = CborBody::with_decoder(
+            &req,
+            payload,
+            &config,
+            |mime| is_cbor_content_type(mime, ctype.as_ref()),
+            |body| check_well_formed(body, DEFAULT_MAX_DEPTH),
+        )
+        .tee(&req)
+        .limit(config.limit_for(&req));
+
+        Box::pin(async move {
+            body.await.map_err(|e| extraction_error(&config, e, &req))?;
+            Ok(CborWellFormed(req.cbor_raw_body().unwrap_or_default()))
+        })
+    }
+}
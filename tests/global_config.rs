@@ -0,0 +1,49 @@
+//! The global default config is set once per process, so it is tested in its own binary.
+
+use actix_cbor::{Cbor, CborConfig};
+use actix_web::http::header;
+use actix_web::test::TestRequest;
+use actix_web::FromRequest;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct MyObject {
+    name: String,
+    number: i32,
+}
+
+impl Default for MyObject {
+    fn default() -> Self {
+        Self {
+            name: "test".to_owned(),
+            number: 7,
+        }
+    }
+}
+
+fn get_test_bytes() -> Vec<u8> {
+    serde_cbor::to_vec(&MyObject::default()).unwrap()
+}
+
+#[actix_rt::test]
+async fn test_global_default_config() {
+    let global = CborConfig::default().content_type_raw(|mime| mime == "application/x-global");
+    assert!(CborConfig::set_global_default(global).is_ok());
+    assert!(CborConfig::set_global_default(CborConfig::default()).is_err());
+
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/x-global"))
+        .set_payload(get_test_bytes())
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await.unwrap();
+    assert_eq!(s.into_inner(), MyObject::default());
+
+    // app data configs inherit the global settings they leave unset
+    let (req, mut pl) = TestRequest::default()
+        .insert_header((header::CONTENT_TYPE, "application/x-global"))
+        .set_payload(get_test_bytes())
+        .app_data(CborConfig::default().limit(10))
+        .to_http_parts();
+    let s = Cbor::<MyObject>::from_request(&req, &mut pl).await;
+    assert!(format!("{}", s.err().unwrap()).contains("Cbor payload size is bigger than allowed"));
+}